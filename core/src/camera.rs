@@ -0,0 +1,163 @@
+//! The zoom and pan `GridView` applies when drawing the grid, and the
+//! mapping from window positions back to cells under that transform.
+//!
+//! Sizes are measured against the "fit" cell size, the size that stretches
+//! the whole grid over the window, so a camera stays sensible when the
+//! window is resized.
+
+/// Zooming stops when a cell reaches this many pixels across.
+const MAX_CELL_PIXELS: f64 = 256.0;
+
+/// The furthest out zooming goes, as a fraction of the fit size.
+const MIN_ZOOM: f64 = 0.25;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Camera {
+    /// Cell size as a multiple of the fit size.
+    zoom: f64,
+    /// Where the grid's top left corner is drawn, in window pixels.
+    pan: [f64; 2],
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera {
+    /// A camera showing the whole grid stretched over the window.
+    pub fn new() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: [0.0, 0.0],
+        }
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    pub fn pan(&self) -> [f64; 2] {
+        self.pan
+    }
+
+    /// The width and height of a cell on screen, given the fit size.
+    pub fn cell_size(&self, fit: [f64; 2]) -> [f64; 2] {
+        [fit[0] * self.zoom, fit[1] * self.zoom]
+    }
+
+    /// Goes back to showing the whole grid.
+    pub fn fit(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn pan_by(&mut self, delta: [f64; 2]) {
+        self.pan = [self.pan[0] + delta[0], self.pan[1] + delta[1]];
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the point of the grid under
+    /// `cursor` where it is on screen.
+    pub fn zoom_at(&mut self, fit: [f64; 2], cursor: [f64; 2], factor: f64) {
+        let max_zoom = (MAX_CELL_PIXELS / fit[0].min(fit[1])).max(1.0);
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, max_zoom);
+        let scale = zoom / self.zoom;
+        self.pan = [
+            cursor[0] - (cursor[0] - self.pan[0]) * scale,
+            cursor[1] - (cursor[1] - self.pan[1]) * scale,
+        ];
+        self.zoom = zoom;
+    }
+
+    /// The `(row, col)` of the cell drawn at `pos`, or that would be drawn
+    /// there if the grid went on forever.
+    pub fn cell_under(&self, fit: [f64; 2], pos: [f64; 2]) -> (i64, i64) {
+        let [width, height] = self.cell_size(fit);
+        let col = ((pos[0] - self.pan[0]) / width).floor();
+        let row = ((pos[1] - self.pan[1]) / height).floor();
+        (row as i64, col as i64)
+    }
+
+    /// The `(row, col)` of the cell drawn at `pos`, if it is inside a
+    /// `rows x cols` grid.
+    pub fn cell_at(
+        &self,
+        fit: [f64; 2],
+        pos: [f64; 2],
+        rows: usize,
+        cols: usize,
+    ) -> Option<(usize, usize)> {
+        let (row, col) = self.cell_under(fit, pos);
+        let inside = |n: i64, len: usize| n >= 0 && n < len as i64;
+        if inside(row, rows) && inside(col, cols) {
+            Some((row as usize, col as usize))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod camera_tests {
+    use super::*;
+
+    /// A 10x20 grid in a 200x100 window.
+    const FIT: [f64; 2] = [10.0, 10.0];
+    const ROWS: usize = 10;
+    const COLS: usize = 20;
+
+    #[test]
+    fn maps_positions_to_cells_when_fitted() {
+        let camera = Camera::new();
+        assert_eq!(Some((0, 0)), camera.cell_at(FIT, [0.0, 0.0], ROWS, COLS));
+        assert_eq!(Some((3, 7)), camera.cell_at(FIT, [75.0, 39.9], ROWS, COLS));
+        assert_eq!(
+            Some((9, 19)),
+            camera.cell_at(FIT, [199.9, 99.9], ROWS, COLS)
+        );
+        assert_eq!(None, camera.cell_at(FIT, [200.0, 50.0], ROWS, COLS));
+        assert_eq!(None, camera.cell_at(FIT, [-0.1, 50.0], ROWS, COLS));
+        assert_eq!((5, -1), camera.cell_under(FIT, [-0.1, 50.0]));
+    }
+
+    #[test]
+    fn zooming_keeps_the_cell_under_the_cursor() {
+        let mut camera = Camera::new();
+        let cursor = [75.0, 35.0];
+        let before = camera.cell_at(FIT, cursor, ROWS, COLS);
+        camera.zoom_at(FIT, cursor, 4.0);
+        assert_eq!([40.0, 40.0], camera.cell_size(FIT));
+        assert_eq!(before, camera.cell_at(FIT, cursor, ROWS, COLS));
+        // The cursor is now 20 pixels into a 40 pixel cell.
+        assert_eq!(Some((3, 7)), camera.cell_at(FIT, [94.0, 54.0], ROWS, COLS));
+        assert_eq!(Some((2, 6)), camera.cell_at(FIT, [54.0, 14.0], ROWS, COLS));
+    }
+
+    #[test]
+    fn panning_moves_the_grid_under_the_mouse() {
+        let mut camera = Camera::new();
+        camera.pan_by([-30.0, 10.0]);
+        assert_eq!(Some((0, 3)), camera.cell_at(FIT, [0.0, 10.0], ROWS, COLS));
+        assert_eq!(None, camera.cell_at(FIT, [0.0, 5.0], ROWS, COLS));
+    }
+
+    #[test]
+    fn zoom_is_limited_and_fit_resets_it() {
+        let mut camera = Camera::new();
+        camera.zoom_at(FIT, [0.0, 0.0], 1000.0);
+        assert_eq!([MAX_CELL_PIXELS, MAX_CELL_PIXELS], camera.cell_size(FIT));
+        camera.zoom_at(FIT, [0.0, 0.0], 1e-6);
+        assert_eq!(MIN_ZOOM, camera.zoom());
+        camera.pan_by([5.0, 5.0]);
+        camera.fit();
+        assert_eq!(Camera::new(), camera);
+    }
+
+    #[test]
+    fn tiny_cells_can_still_zoom_past_the_fit_size() {
+        let mut camera = Camera::new();
+        let fit = [0.48, 0.5];
+        camera.zoom_at(fit, [0.0, 0.0], 100.0);
+        assert_eq!(100.0, camera.zoom());
+    }
+}
@@ -0,0 +1,114 @@
+//! Command line pieces both apps share: the errors an option can have and
+//! the parsers its value goes through.
+
+use crate::model::MAX_UPS;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A problem with one `--option`, named as written after the `--`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum OptionError {
+    UnknownOption(String),
+    MissingValue(&'static str),
+    UnexpectedValue(&'static str),
+    InvalidValue {
+        option: &'static str,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            Self::MissingValue(option) => write!(f, "option '--{}' needs a value", option),
+            Self::UnexpectedValue(option) => {
+                write!(f, "option '--{}' does not take a value", option)
+            }
+            Self::InvalidValue {
+                option,
+                value,
+                reason,
+            } => write!(
+                f,
+                "invalid value '{}' for '--{}': {}",
+                value, option, reason
+            ),
+        }
+    }
+}
+
+impl Error for OptionError {}
+
+/// Parses `value` with `FromStr`, giving the parser's message as the reason
+/// when it fails.
+pub fn parse_value<T>(option: &'static str, value: &str) -> Result<T, OptionError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e: T::Err| invalid(option, value, &e.to_string()))
+}
+
+pub fn invalid(option: &'static str, value: &str, reason: &str) -> OptionError {
+    OptionError::InvalidValue {
+        option,
+        value: value.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Parses a count that has to be at least one, such as a grid size.
+pub fn parse_positive<T>(option: &'static str, value: &str) -> Result<T, OptionError>
+where
+    T: FromStr + Default + PartialEq,
+    T::Err: fmt::Display,
+{
+    let n: T = parse_value(option, value)?;
+    if n == T::default() {
+        return Err(invalid(option, value, "must be at least 1"));
+    }
+    Ok(n)
+}
+
+/// Parses an update rate, which has to be at least one. Rates faster than
+/// `MAX_UPS` are slowed to it.
+pub fn parse_ups(option: &'static str, value: &str) -> Result<u64, OptionError> {
+    parse_positive(option, value).map(|ups: u64| ups.min(MAX_UPS))
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+
+    #[test]
+    fn values_that_do_not_parse_give_the_parser_s_reason() {
+        assert_eq!(Ok(12u32), parse_value("rows", "12"));
+        assert_eq!(
+            "invalid value 'ten' for '--rows': invalid digit found in string",
+            parse_value::<u32>("rows", "ten").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn positive_values_must_be_at_least_one() {
+        assert_eq!(Ok(3usize), parse_positive("scale", "3"));
+        assert_eq!(
+            Err(invalid("scale", "0", "must be at least 1")),
+            parse_positive::<usize>("scale", "0")
+        );
+        assert!(parse_positive::<usize>("scale", "-1").is_err());
+    }
+
+    #[test]
+    fn update_rates_are_clamped_to_the_fastest_supported() {
+        assert_eq!(Ok(12), parse_ups("ups", "12"));
+        assert_eq!(Ok(MAX_UPS), parse_ups("ups", "5000"));
+        assert_eq!(Ok(MAX_UPS), parse_ups("ups", &u64::MAX.to_string()));
+        assert!(parse_ups("ups", "0").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fmt;
+
+const NAMED_COLORS: [(&str, u32); 9] = [
+    ("black", 0x000000),
+    ("white", 0xffffff),
+    ("grey", 0x808080),
+    ("red", 0xff0000),
+    ("green", 0x00ff00),
+    ("blue", 0x0000ff),
+    ("yellow", 0xffff00),
+    ("cyan", 0x00ffff),
+    ("magenta", 0xff00ff),
+];
+
+/// Turns a `0xRRGGBB` colour into an opaque RGBA colour.
+pub fn hex_to_rgba(hex_color: u32) -> [f32; 4] {
+    let channel = |shift: u32| ((hex_color >> shift) & 0xFF) as f32 / 255.0;
+    [channel(16), channel(8), channel(0), 1.0]
+}
+
+/// Writes a colour as `#rrggbb`, or `#rrggbbaa` when it is not opaque.
+pub fn rgba_to_hex(color: [f32; 4]) -> String {
+    let [r, g, b, a] = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+    if a == 0xFF {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "'{}' is not a colour; use #rrggbb, #rrggbbaa or one of {}",
+            self.0,
+            NAMED_COLORS.map(|(name, _)| name).join(", ")
+        )
+    }
+}
+
+impl Error for ParseColorError {}
+
+/// Reads a colour written as `#rrggbb` or `#rrggbbaa` (the `#` is
+/// optional) or as a simple colour name such as `magenta`.
+pub fn parse_color(s: &str) -> Result<[f32; 4], ParseColorError> {
+    let s = s.trim();
+    let error = || ParseColorError(s.to_string());
+    if let Some(&(_, hex)) = NAMED_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(s))
+    {
+        return Ok(hex_to_rgba(hex));
+    }
+
+    let digits = s.strip_prefix('#').unwrap_or(s);
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error());
+    }
+    let value = u32::from_str_radix(digits, 16).map_err(|_| error())?;
+    match digits.len() {
+        6 => Ok(hex_to_rgba(value)),
+        8 => {
+            let mut color = hex_to_rgba(value >> 8);
+            color[3] = (value & 0xFF) as f32 / 255.0;
+            Ok(color)
+        }
+        _ => Err(error()),
+    }
+}
+
+#[cfg(test)]
+mod color_tests {
+    use super::*;
+
+    #[test]
+    fn hex_colours_become_opaque_rgba() {
+        assert_eq!([1.0, 0.0, 1.0, 1.0], hex_to_rgba(0xff00ff));
+        assert_eq!([0.0, 0.0, 0.0, 1.0], hex_to_rgba(0x000000));
+    }
+
+    #[test]
+    fn parses_hex_and_named_colours() {
+        assert_eq!(Ok([1.0, 0.0, 1.0, 1.0]), parse_color("#ff00ff"));
+        assert_eq!(Ok([1.0, 0.0, 1.0, 1.0]), parse_color("FF00FF"));
+        assert_eq!(Ok([1.0, 0.0, 1.0, 1.0]), parse_color("Magenta"));
+        assert_eq!(Ok([0.0, 1.0, 0.0, 0.0]), parse_color("#00ff0000"));
+    }
+
+    #[test]
+    fn rejects_malformed_colours() {
+        for s in ["", "#", "#fff", "#ff00fg", "+ff00ff", "mauve", "#ff00ff0"] {
+            assert_eq!(Err(ParseColorError(s.to_string())), parse_color(s), "{}", s);
+        }
+        assert_eq!(
+            "'mauve' is not a colour; use #rrggbb, #rrggbbaa or one of \
+             black, white, grey, red, green, blue, yellow, cyan, magenta",
+            parse_color("mauve").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn round_trips_through_hex() {
+        for s in ["#4ca8bf", "#bfa84c", "#00ff0080"] {
+            assert_eq!(s, rgba_to_hex(parse_color(s).unwrap()));
+        }
+    }
+}
@@ -1,49 +1,96 @@
 
 extern crate piston_window;
-extern crate image as im;
 
+use crate::camera::Camera;
+use crate::hud;
+use crate::model::{Controller, GridViewModel, PaintButton, MAX_UPS, MIN_UPS};
+use crate::record::{Recorder, RecordingFormat};
+use crate::render;
+use crate::selection::{Edit, Region, Transform};
+use ::image::{ImageFormat, Rgba, RgbaImage};
 use piston_window::draw_state::DrawState;
 use piston_window::*;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-pub trait Controller {
-    fn model(&self) -> GridViewModel;
-    fn update(&mut self);
-    fn mouse_click(&mut self, row: usize, col: usize);
-    fn clear(&mut self);
+/// The rate the speed up key moves to: the next power of two above `ups`,
+/// up to `MAX_UPS`. A rate already past that is kept.
+fn faster(ups: u64) -> u64 {
+    if ups >= MAX_UPS {
+        return ups;
+    }
+    (ups + 1).next_power_of_two().min(MAX_UPS)
 }
 
-pub struct Entity {
-    color: [f32; 4],
-    row: usize,
-    col: usize,
+/// The rate the slow down key moves to: the next power of two below `ups`.
+fn slower(ups: u64) -> u64 {
+    let below = if ups.is_power_of_two() {
+        ups / 2
+    } else {
+        ups.checked_next_power_of_two().map_or(1 << 63, |above| above / 2)
+    };
+    below.max(MIN_UPS)
 }
 
-impl Entity {
-    pub fn new(color: [f32; 4], row: usize, col: usize) -> Self {
-        Self { color, row, col }
+/// The cells on a straight line from `from` to `to`, both included, by
+/// Bresenham's algorithm.
+fn line(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (mut row, mut col) = from;
+    let d_row = -(to.0 - row).abs();
+    let d_col = (to.1 - col).abs();
+    let step_row = if row < to.0 { 1 } else { -1 };
+    let step_col = if col < to.1 { 1 } else { -1 };
+    let mut error = d_col + d_row;
+    let mut cells = vec![(row, col)];
+    while (row, col) != to {
+        let double = 2 * error;
+        if double >= d_row {
+            error += d_row;
+            col += step_col;
+        }
+        if double <= d_col {
+            error += d_col;
+            row += step_row;
+        }
+        cells.push((row, col));
     }
+    cells
 }
 
-pub struct GridViewModel {
-    rows: usize,
-    cols: usize,
-    entities: Vec<Entity>,
-    background_color: [f32; 4],
+/// Counts events to report how often they actually happen, which can fall
+/// short of the rate asked for when the automaton is slow to step.
+struct RateMeter {
+    count: u32,
+    since: Instant,
+    rate: f64,
 }
 
-impl GridViewModel {
-    pub fn new(
-        rows: usize,
-        cols: usize,
-        entities: Vec<Entity>,
-        background_color: [f32; 4],
-    ) -> Self {
+impl RateMeter {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    fn new() -> Self {
         Self {
-            rows,
-            cols,
-            entities,
-            background_color,
+            count: 0,
+            since: Instant::now(),
+            rate: 0.0,
+        }
+    }
+
+    fn tick(&mut self) {
+        self.count += 1;
+    }
+
+    /// Events per second over the last whole second.
+    fn rate(&mut self) -> f64 {
+        let elapsed = self.since.elapsed();
+        if elapsed >= Self::WINDOW {
+            self.rate = self.count as f64 / elapsed.as_secs_f64();
+            self.count = 0;
+            self.since = Instant::now();
         }
+        self.rate
     }
 }
 
@@ -70,6 +117,56 @@ where
     events: Events,
     controller: C,
     play_state: PlayState,
+    title: String,
+    /// The outcome of the last screenshot, recording or clipboard saved,
+    /// shown after the title.
+    message: Option<String>,
+    ups: u64,
+    /// How many generations the multi-step key advances.
+    step_size: u64,
+    output_dir: PathBuf,
+    image_scale: u32,
+    recording_format: RecordingFormat,
+    recorder: Option<Recorder>,
+    /// The grid as last drawn, one pixel per cell.
+    frame: Option<RgbaImage>,
+    show_hud: bool,
+    updates: RateMeter,
+    frames: RateMeter,
+    camera: Camera,
+    /// Whether the middle button is held down to drag the grid around.
+    panning: bool,
+    /// The button painting and the last cell it was over, which may be
+    /// off the grid.
+    painting: Option<(PaintButton, (i64, i64))>,
+    /// Where the mouse was last seen in the window.
+    cursor_pos: Option<[f64; 2]>,
+    shift: bool,
+    ctrl: bool,
+    selection: Option<Region>,
+    /// The corner a shift drag started from while it is making a selection.
+    selecting: Option<(usize, usize)>,
+    /// Whether the clipboard follows the cursor, waiting to be pasted.
+    pasting: bool,
+}
+
+/// A file name in `dir` that sorts by when it was made, such as
+/// `recording-1700000000123.gif`.
+fn timestamped(dir: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    dir.join(format!("{}-{}.{}", prefix, millis, extension))
+}
+
+/// Saves a recording, saying how it went.
+fn finish_recording(recorder: Recorder) -> Result<String, String> {
+    let path = recorder.path().to_path_buf();
+    match recorder.finish() {
+        Ok(frames) => Ok(format!("saved {} frames to {}", frames, path.display())),
+        Err(e) => Err(format!("couldn't save {}: {}", path.display(), e)),
+    }
 }
 
 impl<C> GridView<C>
@@ -86,51 +183,94 @@ where
             .max_fps(fps)
             .ups(ups);
         
-        Self {
+        let mut view = Self {
             window,
             events,
             controller,
             play_state: PlayState::Running,
+            title: title.to_string(),
+            message: None,
+            ups,
+            step_size: 10,
+            output_dir: PathBuf::from("."),
+            image_scale: 1,
+            recording_format: RecordingFormat::Gif,
+            recorder: None,
+            frame: None,
+            show_hud: false,
+            updates: RateMeter::new(),
+            frames: RateMeter::new(),
+            camera: Camera::new(),
+            panning: false,
+            painting: None,
+            cursor_pos: None,
+            shift: false,
+            ctrl: false,
+            selection: None,
+            selecting: None,
+            pasting: false,
+        };
+        view.show_status();
+        view
+    }
+
+    /// Where recordings, screenshots and the clipboard are saved. Defaults
+    /// to the working directory.
+    pub fn with_output_dir<P: Into<PathBuf>>(self, output_dir: P) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            ..self
         }
     }
 
-    fn cell_height(&self) -> f64 {
-        let size = self.window.size();
-        let model = self.controller.model();
-        size.height / model.rows as f64
+    /// How many pixels across each cell is in saved images.
+    pub fn with_image_scale(self, image_scale: u32) -> Self {
+        assert!(image_scale > 0, "cells must be at least one pixel across");
+        Self {
+            image_scale,
+            ..self
+        }
     }
 
-    fn cell_width(&self) -> f64 {
+    /// How many generations Return advances.
+    pub fn with_step_size(self, step_size: u64) -> Self {
+        Self {
+            step_size,
+            ..self
+        }
+    }
+
+    /// Whether the status overlay starts out shown. Tab toggles it.
+    pub fn with_hud(self, show_hud: bool) -> Self {
+        Self { show_hud, ..self }
+    }
+
+    pub fn with_recording_format(self, recording_format: RecordingFormat) -> Self {
+        Self {
+            recording_format,
+            ..self
+        }
+    }
+
+    /// The cell size that stretches the whole grid over the window.
+    fn fit_cell_size(&self, model: &GridViewModel) -> [f64; 2] {
         let size = self.window.size();
-        let model = self.controller.model();
-        size.width / model.cols as f64
+        [size.width / model.cols() as f64, size.height / model.rows() as f64]
     }
 
-    fn to_u8_pixel(pix: &[f32; 4]) -> im::Rgba<u8> {
-        const MAX_VAL: f32 = 255.0;
-        let [r, g, b, a] = pix;
-        let normalize = |c|  {
-            (c * MAX_VAL) as u8
-        };
-        im::Rgba([normalize(r), normalize(g), normalize(b), normalize(a)])
+    /// The cell under `pos` in the window, allowing for zoom and pan.
+    fn cell_at(&self, pos: [f64; 2]) -> Option<(usize, usize)> {
+        let model = self.controller.model();
+        let fit = self.fit_cell_size(&model);
+        self.camera.cell_at(fit, pos, model.rows(), model.cols())
     }
 
     fn render_view_model(&mut self, e: &Event) {
-        let width = self.cell_width();
-        let height = self.cell_height();
+        const OUTSIDE_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
         let model = self.controller.model();
-
-        let mut img = im::ImageBuffer::from_pixel(
-            model.cols as u32,
-            model.rows as u32,
-            Self::to_u8_pixel(&model.background_color)
-        );
-        
-        for entity in model.entities.iter() {
-            let r = entity.row as u32;
-            let c = entity.col as u32;
-            img.put_pixel(c, r, Self::to_u8_pixel(&entity.color));
-        }
+        let [width, height] = self.camera.cell_size(self.fit_cell_size(&model));
+        let [left, top] = self.camera.pan();
+        let img = render::render(&model, 1);
         let mut texture_context = TextureContext {
             factory: self.window.factory.clone(),
             encoder: self.window.factory.create_command_buffer().into()
@@ -141,10 +281,100 @@ where
                 &TextureSettings::new().mag(Filter::Nearest)
             ).expect("Couldn't build texture");
         self.window.draw_2d(e, |cxt, g, _device| {
-            // clear(model.background_color, g);
-            image(&texture, cxt.transform.scale(width, height), g);
+            clear(OUTSIDE_COLOR, g);
+            let transform = cxt.transform
+                .trans(left, top)
+                .scale(width, height);
+            image(&texture, transform, g);
         });
-        
+        self.frame = Some(img);
+    }
+
+    /// Outlines the selection and draws the clipboard, faded, where it would
+    /// be pasted.
+    fn render_selection(&mut self, e: &Event) {
+        const SELECTION_COLOR: [f32; 4] = [0.3, 0.6, 1.0, 1.0];
+        const PASTE_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+        const BORDER_RADIUS: f64 = 1.0;
+        const PREVIEW_ALPHA: f32 = 0.6;
+        let model = self.controller.model();
+        let [width, height] = self.camera.cell_size(self.fit_cell_size(&model));
+        let [left, top] = self.camera.pan();
+        let bounds = |region: Region| [
+            left + region.left as f64 * width,
+            top + region.top as f64 * height,
+            region.cols as f64 * width,
+            region.rows as f64 * height,
+        ];
+
+        let preview = match (self.pasting, self.cursor_pos.and_then(|pos| self.cell_at(pos))) {
+            (true, Some((row, col))) => self.controller.clipboard().map(|clipboard| {
+                let region = Region::new(row, col, clipboard.rows(), clipboard.cols());
+                (region, render::render_overlay(&clipboard, PREVIEW_ALPHA))
+            }),
+            _ => None,
+        };
+        let texture = match &preview {
+            Some((_, img)) => {
+                let mut texture_context = TextureContext {
+                    factory: self.window.factory.clone(),
+                    encoder: self.window.factory.create_command_buffer().into()
+                };
+                Some(Texture::from_image(
+                        &mut texture_context,
+                        img,
+                        &TextureSettings::new().mag(Filter::Nearest)
+                    ).expect("Couldn't build texture"))
+            }
+            None => None,
+        };
+        let selection = self.selection;
+        self.window.draw_2d(e, |cxt, g, _device| {
+            if let Some(region) = selection {
+                Rectangle::new_border(SELECTION_COLOR, BORDER_RADIUS)
+                    .draw(bounds(region), &DrawState::default(), cxt.transform, g);
+            }
+            if let (Some((region, _)), Some(texture)) = (preview, texture) {
+                let transform = cxt.transform
+                    .trans(left, top)
+                    .scale(width, height)
+                    .trans(region.left as f64, region.top as f64);
+                image(&texture, transform, g);
+                Rectangle::new_border(PASTE_COLOR, BORDER_RADIUS)
+                    .draw(bounds(region), &DrawState::default(), cxt.transform, g);
+            }
+        });
+    }
+
+    /// Saves the grid as last drawn, without the pause overlay, with each
+    /// cell `image_scale` pixels across.
+    fn save_screenshot(&mut self) {
+        let frame = match &self.frame {
+            Some(frame) => frame.clone(),
+            None => render::render(&self.controller.model(), 1),
+        };
+        let path = timestamped(&self.output_dir, "screenshot", "png");
+        let scaled = render::upscale(frame, self.image_scale);
+        let message = match scaled.save_with_format(&path, ImageFormat::Png) {
+            Ok(()) => format!("saved {}", path.display()),
+            Err(e) => format!("couldn't save {}: {}", path.display(), e),
+        };
+        self.report(message);
+    }
+
+    /// Saves the clipboard as RLE next to the screenshots.
+    fn save_clipboard(&mut self) {
+        let message = match self.controller.clipboard_text() {
+            Some(text) => {
+                let path = timestamped(&self.output_dir, "clipboard", "rle");
+                match fs::write(&path, text) {
+                    Ok(()) => format!("saved {}", path.display()),
+                    Err(e) => format!("couldn't save {}: {}", path.display(), e),
+                }
+            }
+            None => "the clipboard is empty".to_string(),
+        };
+        self.report(message);
     }
 
     fn render_pause(&mut self, e: &Event) {
@@ -176,7 +406,38 @@ where
         });
     }
 
+    fn render_hud(&mut self, e: &Event) {
+        const HUD_MARGIN: f64 = 10.0;
+        const HUD_SCALE: f64 = 2.0;
+        const HUD_TEXT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+        const HUD_BACKGROUND: Rgba<u8> = Rgba([0, 0, 0, 160]);
+        let mut status = self.controller.status();
+        status.push((
+            "ups".to_string(),
+            format!("{:.1} of {}", self.updates.rate(), self.ups),
+        ));
+        status.push(("fps".to_string(), format!("{:.1}", self.frames.rate())));
+        let img = hud::render_text(&hud::format_status(&status), HUD_TEXT, HUD_BACKGROUND);
+
+        let mut texture_context = TextureContext {
+            factory: self.window.factory.clone(),
+            encoder: self.window.factory.create_command_buffer().into()
+        };
+        let texture = Texture::from_image(
+                &mut texture_context,
+                &img,
+                &TextureSettings::new().mag(Filter::Nearest)
+            ).expect("Couldn't build texture");
+        self.window.draw_2d(e, |cxt, g, _device| {
+            let transform = cxt.transform
+                .trans(HUD_MARGIN, HUD_MARGIN)
+                .scale(HUD_SCALE, HUD_SCALE);
+            image(&texture, transform, g);
+        });
+    }
+
     fn render(&mut self, e: &Event) {
+        self.frames.tick();
         match self.play_state {
             PlayState::Running => self.render_view_model(e),
             PlayState::Paused => {
@@ -184,41 +445,375 @@ where
                 self.render_pause(e);
             }
         }
+        self.render_selection(e);
+        if self.show_hud {
+            self.render_hud(e);
+        }
     }
 
     fn update(&mut self, _e: &Event) {
+        self.updates.tick();
         self.controller.update();
+        self.record_frame();
+    }
+
+    /// Advances `generations` at once, whether or not the view is paused.
+    fn step(&mut self, generations: u64) {
+        for _ in 0..generations {
+            self.controller.update();
+            self.record_frame();
+        }
+    }
+
+    fn set_ups(&mut self, ups: u64) {
+        self.ups = ups;
+        self.events.set_ups(ups);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.set_ups(ups);
+        }
+        self.show_status();
+    }
+
+    /// Puts the update rate, whether the view is paused and the last
+    /// message in the title bar.
+    fn show_status(&mut self) {
+        let paused = match self.play_state {
+            PlayState::Paused => ", paused",
+            PlayState::Running => "",
+        };
+        let mut title = format!("{} ({} ups{})", self.title, self.ups, paused);
+        if let Some(message) = &self.message {
+            title = format!("{} - {}", title, message);
+        }
+        self.window.set_title(title);
+    }
+
+    /// Tells the user something in the title bar, until the next message.
+    fn report(&mut self, message: String) {
+        self.message = Some(message);
+        self.show_status();
+    }
+
+    /// Starts recording from the current frame, or saves the recording in
+    /// progress.
+    fn toggle_recording(&mut self) {
+        match self.recorder.take() {
+            Some(recorder) => match finish_recording(recorder) {
+                Ok(message) | Err(message) => self.report(message),
+            },
+            None => {
+                let extension = self.recording_format.extension();
+                let path = timestamped(&self.output_dir, "recording", extension);
+                match Recorder::create_with_format(
+                    &path,
+                    self.recording_format,
+                    self.ups,
+                    self.image_scale,
+                ) {
+                    Ok(recorder) => {
+                        self.report(format!("recording to {}", path.display()));
+                        self.recorder = Some(recorder);
+                        self.record_frame();
+                    }
+                    Err(e) => self.report(format!("couldn't record to {}: {}", path.display(), e)),
+                }
+            }
+        }
+    }
+
+    fn record_frame(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.add_frame(&self.controller.model()) {
+                let message = format!("stopped recording {}: {}", recorder.path().display(), e);
+                let recorder = self.recorder.take().unwrap();
+                // Keep the frames recorded so far. The failure to add this
+                // one is what's worth reporting.
+                let _ = finish_recording(recorder);
+                self.report(message);
+            }
+        }
     }
 
     fn handle_button_event(&mut self, _e: &Event, args: &ButtonArgs, pos: Option<[f64; 2]>) {
         if let Button::Keyboard(Key::Space) = args.button {
             if let ButtonState::Press = args.state {
                 self.play_state = self.play_state.toggle();
+                self.show_status();
+            }
+        }
+
+        if let Button::Keyboard(Key::Tab) = args.button {
+            if let ButtonState::Press = args.state {
+                self.show_hud = !self.show_hud;
+            }
+        }
+
+        if let Button::Keyboard(Key::Period) = args.button {
+            if let ButtonState::Press = args.state {
+                self.step(1);
+            }
+        }
+
+        if let Button::Keyboard(Key::Return) = args.button {
+            if let ButtonState::Press = args.state {
+                self.step(self.step_size);
             }
         }
 
+        if let Button::Keyboard(Key::Comma) = args.button {
+            if let (ButtonState::Press, PlayState::Paused) = (args.state, &self.play_state) {
+                self.controller.step_back();
+            }
+        }
+
+        if let Button::Keyboard(Key::Equals | Key::NumPadPlus) = args.button {
+            if let ButtonState::Press = args.state {
+                self.set_ups(faster(self.ups));
+            }
+        }
+
+        if let Button::Keyboard(Key::Minus | Key::NumPadMinus) = args.button {
+            if let ButtonState::Press = args.state {
+                self.set_ups(slower(self.ups));
+            }
+        }
+
+        if let Button::Keyboard(Key::LShift | Key::RShift) = args.button {
+            self.shift = args.state == ButtonState::Press;
+        }
+
+        if let Button::Keyboard(Key::LCtrl | Key::RCtrl) = args.button {
+            self.ctrl = args.state == ButtonState::Press;
+        }
+
         if let Button::Keyboard(Key::X) = args.button {
             if let ButtonState::Press = args.state {
-                self.controller.clear();
+                if self.ctrl {
+                    if let Some(region) = self.selection {
+                        self.controller.edit(Edit::Cut(region));
+                    }
+                } else {
+                    self.controller.clear();
+                }
             }
         }
 
-        if let Button::Mouse(MouseButton::Left) = args.button {
+        if let Button::Keyboard(Key::Backspace) = args.button {
             if let ButtonState::Press = args.state {
-                if let Some([x, y]) = pos {
-                    let row = (y / self.cell_height()).floor() as usize;
-                    let col = (x / self.cell_width()).floor() as usize;
-                    self.controller.mouse_click(row, col);
+                self.controller.reset();
+            }
+        }
+
+        if let Button::Keyboard(Key::N) = args.button {
+            if let ButtonState::Press = args.state {
+                self.controller.new_soup();
+            }
+        }
+
+        if let Button::Keyboard(Key::C) = args.button {
+            if let (ButtonState::Press, true, Some(region)) = (args.state, self.ctrl, self.selection) {
+                self.controller.edit(Edit::Copy(region));
+            }
+        }
+
+        if let Button::Keyboard(Key::Z) = args.button {
+            if let (ButtonState::Press, true) = (args.state, self.ctrl) {
+                if self.shift {
+                    self.controller.redo();
+                } else {
+                    self.controller.undo();
                 }
             }
         }
+
+        if let Button::Keyboard(Key::Y) = args.button {
+            if let (ButtonState::Press, true) = (args.state, self.ctrl) {
+                self.controller.redo();
+            }
+        }
+
+        if let Button::Keyboard(Key::V) = args.button {
+            if let (ButtonState::Press, true) = (args.state, self.ctrl) {
+                self.pasting = self.controller.clipboard().is_some();
+            }
+        }
+
+        if let Button::Keyboard(Key::Delete) = args.button {
+            if let (ButtonState::Press, Some(region)) = (args.state, self.selection) {
+                self.controller.edit(Edit::Clear(region));
+            }
+        }
+
+        if let Button::Keyboard(Key::F) = args.button {
+            if let (ButtonState::Press, Some(region)) = (args.state, self.selection) {
+                self.controller.edit(Edit::FillRandom(region));
+            }
+        }
+
+        if let Button::Keyboard(key @ (Key::R | Key::M | Key::U)) = args.button {
+            if let ButtonState::Press = args.state {
+                let transform = match key {
+                    Key::R => Transform::RotateClockwise,
+                    Key::M => Transform::FlipHorizontal,
+                    _ => Transform::FlipVertical,
+                };
+                self.transform(transform);
+            }
+        }
+
+        if let Button::Keyboard(Key::E) = args.button {
+            if let ButtonState::Press = args.state {
+                self.save_clipboard();
+            }
+        }
+
+        if let Button::Keyboard(Key::F10) = args.button {
+            if let ButtonState::Press = args.state {
+                self.toggle_recording();
+            }
+        }
+
+        if let Button::Keyboard(Key::F12) = args.button {
+            if let ButtonState::Press = args.state {
+                self.save_screenshot();
+            }
+        }
+
+        if let Button::Keyboard(Key::D0) = args.button {
+            if let ButtonState::Press = args.state {
+                self.camera.fit();
+            }
+        }
+
+        if let Button::Keyboard(key @ (Key::Left | Key::Right | Key::Up | Key::Down)) = args.button {
+            if let ButtonState::Press = args.state {
+                const PAN_STEP: f64 = 50.0;
+                // The arrows move the view, so the grid moves the other way.
+                let delta = match key {
+                    Key::Left => [PAN_STEP, 0.0],
+                    Key::Right => [-PAN_STEP, 0.0],
+                    Key::Up => [0.0, PAN_STEP],
+                    _ => [0.0, -PAN_STEP],
+                };
+                self.camera.pan_by(delta);
+            }
+        }
+
+        if let Button::Mouse(MouseButton::Middle) = args.button {
+            self.panning = args.state == ButtonState::Press;
+        }
+
+        let paint_button = match args.button {
+            Button::Mouse(MouseButton::Left) => Some(PaintButton::Left),
+            Button::Mouse(MouseButton::Right) => Some(PaintButton::Right),
+            _ => None,
+        };
+        if let (true, Some(button), ButtonState::Press) = (self.pasting, paint_button, args.state) {
+            // While pasting, the left button drops the clipboard and the
+            // right button gives up.
+            if button == PaintButton::Left {
+                if let Some((row, col)) = pos.and_then(|pos| self.cell_at(pos)) {
+                    self.controller.edit(Edit::Paste { row, col });
+                }
+            }
+            self.pasting = false;
+            return;
+        }
+
+        if let (Some(PaintButton::Left), ButtonState::Press, true) = (paint_button, args.state, self.shift) {
+            if let Some(cell) = pos.and_then(|pos| self.cell_at(pos)) {
+                self.selecting = Some(cell);
+                self.selection = Some(Region::spanning(cell, cell));
+            }
+            return;
+        }
+
+        if let (Some(PaintButton::Left), ButtonState::Release, Some(_)) = (paint_button, args.state, self.selecting) {
+            self.selecting = None;
+            return;
+        }
+
+        if let Some(button) = paint_button {
+            match (args.state, self.painting) {
+                (ButtonState::Press, None) => {
+                    self.selection = None;
+                    if let Some((row, col)) = pos.and_then(|pos| self.cell_at(pos)) {
+                        self.controller.mouse_press(button, row, col);
+                        self.painting = Some((button, (row as i64, col as i64)));
+                    }
+                }
+                (ButtonState::Release, Some((painting, _))) if painting == button => {
+                    self.controller.mouse_release(button);
+                    self.painting = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Transforms the clipboard while pasting, or else the selection in
+    /// place.
+    fn transform(&mut self, transform: Transform) {
+        if self.pasting {
+            self.controller.edit(Edit::TransformClipboard(transform));
+        } else if let Some(region) = self.selection {
+            self.controller.edit(Edit::Transform(region, transform));
+            let model = self.controller.model();
+            self.selection = region.transformed(transform).clamped(model.rows(), model.cols());
+        }
     }
 
-    pub fn game_loop(&mut self) {
-        let mut last_cursor_pos = None;
+    /// Stretches the selection being made to the cell under `pos`, or the
+    /// nearest cell on the grid when `pos` is off it.
+    fn select_to(&mut self, pos: [f64; 2]) {
+        let Some(anchor) = self.selecting else {
+            return;
+        };
+        let model = self.controller.model();
+        let (row, col) = self.camera.cell_under(self.fit_cell_size(&model), pos);
+        let clamp = |n: i64, len: usize| n.clamp(0, len as i64 - 1) as usize;
+        let cell = (clamp(row, model.rows()), clamp(col, model.cols()));
+        self.selection = Some(Region::spanning(anchor, cell));
+    }
+
+    /// Paints every cell between the last one the drag was over and the
+    /// one under `pos`.
+    fn drag_to(&mut self, pos: [f64; 2]) {
+        let Some((button, last)) = self.painting else {
+            return;
+        };
+        let model = self.controller.model();
+        let cell = self.camera.cell_under(self.fit_cell_size(&model), pos);
+        if cell == last {
+            return;
+        }
+        let inside = |n: i64, len: usize| n >= 0 && n < len as i64;
+        for (row, col) in line(last, cell).into_iter().skip(1) {
+            if inside(row, model.rows()) && inside(col, model.cols()) {
+                self.controller.mouse_drag(button, row as usize, col as usize);
+            }
+        }
+        self.painting = Some((button, cell));
+    }
+
+    /// Runs until the window is closed, then saves any recording in
+    /// progress, returning an error if that fails.
+    pub fn game_loop(&mut self) -> Result<(), Box<dyn Error>> {
+        const ZOOM_STEP: f64 = 1.25;
         while let Some(e) = self.events.next(&mut self.window) {
             if let Some(pos) = e.mouse_cursor_args() {
-                last_cursor_pos = Some(pos);
+                if let (true, Some([x, y])) = (self.panning, self.cursor_pos) {
+                    self.camera.pan_by([pos[0] - x, pos[1] - y]);
+                }
+                self.drag_to(pos);
+                self.select_to(pos);
+                self.cursor_pos = Some(pos);
+            }
+
+            if let Some([_, scroll]) = e.mouse_scroll_args() {
+                let cursor = self.cursor_pos.unwrap_or([0.0, 0.0]);
+                let fit = self.fit_cell_size(&self.controller.model());
+                self.camera.zoom_at(fit, cursor, ZOOM_STEP.powf(scroll));
             }
 
             if e.render_args().is_some() {
@@ -232,8 +827,54 @@ where
             }
 
             if let Some(args) = e.button_args() {
-                self.handle_button_event(&e, &args, last_cursor_pos);
+                self.handle_button_event(&e, &args, self.cursor_pos);
             }
         }
+
+        if let Some(recorder) = self.recorder.take() {
+            finish_recording(recorder)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod grid_view_tests {
+    use super::*;
+
+    #[test]
+    fn lines_include_both_ends_and_step_one_cell_at_a_time() {
+        assert_eq!(vec![(2, 3)], line((2, 3), (2, 3)));
+        assert_eq!(vec![(0, 0), (0, 1), (0, 2)], line((0, 0), (0, 2)));
+        assert_eq!(vec![(2, 0), (1, 0), (0, 0)], line((2, 0), (0, 0)));
+        assert_eq!(vec![(0, 0), (-1, -1), (-2, -2)], line((0, 0), (-2, -2)));
+        let cells = line((0, 0), (3, 7));
+        assert_eq!((3, 7), *cells.last().unwrap());
+        assert_eq!(8, cells.len());
+        for pair in cells.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!((a.0 - b.0).abs() <= 1 && (a.1 - b.1).abs() <= 1, "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn speed_keys_move_between_powers_of_two() {
+        assert_eq!([8, 16, 16], [5, 8, 12].map(faster));
+        assert_eq!([4, 4, 8], [5, 8, 12].map(slower));
+    }
+
+    #[test]
+    fn speed_keys_stop_at_the_limits() {
+        assert_eq!(MAX_UPS, faster(MAX_UPS));
+        assert_eq!(MAX_UPS, faster(MAX_UPS - 1));
+        assert_eq!(MIN_UPS, slower(MIN_UPS));
+    }
+
+    #[test]
+    fn speeding_up_never_slows_a_rate_past_the_limit() {
+        assert_eq!(3000, faster(3000));
+        assert_eq!(u64::MAX, faster(u64::MAX));
+        assert_eq!(2048, slower(3000));
+        assert_eq!(1 << 63, slower(u64::MAX));
     }
 }
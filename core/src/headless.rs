@@ -0,0 +1,296 @@
+//! Runs an automaton for a fixed number of generations with no window, for
+//! batch jobs on machines without a display. Both apps share the options,
+//! the stats kept each generation and the files they are written to.
+
+use crate::cli::{parse_value, OptionError};
+use crate::model::GridViewModel;
+use crate::record::Recorder;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Options only the `headless` subcommand takes.
+pub const HEADLESS_OPTIONS: [&str; 4] = ["generations", "output", "stats", "record"];
+
+pub const DEFAULT_GENERATIONS: u64 = 100;
+
+/// Where a headless run writes its results. The final state goes to
+/// standard output when there is no `output` file, and stats and
+/// recordings are only written when asked for.
+#[derive(Debug, PartialEq, Eq)]
+pub struct HeadlessOptions {
+    pub generations: u64,
+    pub output: Option<PathBuf>,
+    pub stats: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            generations: DEFAULT_GENERATIONS,
+            output: None,
+            stats: None,
+            record: None,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    /// Sets `option`, one of `HEADLESS_OPTIONS`, to `value`.
+    pub fn apply(&mut self, option: &'static str, value: &str) -> Result<(), OptionError> {
+        match option {
+            "generations" => self.generations = parse_value(option, value)?,
+            "output" => self.output = Some(value.into()),
+            "stats" => self.stats = Some(value.into()),
+            "record" => self.record = Some(value.into()),
+            _ => unreachable!("'{}' is not in HEADLESS_OPTIONS", option),
+        }
+        Ok(())
+    }
+}
+
+/// An automaton a headless run can step and count.
+pub trait Automaton {
+    fn step(&mut self);
+
+    /// Whether each cell is alive, always in the same order.
+    fn alive_cells(&self) -> Vec<bool>;
+}
+
+/// What happened to the population in one generation, counted from the
+/// start of the run.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GenerationStats {
+    pub generation: u64,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+/// Advances `automaton` by `generations`, calling `on_generation` with the
+/// automaton and its stats for the starting state and after every step.
+pub fn run<A, F>(automaton: &mut A, generations: u64, mut on_generation: F)
+where
+    A: Automaton,
+    F: FnMut(&A, &GenerationStats),
+{
+    let mut before = automaton.alive_cells();
+    let stats = GenerationStats {
+        generation: 0,
+        population: before.iter().filter(|&&cell| cell).count(),
+        births: 0,
+        deaths: 0,
+    };
+    on_generation(automaton, &stats);
+    for generation in 1..=generations {
+        automaton.step();
+        let after = automaton.alive_cells();
+        let changed = || before.iter().zip(&after);
+        let stats = GenerationStats {
+            generation,
+            population: after.iter().filter(|&&cell| cell).count(),
+            births: changed().filter(|&(&was, &is)| !was && is).count(),
+            deaths: changed().filter(|&(&was, &is)| was && !is).count(),
+        };
+        on_generation(automaton, &stats);
+        before = after;
+    }
+}
+
+/// Like `run`, writing the stats to `out` as CSV with a header row.
+pub fn run_with_csv<A, W>(automaton: &mut A, generations: u64, out: &mut W) -> io::Result<()>
+where
+    A: Automaton,
+    W: Write,
+{
+    write_csv_header(out)?;
+    let mut result = Ok(());
+    run(automaton, generations, |_, stats| {
+        if result.is_ok() {
+            result = write_csv_row(out, stats);
+        }
+    });
+    result
+}
+
+/// The header row `run_with_csv` starts with.
+pub fn write_csv_header<W: Write>(out: &mut W) -> io::Result<()> {
+    writeln!(out, "generation,population,births,deaths")
+}
+
+/// One generation's stats as a row of CSV.
+pub fn write_csv_row<W: Write>(out: &mut W, stats: &GenerationStats) -> io::Result<()> {
+    writeln!(
+        out,
+        "{},{},{},{}",
+        stats.generation, stats.population, stats.births, stats.deaths
+    )
+}
+
+/// A file a headless run could not write, and why.
+#[derive(Debug)]
+pub struct OutputError {
+    pub path: PathBuf,
+    pub error: Box<dyn Error>,
+}
+
+impl OutputError {
+    pub fn new<E: Into<Box<dyn Error>>>(path: &Path, error: E) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            error: error.into(),
+        }
+    }
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for OutputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// The files `HeadlessOptions` asks for as the run goes: a row of stats and
+/// a recorded frame per generation.
+pub struct Outputs {
+    stats: Option<(PathBuf, BufWriter<File>)>,
+    recorder: Option<Recorder>,
+}
+
+impl Outputs {
+    /// Creates the stats and recording files, recording at `fps` frames per
+    /// second and `scale` pixels per cell.
+    pub fn create(options: &HeadlessOptions, fps: u64, scale: u32) -> Result<Self, OutputError> {
+        let stats = match &options.stats {
+            Some(path) => {
+                let file = File::create(path).map_err(|e| OutputError::new(path, e))?;
+                let mut out = BufWriter::new(file);
+                write_csv_header(&mut out).map_err(|e| OutputError::new(path, e))?;
+                Some((path.clone(), out))
+            }
+            None => None,
+        };
+        let recorder = match &options.record {
+            Some(path) => {
+                Some(Recorder::create(path, fps, scale).map_err(|e| OutputError::new(path, e))?)
+            }
+            None => None,
+        };
+        Ok(Self { stats, recorder })
+    }
+
+    /// Writes one generation's stats, and the frame `model` draws if the run
+    /// is being recorded.
+    pub fn write<F>(&mut self, stats: &GenerationStats, model: F) -> Result<(), OutputError>
+    where
+        F: FnOnce() -> GridViewModel,
+    {
+        if let Some((path, out)) = &mut self.stats {
+            write_csv_row(out, stats).map_err(|e| OutputError::new(path, e))?;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder
+                .add_frame(&model())
+                .map_err(|e| OutputError::new(recorder.path(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the stats and finishes the recording.
+    pub fn finish(self) -> Result<(), OutputError> {
+        if let Some((path, mut out)) = self.stats {
+            out.flush().map_err(|e| OutputError::new(&path, e))?;
+        }
+        if let Some(recorder) = self.recorder {
+            let path = recorder.path().to_path_buf();
+            recorder.finish().map_err(|e| OutputError::new(&path, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod headless_tests {
+    use super::*;
+
+    /// A ring of cells where every live cell moves one place to the right
+    /// each step.
+    struct Ring(Vec<bool>);
+
+    impl Automaton for Ring {
+        fn step(&mut self) {
+            self.0.rotate_right(1);
+        }
+
+        fn alive_cells(&self) -> Vec<bool> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn reports_every_generation_including_the_first() {
+        let mut ring = Ring(vec![true, true, false, false]);
+        let mut seen = Vec::new();
+        run(&mut ring, 2, |_, stats| seen.push(*stats));
+        assert_eq!(
+            vec![
+                GenerationStats {
+                    generation: 0,
+                    population: 2,
+                    births: 0,
+                    deaths: 0
+                },
+                GenerationStats {
+                    generation: 1,
+                    population: 2,
+                    births: 1,
+                    deaths: 1
+                },
+                GenerationStats {
+                    generation: 2,
+                    population: 2,
+                    births: 1,
+                    deaths: 1
+                },
+            ],
+            seen
+        );
+        assert_eq!(vec![false, false, true, true], ring.0);
+    }
+
+    #[test]
+    fn writes_stats_as_csv() {
+        let mut ring = Ring(vec![true, false, false]);
+        let mut out = Vec::new();
+        run_with_csv(&mut ring, 1, &mut out).unwrap();
+        assert_eq!(
+            "generation,population,births,deaths\n0,1,0,0\n1,1,1,1\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn options_default_to_stdout_and_no_files() {
+        let options = HeadlessOptions::default();
+        assert_eq!(DEFAULT_GENERATIONS, options.generations);
+        assert_eq!(
+            (None, None, None),
+            (options.output, options.stats, options.record)
+        );
+
+        let mut options = HeadlessOptions::default();
+        options.apply("generations", "64").unwrap();
+        options.apply("stats", "stats.csv").unwrap();
+        assert_eq!(64, options.generations);
+        assert_eq!(Some(PathBuf::from("stats.csv")), options.stats);
+        assert!(options.apply("generations", "-1").is_err());
+    }
+}
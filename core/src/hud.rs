@@ -0,0 +1,195 @@
+//! Draws status lines for the on-screen display in a built in 5x7 bitmap
+//! font, so that no font file has to be found at runtime.
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+const LETTER_SPACING: u32 = 1;
+const LINE_SPACING: u32 = 2;
+const PADDING: u32 = 2;
+
+/// The rows of a character's glyph, top first, with the leftmost pixel in
+/// the fifth bit. Letters are drawn in capitals and anything the font
+/// doesn't have is drawn as `?`.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '#' => [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '+' => [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '<' => [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],
+        '=' => [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00],
+        '>' => [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],
+        _ => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // ?
+    }
+}
+
+/// Lines the values of `status` up in a column after the widest key.
+pub fn format_status(status: &[(String, String)]) -> Vec<String> {
+    let width = status
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+    status
+        .iter()
+        .map(|(key, value)| format!("{:<width$}  {}", key, value, width = width))
+        .collect()
+}
+
+/// Draws `lines` in `color` on a `background` box just big enough to hold
+/// them, one pixel per font pixel.
+pub fn render_text(lines: &[String], color: Rgba<u8>, background: Rgba<u8>) -> RgbaImage {
+    let columns = lines
+        .iter()
+        .map(|line| line.chars().count() as u32)
+        .max()
+        .unwrap_or(0);
+    let rows = lines.len() as u32;
+    let text_width = (columns * (GLYPH_WIDTH + LETTER_SPACING)).saturating_sub(LETTER_SPACING);
+    let text_height = (rows * (GLYPH_HEIGHT + LINE_SPACING)).saturating_sub(LINE_SPACING);
+    let mut img = ImageBuffer::from_pixel(
+        text_width + 2 * PADDING,
+        text_height + 2 * PADDING,
+        background,
+    );
+    for (row, line) in lines.iter().enumerate() {
+        let top = PADDING + row as u32 * (GLYPH_HEIGHT + LINE_SPACING);
+        for (column, c) in line.chars().enumerate() {
+            let left = PADDING + column as u32 * (GLYPH_WIDTH + LETTER_SPACING);
+            for (y, bits) in glyph(c).iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        img.put_pixel(left + x, top + y as u32, color);
+                    }
+                }
+            }
+        }
+    }
+    img
+}
+
+#[cfg(test)]
+mod hud_tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    /// The lit pixels of a single line as rows of `#` and `.`.
+    fn pixels(text: &str) -> Vec<String> {
+        let img = render_text(&[text.to_string()], WHITE, CLEAR);
+        (PADDING..img.height() - PADDING)
+            .map(|y| {
+                (PADDING..img.width() - PADDING)
+                    .map(|x| {
+                        if img.get_pixel(x, y) == &WHITE {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn draws_glyphs_left_to_right_with_a_gap() {
+        assert_eq!(
+            vec![
+                ".###....#..",
+                "..#....##..",
+                "..#.....#..",
+                "..#.....#..",
+                "..#.....#..",
+                "..#.....#..",
+                ".###...###.",
+            ],
+            pixels("I1")
+        );
+    }
+
+    #[test]
+    fn sizes_the_box_to_the_longest_line() {
+        let lines = vec!["ab".to_string(), "abcd".to_string(), String::new()];
+        let img = render_text(&lines, WHITE, CLEAR);
+        assert_eq!(
+            4 * GLYPH_WIDTH + 3 * LETTER_SPACING + 2 * PADDING,
+            img.width()
+        );
+        assert_eq!(
+            3 * GLYPH_HEIGHT + 2 * LINE_SPACING + 2 * PADDING,
+            img.height()
+        );
+        assert_eq!(
+            (2 * PADDING, 2 * PADDING),
+            render_text(&[], WHITE, CLEAR).dimensions()
+        );
+    }
+
+    #[test]
+    fn draws_lower_case_as_capitals_and_unknown_characters_as_question_marks() {
+        assert_eq!(pixels("RULE"), pixels("rule"));
+        assert_eq!(pixels("?"), pixels("~"));
+        assert_ne!(pixels("?"), pixels("7"));
+    }
+
+    #[test]
+    fn lines_values_up_after_the_widest_key() {
+        let status = [
+            ("generation".to_string(), "12".to_string()),
+            ("rule".to_string(), "B3/S23".to_string()),
+        ];
+        assert_eq!(
+            vec!["generation  12", "rule        B3/S23"],
+            format_status(&status)
+        );
+    }
+}
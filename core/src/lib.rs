@@ -1 +1,12 @@
+pub mod camera;
+pub mod cli;
+pub mod color;
+#[cfg(feature = "window")]
 pub mod grid_view;
+pub mod headless;
+pub mod hud;
+pub mod model;
+pub mod record;
+pub mod render;
+pub mod rng;
+pub mod selection;
@@ -0,0 +1,141 @@
+//! What a `Controller` exposes of its automaton: the grid of coloured cells
+//! views draw and recordings render, and the actions a view can ask for.
+//! Nothing here needs a window.
+
+use crate::selection::Edit;
+
+/// The slowest and fastest a view runs, in updates per second.
+pub const MIN_UPS: u64 = 1;
+pub const MAX_UPS: u64 = 1024;
+
+/// The mouse buttons that paint on the grid. Left draws and right erases.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PaintButton {
+    Left,
+    Right,
+}
+
+pub trait Controller {
+    fn model(&self) -> GridViewModel;
+    fn update(&mut self);
+    fn mouse_click(&mut self, row: usize, col: usize);
+    fn clear(&mut self);
+
+    /// Goes back to the state the automaton started from. Does nothing by
+    /// default.
+    fn reset(&mut self) {}
+
+    /// Starts again from a random soup with a fresh seed. Does nothing by
+    /// default.
+    fn new_soup(&mut self) {}
+
+    /// `button` went down over a cell, starting a drag. A left press is a
+    /// click by default.
+    fn mouse_press(&mut self, button: PaintButton, row: usize, col: usize) {
+        if button == PaintButton::Left {
+            self.mouse_click(row, col);
+        }
+    }
+
+    /// The drag started by `mouse_press` crossed another cell. Every cell on
+    /// the line between mouse samples is reported, in order, so consecutive
+    /// calls are for neighbouring cells.
+    fn mouse_drag(&mut self, _button: PaintButton, _row: usize, _col: usize) {}
+
+    /// The drag started by `mouse_press` ended.
+    fn mouse_release(&mut self, _button: PaintButton) {}
+
+    /// Key/value lines for the on-screen display, such as the generation
+    /// and population. Shows nothing by default.
+    fn status(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// Takes back the last edit made to the cells. Does nothing by default.
+    fn undo(&mut self) {}
+
+    /// Makes the last edit undone again. Does nothing by default.
+    fn redo(&mut self) {}
+
+    /// Goes back a generation, if the controller remembers the one before.
+    /// Does nothing by default.
+    fn step_back(&mut self) {}
+
+    /// Carries out an edit of a selection or the clipboard. Controllers
+    /// without a clipboard ignore edits.
+    fn edit(&mut self, _edit: Edit) {}
+
+    /// The clipboard as a model of its own size, drawn over the grid to
+    /// preview a paste, or `None` when there is nothing to paste.
+    fn clipboard(&self) -> Option<GridViewModel> {
+        None
+    }
+
+    /// The clipboard written out as RLE, for saving it to a file.
+    fn clipboard_text(&self) -> Option<String> {
+        None
+    }
+}
+
+pub struct Entity {
+    color: [f32; 4],
+    row: usize,
+    col: usize,
+}
+
+impl Entity {
+    pub fn new(color: [f32; 4], row: usize, col: usize) -> Self {
+        Self { color, row, col }
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+}
+
+pub struct GridViewModel {
+    rows: usize,
+    cols: usize,
+    entities: Vec<Entity>,
+    background_color: [f32; 4],
+}
+
+impl GridViewModel {
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        entities: Vec<Entity>,
+        background_color: [f32; 4],
+    ) -> Self {
+        Self {
+            rows,
+            cols,
+            entities,
+            background_color,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn background_color(&self) -> [f32; 4] {
+        self.background_color
+    }
+}
@@ -0,0 +1,604 @@
+//! Records successive `GridViewModel`s as an animated GIF or APNG. Frames
+//! are rendered with `render::render` and encoded as they arrive, so long
+//! recordings don't have to fit in memory.
+
+use crate::model::GridViewModel;
+use crate::render;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::png::PngEncoder;
+use image::{ColorType, Delay, Frame, ImageError, RgbaImage};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// How quickly the GIF encoder quantises frames, from 1 (best) to 30
+/// (fastest). Grids only use a handful of colours, so speed costs nothing.
+const GIF_SPEED: i32 = 30;
+
+/// The shortest time, in hundredths of a second, a GIF frame is shown for.
+/// Most viewers slow shorter frames right down, to a tenth of a second.
+const MIN_GIF_DELAY: u64 = 2;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecordingFormat {
+    Gif,
+    Apng,
+}
+
+impl RecordingFormat {
+    /// Guesses the format from a file extension: `gif`, or `png`/`apng`.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(Self::Gif),
+            "png" | "apng" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+
+    /// The extension new recordings in this format are given.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    Image(ImageError),
+    UnknownFormat(PathBuf),
+    FrameSizeChanged {
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    NoFrames,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Image(e) => write!(f, "{}", e),
+            Self::UnknownFormat(_) => {
+                write!(f, "unknown recording format, expected a .gif or .png file")
+            }
+            Self::FrameSizeChanged { expected, found } => write!(
+                f,
+                "a {}x{} frame can't be added to a {}x{} recording",
+                found.0, found.1, expected.0, expected.1
+            ),
+            Self::NoFrames => write!(f, "no frames were recorded"),
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+impl From<io::Error> for RecordError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ImageError> for RecordError {
+    fn from(e: ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+/// The file a GIF is written to, shared between the encoder and the
+/// recorder. The encoder only writes the trailer as it is dropped, ignoring
+/// any error, so the first error is kept here for `finish` to report.
+#[derive(Clone)]
+struct GifFile(Rc<RefCell<(BufWriter<File>, Option<io::Error>)>>);
+
+impl GifFile {
+    fn new(out: BufWriter<File>) -> Self {
+        Self(Rc::new(RefCell::new((out, None))))
+    }
+
+    /// Flushes the file, or returns the first error writing to it.
+    fn finish(self) -> io::Result<()> {
+        let mut file = self.0.borrow_mut();
+        match file.1.take() {
+            Some(e) => Err(e),
+            None => file.0.flush(),
+        }
+    }
+
+    fn check<T>(error: &mut Option<io::Error>, result: io::Result<T>) -> io::Result<T> {
+        result.map_err(|e| {
+            let kind = e.kind();
+            error.get_or_insert(e);
+            io::Error::from(kind)
+        })
+    }
+}
+
+impl Write for GifFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (out, error) = &mut *self.0.borrow_mut();
+        Self::check(error, out.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let (out, error) = &mut *self.0.borrow_mut();
+        Self::check(error, out.flush())
+    }
+}
+
+/// Writes a GIF frame by frame, in real time. GIF delays are whole
+/// hundredths of a second and viewers won't show a frame for less than
+/// `MIN_GIF_DELAY`, so at faster rates frames are dropped instead, keeping
+/// the last of each run of them.
+struct GifWriter {
+    encoder: GifEncoder<GifFile>,
+    file: GifFile,
+    /// The latest frame, written once it is known how long to show it.
+    pending: Option<RgbaImage>,
+    /// When the next frame starts, in microseconds from the first.
+    clock: u64,
+    /// How long the frames written so far are shown for, in hundredths of a
+    /// second.
+    shown: u64,
+    frames: usize,
+}
+
+impl GifWriter {
+    fn new(out: BufWriter<File>) -> Result<Self, ImageError> {
+        let file = GifFile::new(out);
+        let mut encoder = GifEncoder::new_with_speed(file.clone(), GIF_SPEED);
+        encoder.set_repeat(Repeat::Infinite)?;
+        Ok(Self {
+            encoder,
+            file,
+            pending: None,
+            clock: 0,
+            shown: 0,
+            frames: 0,
+        })
+    }
+
+    /// How long the pending frame would be shown for if the next started
+    /// now, in hundredths of a second.
+    fn pending_delay(&self) -> u64 {
+        (self.clock + 5_000) / 10_000 - self.shown
+    }
+
+    /// Writes the pending frame, if there is one, shown for `delay`
+    /// hundredths of a second.
+    fn write_pending(&mut self, delay: u64) -> Result<(), ImageError> {
+        if let Some(img) = self.pending.take() {
+            let ms = Delay::from_numer_denom_ms((delay * 10) as u32, 1);
+            self.encoder
+                .encode_frame(Frame::from_parts(img, 0, 0, ms))?;
+            self.shown += delay;
+            self.frames += 1;
+        }
+        Ok(())
+    }
+
+    /// Adds `img`, to be shown for a `ups`th of a second.
+    fn write_frame(&mut self, img: RgbaImage, ups: u64) -> Result<(), ImageError> {
+        let delay = self.pending_delay();
+        if delay >= MIN_GIF_DELAY {
+            self.write_pending(delay)?;
+        }
+        self.pending = Some(img);
+        self.clock += 1_000_000 / ups;
+        Ok(())
+    }
+
+    /// Writes the last frame and the trailer, returning the number of
+    /// frames written.
+    fn finish(mut self) -> Result<usize, RecordError> {
+        self.write_pending(self.pending_delay().max(MIN_GIF_DELAY))?;
+        // Dropping the encoder writes the trailer.
+        drop(self.encoder);
+        self.file.finish()?;
+        Ok(self.frames)
+    }
+}
+
+enum Encoder {
+    Gif(GifWriter),
+    Apng(ApngWriter<BufWriter<File>>),
+}
+
+/// Collects frames into an animation that plays back at the rate the
+/// automaton was run at.
+pub struct Recorder {
+    path: PathBuf,
+    encoder: Encoder,
+    ups: u64,
+    scale: u32,
+    size: Option<(u32, u32)>,
+    frames: usize,
+}
+
+impl Recorder {
+    /// Starts a recording in the format `path`'s extension names, showing
+    /// `ups` frames a second with each cell `scale` pixels across.
+    pub fn create<P: AsRef<Path>>(path: P, ups: u64, scale: u32) -> Result<Self, RecordError> {
+        let path = path.as_ref();
+        let format = RecordingFormat::from_extension(path)
+            .ok_or_else(|| RecordError::UnknownFormat(path.to_path_buf()))?;
+        Self::create_with_format(path, format, ups, scale)
+    }
+
+    /// Like `create`, whatever `path`'s extension.
+    pub fn create_with_format<P: AsRef<Path>>(
+        path: P,
+        format: RecordingFormat,
+        ups: u64,
+        scale: u32,
+    ) -> Result<Self, RecordError> {
+        assert!(ups > 0, "recordings need at least one frame a second");
+        assert!(scale > 0, "cells must be at least one pixel across");
+        let path = path.as_ref().to_path_buf();
+        let out = BufWriter::new(File::create(&path)?);
+        let encoder = match format {
+            RecordingFormat::Gif => Encoder::Gif(GifWriter::new(out)?),
+            RecordingFormat::Apng => Encoder::Apng(ApngWriter::new(out)),
+        };
+        Ok(Self {
+            path,
+            encoder,
+            ups,
+            scale,
+            size: None,
+            frames: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Changes how long frames added from now on are shown for.
+    pub fn set_ups(&mut self, ups: u64) {
+        assert!(ups > 0, "recordings need at least one frame a second");
+        self.ups = ups;
+    }
+
+    /// The number of frames added so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Renders `model` as the next frame. Every frame has to be the same
+    /// size as the first.
+    pub fn add_frame(&mut self, model: &GridViewModel) -> Result<(), RecordError> {
+        let img = render::render(model, self.scale);
+        let found = img.dimensions();
+        match self.size {
+            Some(expected) if expected != found => {
+                return Err(RecordError::FrameSizeChanged { expected, found })
+            }
+            _ => self.size = Some(found),
+        }
+        match &mut self.encoder {
+            Encoder::Gif(gif) => gif.write_frame(img, self.ups)?,
+            Encoder::Apng(apng) => {
+                let delay = (1, self.ups.min(u16::MAX as u64) as u16);
+                apng.write_frame(&img, delay)?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Finishes the file, returning the number of frames in it, which for a
+    /// GIF recorded faster than it can play is fewer than were added. A
+    /// recording without frames is an error, and its file is removed.
+    pub fn finish(self) -> Result<usize, RecordError> {
+        if self.frames == 0 {
+            drop(self.encoder);
+            fs::remove_file(&self.path)?;
+            return Err(RecordError::NoFrames);
+        }
+        match self.encoder {
+            Encoder::Gif(gif) => gif.finish(),
+            Encoder::Apng(apng) => {
+                apng.finish()?;
+                Ok(self.frames)
+            }
+        }
+    }
+}
+
+/// Writes an APNG chunk by chunk. Each frame is compressed by encoding it
+/// as an ordinary PNG and lifting out its image data; the `png` crate
+/// doesn't write animations itself.
+struct ApngWriter<W: Write + Seek> {
+    out: W,
+    /// Where the animation control chunk is, so the frame count can be
+    /// filled in once it is known.
+    actl_offset: u64,
+    frames: u32,
+    sequence: u32,
+}
+
+impl<W: Write + Seek> ApngWriter<W> {
+    fn new(out: W) -> Self {
+        Self {
+            out,
+            actl_offset: 0,
+            frames: 0,
+            sequence: 0,
+        }
+    }
+
+    fn write_chunk(&mut self, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+        self.out.write_all(&(data.len() as u32).to_be_bytes())?;
+        self.out.write_all(kind)?;
+        self.out.write_all(data)?;
+        let crc = crc32(kind.iter().chain(data));
+        self.out.write_all(&crc.to_be_bytes())
+    }
+
+    fn write_header(&mut self, png: &[u8]) -> io::Result<()> {
+        let (signature, _) = png.split_at(8);
+        self.out.write_all(signature)?;
+        let ihdr = chunks(png)
+            .find(|(kind, _)| kind == b"IHDR")
+            .map(|(_, data)| data)
+            .expect("PngEncoder always writes a header");
+        self.write_chunk(b"IHDR", ihdr)?;
+        self.actl_offset = self.out.stream_position()?;
+        self.write_chunk(b"acTL", &actl(0))
+    }
+
+    /// Adds `img` to the animation, shown for `delay.0 / delay.1` seconds.
+    fn write_frame(&mut self, img: &RgbaImage, delay: (u16, u16)) -> Result<(), ImageError> {
+        let (width, height) = img.dimensions();
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png).encode(img, width, height, ColorType::Rgba8)?;
+        if self.frames == 0 {
+            self.write_header(&png)?;
+        }
+
+        let mut fctl = Vec::with_capacity(26);
+        fctl.extend_from_slice(&self.sequence.to_be_bytes());
+        for n in [width, height, 0, 0] {
+            fctl.extend_from_slice(&n.to_be_bytes());
+        }
+        fctl.extend_from_slice(&delay.0.to_be_bytes());
+        fctl.extend_from_slice(&delay.1.to_be_bytes());
+        // Leave the frame in place when it is done and replace what's under
+        // it rather than blending.
+        fctl.extend_from_slice(&[0, 0]);
+        self.write_chunk(b"fcTL", &fctl)?;
+        self.sequence += 1;
+
+        for (_, data) in chunks(&png).filter(|(kind, _)| kind == b"IDAT") {
+            if self.frames == 0 {
+                self.write_chunk(b"IDAT", data)?;
+            } else {
+                let mut fdat = Vec::with_capacity(4 + data.len());
+                fdat.extend_from_slice(&self.sequence.to_be_bytes());
+                fdat.extend_from_slice(data);
+                self.write_chunk(b"fdAT", &fdat)?;
+                self.sequence += 1;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.write_chunk(b"IEND", &[])?;
+        let end = self.out.stream_position()?;
+        self.out.seek(SeekFrom::Start(self.actl_offset))?;
+        self.write_chunk(b"acTL", &actl(self.frames))?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()
+    }
+}
+
+/// The animation control chunk's data, for a GIF-like endless loop.
+fn actl(frames: u32) -> [u8; 8] {
+    let mut data = [0; 8];
+    data[..4].copy_from_slice(&frames.to_be_bytes());
+    data
+}
+
+/// The chunks of a whole PNG file as `(type, data)` pairs.
+fn chunks(png: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut rest = &png[8..];
+    std::iter::from_fn(move || {
+        if rest.len() < 12 {
+            return None;
+        }
+        let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let kind = rest[4..8].try_into().unwrap();
+        let data = &rest[8..8 + len];
+        rest = &rest[12 + len..];
+        Some((kind, data))
+    })
+}
+
+/// The CRC-32 every PNG chunk ends with.
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod record_tests {
+    use super::*;
+    use crate::model::Entity;
+    use image::codecs::gif::GifDecoder;
+    use image::codecs::png::ApngDecoder;
+    use image::codecs::png::PngDecoder;
+    use image::{AnimationDecoder, Rgba};
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+    /// A 1x3 grid with only cell `col` alive.
+    fn frame(col: usize) -> GridViewModel {
+        GridViewModel::new(1, 3, vec![Entity::new(WHITE, 0, col)], BLACK)
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("record-{}-{}", std::process::id(), name))
+    }
+
+    fn record(path: &Path) -> usize {
+        let mut recorder = Recorder::create(path, 4, 2).unwrap();
+        for col in 0..3 {
+            recorder.add_frame(&frame(col)).unwrap();
+        }
+        recorder.finish().unwrap()
+    }
+
+    fn assert_frames<'a, D: AnimationDecoder<'a>>(decoder: D) {
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(3, frames.len());
+        for (col, frame) in frames.iter().enumerate() {
+            assert_eq!((250, 1), frame.delay().numer_denom_ms());
+            let img = frame.buffer();
+            assert_eq!((6, 2), img.dimensions());
+            for (x, y, pixel) in img.enumerate_pixels() {
+                let alive = x / 2 == col as u32;
+                assert_eq!(
+                    alive,
+                    pixel == &Rgba([255, 255, 255, 255]),
+                    "({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        let format = |name: &str| RecordingFormat::from_extension(Path::new(name));
+        assert_eq!(Some(RecordingFormat::Gif), format("run.GIF"));
+        assert_eq!(Some(RecordingFormat::Apng), format("run.png"));
+        assert_eq!(Some(RecordingFormat::Apng), format("run.apng"));
+        assert_eq!(None, format("run.mp4"));
+        assert_eq!(None, format("run"));
+        assert!(matches!(
+            Recorder::create(temp_path("run.mp4"), 4, 1),
+            Err(RecordError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn gifs_play_every_frame_at_the_update_rate() {
+        let path = temp_path("frames.gif");
+        assert_eq!(3, record(&path));
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        assert_frames(decoder);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn apngs_play_every_frame_at_the_update_rate() {
+        let path = temp_path("frames.png");
+        assert_eq!(3, record(&path));
+        let decoder = PngDecoder::new(File::open(&path).unwrap()).unwrap();
+        assert!(decoder.is_apng());
+        let decoder: ApngDecoder<_> = decoder.apng();
+        assert_frames(decoder);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn apngs_show_the_first_frame_to_plain_png_readers() {
+        let path = temp_path("still.png");
+        record(&path);
+        let img = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(render::render(&frame(0), 2), img);
+    }
+
+    #[test]
+    fn frames_keep_the_rate_they_were_added_at() {
+        let path = temp_path("rates.gif");
+        let mut recorder = Recorder::create(&path, 10, 1).unwrap();
+        recorder.add_frame(&frame(0)).unwrap();
+        recorder.set_ups(2);
+        recorder.add_frame(&frame(1)).unwrap();
+        recorder.finish().unwrap();
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let delays: Vec<_> = frames.iter().map(|f| f.delay().numer_denom_ms()).collect();
+        assert_eq!(vec![(100, 1), (500, 1)], delays);
+    }
+
+    #[test]
+    fn fast_gifs_drop_frames_to_keep_real_time() {
+        let path = temp_path("fast.gif");
+        let mut recorder = Recorder::create(&path, 100, 1).unwrap();
+        for col in [0, 1, 2, 0, 1, 2, 0, 1, 2, 1] {
+            recorder.add_frame(&frame(col)).unwrap();
+        }
+        assert_eq!(5, recorder.finish().unwrap());
+        let decoder = GifDecoder::new(File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let delays: Vec<_> = frames.iter().map(|f| f.delay().numer_denom_ms()).collect();
+        assert_eq!(vec![(20, 1); 5], delays);
+        // The last frame added is always kept.
+        assert_eq!(render::render(&frame(1), 1), *frames[4].buffer());
+    }
+
+    #[test]
+    fn frames_must_all_be_the_same_size() {
+        let path = temp_path("sizes.gif");
+        let mut recorder = Recorder::create(&path, 4, 1).unwrap();
+        recorder.add_frame(&frame(0)).unwrap();
+        let bigger = GridViewModel::new(2, 3, Vec::new(), BLACK);
+        let err = recorder.add_frame(&bigger).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            "a 3x2 frame can't be added to a 3x1 recording",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn empty_recordings_are_an_error_and_leave_no_file() {
+        for name in ["empty.png", "empty.gif"] {
+            let path = temp_path(name);
+            let recorder = Recorder::create(&path, 4, 1).unwrap();
+            assert!(matches!(recorder.finish(), Err(RecordError::NoFrames)));
+            assert!(!path.exists(), "{}", name);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn errors_writing_the_end_of_a_gif_are_reported() {
+        // The frame fits in the file's buffer, so nothing fails until the
+        // trailer is written and the buffer flushed.
+        let gif = RecordingFormat::Gif;
+        let mut recorder = Recorder::create_with_format("/dev/full", gif, 4, 1).unwrap();
+        recorder.add_frame(&frame(0)).unwrap();
+        assert!(matches!(recorder.finish(), Err(RecordError::Io(_))));
+    }
+
+    #[test]
+    fn crc_matches_the_png_specification() {
+        assert_eq!(0xae42_6082, crc32(b"IEND"));
+    }
+}
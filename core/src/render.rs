@@ -0,0 +1,133 @@
+//! Rasterises a `GridViewModel` without a window, producing the same pixels
+//! `GridView` draws, so that headless tools and tests can save them.
+
+use crate::model::GridViewModel;
+use image::imageops::{self, FilterType};
+use image::{ImageBuffer, ImageResult, Rgba, RgbaImage};
+use std::path::Path;
+
+/// Converts a colour with channels in `0.0..=1.0` to 8 bits per channel.
+pub fn to_u8_pixel(pix: &[f32; 4]) -> Rgba<u8> {
+    const MAX_VAL: f32 = 255.0;
+    let [r, g, b, a] = pix;
+    let normalize = |c| (c * MAX_VAL) as u8;
+    Rgba([normalize(r), normalize(g), normalize(b), normalize(a)])
+}
+
+/// Draws each cell of `model` as a `scale x scale` square of pixels.
+/// Entities outside the model's grid are skipped.
+pub fn render(model: &GridViewModel, scale: u32) -> RgbaImage {
+    assert!(scale > 0, "cells must be at least one pixel across");
+    let mut img = ImageBuffer::from_pixel(
+        model.cols() as u32 * scale,
+        model.rows() as u32 * scale,
+        to_u8_pixel(&model.background_color()),
+    );
+    for entity in model.entities() {
+        if entity.row() >= model.rows() || entity.col() >= model.cols() {
+            continue;
+        }
+        let pixel = to_u8_pixel(&entity.color());
+        let (top, left) = (entity.row() as u32 * scale, entity.col() as u32 * scale);
+        for y in top..top + scale {
+            for x in left..left + scale {
+                img.put_pixel(x, y, pixel);
+            }
+        }
+    }
+    img
+}
+
+/// Draws only the entities of `model`, one pixel per cell, with their
+/// opacity scaled by `alpha` and the background left transparent, for
+/// laying over the grid.
+pub fn render_overlay(model: &GridViewModel, alpha: f32) -> RgbaImage {
+    let mut img = ImageBuffer::from_pixel(model.cols() as u32, model.rows() as u32, Rgba([0; 4]));
+    for entity in model.entities() {
+        if entity.row() >= model.rows() || entity.col() >= model.cols() {
+            continue;
+        }
+        let [r, g, b, a] = entity.color();
+        let pixel = to_u8_pixel(&[r, g, b, a * alpha]);
+        img.put_pixel(entity.col() as u32, entity.row() as u32, pixel);
+    }
+    img
+}
+
+/// Blows up an image rendered at scale 1 so that each pixel becomes a
+/// `scale x scale` square, the same as rendering it at `scale`.
+pub fn upscale(img: RgbaImage, scale: u32) -> RgbaImage {
+    assert!(scale > 0, "cells must be at least one pixel across");
+    if scale == 1 {
+        return img;
+    }
+    let (width, height) = img.dimensions();
+    imageops::resize(&img, width * scale, height * scale, FilterType::Nearest)
+}
+
+/// Renders `model` as `render` does and saves it as a PNG.
+pub fn save_png<P: AsRef<Path>>(model: &GridViewModel, scale: u32, path: P) -> ImageResult<()> {
+    render(model, scale).save_with_format(path, image::ImageFormat::Png)
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use crate::model::Entity;
+
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+    fn model() -> GridViewModel {
+        let entities = vec![Entity::new(RED, 0, 2), Entity::new(RED, 1, 0)];
+        GridViewModel::new(2, 3, entities, BLACK)
+    }
+
+    #[test]
+    fn draws_one_pixel_per_cell_at_scale_one() {
+        let img = render(&model(), 1);
+        assert_eq!((3, 2), img.dimensions());
+        assert_eq!(&Rgba([255, 0, 0, 255]), img.get_pixel(2, 0));
+        assert_eq!(&Rgba([255, 0, 0, 255]), img.get_pixel(0, 1));
+        assert_eq!(&Rgba([0, 0, 0, 255]), img.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn scales_cells_to_squares() {
+        let img = render(&model(), 4);
+        assert_eq!((12, 8), img.dimensions());
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let alive = (y / 4, x / 4) == (0, 2) || (y / 4, x / 4) == (1, 0);
+            assert_eq!(alive, pixel[0] == 255, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn upscaling_matches_rendering_at_that_scale() {
+        assert_eq!(render(&model(), 3), upscale(render(&model(), 1), 3));
+        assert_eq!(render(&model(), 1), upscale(render(&model(), 1), 1));
+    }
+
+    #[test]
+    fn overlays_are_transparent_apart_from_the_entities() {
+        let img = render_overlay(&model(), 0.5);
+        assert_eq!((3, 2), img.dimensions());
+        assert_eq!(&Rgba([255, 0, 0, 127]), img.get_pixel(2, 0));
+        assert_eq!(&Rgba([0, 0, 0, 0]), img.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn skips_entities_outside_the_grid() {
+        let model = GridViewModel::new(1, 1, vec![Entity::new(RED, 3, 3)], BLACK);
+        assert_eq!(&Rgba([0, 0, 0, 255]), render(&model, 1).get_pixel(0, 0));
+    }
+
+    #[test]
+    fn saved_pngs_hold_the_rendered_pixels() {
+        let path = std::env::temp_dir().join(format!("render-{}.png", std::process::id()));
+        save_png(&model(), 2, &path).unwrap();
+        let loaded = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(render(&model(), 2), loaded);
+    }
+}
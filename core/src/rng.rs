@@ -0,0 +1,112 @@
+//! A small seedable random number generator: xoshiro256** seeded through
+//! splitmix64, so that every seed, even 0, gives well mixed output and all
+//! of its bits are equally random.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Advances a splitmix64 state and returns its next output.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A seed that differs from run to run, for when none was asked for.
+pub fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0);
+    let mut state = nanos ^ u64::from(std::process::id()).rotate_left(32);
+    splitmix64(&mut state)
+}
+
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut seed = seed;
+        let state = [(); 4].map(|_| splitmix64(&mut seed));
+        Self { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    pub fn next_rnd(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// A number in `0.0..1.0`, with 53 random bits.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `true` with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+}
+
+impl Iterator for Rng {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_rnd())
+    }
+}
+
+#[cfg(test)]
+mod rng_tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_reference_xoshiro256_star_star() {
+        let mut rng = Rng::new(0);
+        assert_eq!(0x99ec_5f36_cb75_f2b4, rng.next_u64());
+        assert_eq!(0xbf6e_1f78_4956_452a, rng.next_u64());
+        assert_eq!(0x1a5f_849d, rng.next_rnd());
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        let a: Vec<u32> = Rng::new(96155).take(10).collect();
+        let b: Vec<u32> = Rng::new(96155).take(10).collect();
+        let c: Vec<u32> = Rng::new(96156).take(10).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn low_bits_are_as_random_as_high_bits() {
+        const SAMPLES: usize = 10_000;
+        for seed in [0, 1, 42] {
+            let odd = Rng::new(seed).take(SAMPLES).filter(|n| n % 2 == 1).count();
+            assert!((4_700..5_300).contains(&odd), "{} odd of {}", odd, SAMPLES);
+        }
+    }
+
+    #[test]
+    fn chances_come_up_about_as_often_as_asked() {
+        let mut rng = Rng::new(7);
+        assert!((0..1_000).all(|_| (0.0..1.0).contains(&rng.next_f64())));
+        let hits = (0..10_000).filter(|_| rng.chance(0.25)).count();
+        assert!((2_300..2_700).contains(&hits), "{}", hits);
+        assert!(!(0..100).any(|_| rng.chance(0.0)));
+        assert!((0..100).all(|_| rng.chance(1.0)));
+    }
+}
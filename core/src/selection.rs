@@ -0,0 +1,139 @@
+//! Rectangular selections of grid cells, and the edits `GridView` asks its
+//! `Controller` to make with them.
+
+/// A rectangle of cells, `rows x cols` with its top left corner at
+/// `(top, left)`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Region {
+    pub top: usize,
+    pub left: usize,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Region {
+    pub fn new(top: usize, left: usize, rows: usize, cols: usize) -> Self {
+        Self {
+            top,
+            left,
+            rows,
+            cols,
+        }
+    }
+
+    /// The smallest region holding both corners, whichever way round they
+    /// are.
+    pub fn spanning(a: (usize, usize), b: (usize, usize)) -> Self {
+        let (top, bottom) = (a.0.min(b.0), a.0.max(b.0));
+        let (left, right) = (a.1.min(b.1), a.1.max(b.1));
+        Self::new(top, left, bottom - top + 1, right - left + 1)
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        row >= self.top
+            && row < self.top + self.rows
+            && col >= self.left
+            && col < self.left + self.cols
+    }
+
+    /// The cells of the region, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.top..self.top + self.rows)
+            .flat_map(move |row| (self.left..self.left + self.cols).map(move |col| (row, col)))
+    }
+
+    /// Where the region's cells end up after `transform` is applied to them
+    /// in place, which keeps the top left corner where it is.
+    pub fn transformed(&self, transform: Transform) -> Self {
+        match transform {
+            Transform::RotateClockwise => Self::new(self.top, self.left, self.cols, self.rows),
+            Transform::FlipHorizontal | Transform::FlipVertical => *self,
+        }
+    }
+
+    /// The part of the region inside a `rows x cols` grid, if any.
+    pub fn clamped(&self, rows: usize, cols: usize) -> Option<Self> {
+        if self.top >= rows || self.left >= cols {
+            return None;
+        }
+        Some(Self::new(
+            self.top,
+            self.left,
+            self.rows.min(rows - self.top),
+            self.cols.min(cols - self.left),
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Transform {
+    /// A quarter turn clockwise.
+    RotateClockwise,
+    /// Mirrors left to right.
+    FlipHorizontal,
+    /// Mirrors top to bottom.
+    FlipVertical,
+}
+
+/// A change to the grid or the clipboard, in grid coordinates.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Edit {
+    /// Puts the region's cells on the clipboard.
+    Copy(Region),
+    /// Puts the region's cells on the clipboard and kills them.
+    Cut(Region),
+    /// Kills every cell in the region.
+    Clear(Region),
+    /// Fills the region with a random soup.
+    FillRandom(Region),
+    /// Transforms the region's cells in place, about its top left corner.
+    Transform(Region, Transform),
+    /// Transforms the clipboard ready for the next paste.
+    TransformClipboard(Transform),
+    /// Brings the clipboard's live cells to life with its top left corner
+    /// at `(row, col)`, leaving the cells under its dead ones alone.
+    Paste { row: usize, col: usize },
+}
+
+#[cfg(test)]
+mod selection_tests {
+    use super::*;
+
+    #[test]
+    fn spans_corners_in_any_order() {
+        let region = Region::new(2, 1, 3, 4);
+        assert_eq!(region, Region::spanning((2, 1), (4, 4)));
+        assert_eq!(region, Region::spanning((4, 4), (2, 1)));
+        assert_eq!(region, Region::spanning((2, 4), (4, 1)));
+        assert_eq!(Region::new(3, 3, 1, 1), Region::spanning((3, 3), (3, 3)));
+    }
+
+    #[test]
+    fn lists_cells_row_by_row() {
+        let region = Region::new(1, 2, 2, 2);
+        let cells: Vec<_> = region.cells().collect();
+        assert_eq!(vec![(1, 2), (1, 3), (2, 2), (2, 3)], cells);
+        assert!(cells.iter().all(|&(row, col)| region.contains(row, col)));
+        assert!(!region.contains(0, 2));
+        assert!(!region.contains(1, 4));
+    }
+
+    #[test]
+    fn rotating_swaps_the_dimensions() {
+        let region = Region::new(1, 2, 3, 5);
+        assert_eq!(
+            Region::new(1, 2, 5, 3),
+            region.transformed(Transform::RotateClockwise)
+        );
+        assert_eq!(region, region.transformed(Transform::FlipHorizontal));
+        assert_eq!(region, region.transformed(Transform::FlipVertical));
+    }
+
+    #[test]
+    fn clamping_trims_to_the_grid() {
+        let region = Region::new(1, 2, 5, 3);
+        assert_eq!(Some(region), region.clamped(10, 10));
+        assert_eq!(Some(Region::new(1, 2, 3, 2)), region.clamped(4, 4));
+        assert_eq!(None, region.clamped(1, 10));
+    }
+}
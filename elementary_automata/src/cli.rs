@@ -0,0 +1,447 @@
+//! Command line parsing for the elementary automata app. Each option
+//! overrides one `ElemAutomataApp` builder field, starting from the defaults
+//! `main` sets up.
+
+use crate::{ElemAutomataApp, InitialRow};
+use automata_core::cli::{invalid, parse_positive, parse_ups, parse_value, OptionError};
+use automata_core::color::{parse_color, rgba_to_hex};
+use automata_core::headless::{HeadlessOptions, DEFAULT_GENERATIONS, HEADLESS_OPTIONS};
+use automata_core::model::MAX_UPS;
+use std::error::Error;
+use std::fmt;
+
+/// Options that take a value, as written after the `--`.
+const VALUE_OPTIONS: [&str; 13] = [
+    "rule",
+    "width",
+    "height",
+    "ups",
+    "fps",
+    "live-color",
+    "dead-color",
+    "init",
+    "density",
+    "seed",
+    "image-dir",
+    "scale",
+    "step",
+];
+
+const DEFAULT_DENSITY: f64 = 0.5;
+const DEFAULT_SEED: u64 = 96155;
+
+pub enum Command {
+    Run(ElemAutomataApp),
+    Headless(ElemAutomataApp, HeadlessOptions),
+    Help(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliError {
+    Option(OptionError),
+    UnexpectedArgument(String),
+    RowTooWide { bits: usize, width: usize },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Option(e) => e.fmt(f),
+            Self::UnexpectedArgument(arg) => write!(
+                f,
+                "unexpected argument '{}', only the rule number can be given without an option",
+                arg
+            ),
+            Self::RowTooWide { bits, width } => write!(
+                f,
+                "the initial row has {} cells but the grid is only {} wide",
+                bits, width
+            ),
+        }
+    }
+}
+
+impl Error for CliError {}
+
+impl From<OptionError> for CliError {
+    fn from(e: OptionError) -> Self {
+        Self::Option(e)
+    }
+}
+
+fn usage(defaults: &ElemAutomataApp) -> String {
+    format!(
+        "\
+Usage: elementary_automata [OPTIONS] [RULE]
+       elementary_automata headless [OPTIONS] [RULE]
+
+Runs an elementary cellular automaton in a window, scrolling each new
+generation in at the bottom. The headless subcommand runs without a window
+instead, for batch jobs.
+
+Options:
+      --rule <0-255>       Wolfram rule number, the same as giving RULE [default: {rule}]
+      --width <N>          cells in each row [default: {width}]
+      --height <N>         generations kept on screen [default: {height}]
+      --ups <N>            generations per second, at most {max_ups} [default: {ups}]
+      --fps <N>            frames drawn per second [default: {fps}]
+      --live-color <COLOR> #rrggbb, #rrggbbaa or a colour name [default: {live}]
+      --dead-color <COLOR> colour of dead cells [default: {dead}]
+      --init <MODE>        first row: centre, random, or a string of 0s and 1s
+                           placed in the middle [default: centre]
+      --density <P>        chance of each random cell being alive [default: {density}]
+      --seed <N>           seed for the random first row [default: {seed}]
+      --image-dir <DIR>    where F10 and F12 save recordings and screenshots
+                           [default: {image_dir}]
+      --scale <N>          pixels per cell in recordings and screenshots [default: {scale}]
+      --step <N>           generations Return advances [default: {step}]
+  -h, --help               print this help
+
+Headless options:
+      --generations <N>    generations to run [default: {generations}]
+      --output <FILE>      save the final row here as 0s and 1s [default: stdout]
+      --stats <FILE>       write the population of each generation as CSV
+      --record <FILE>      save every generation as an animated .gif or .png
+
+Keys:
+  Space                    pause or resume
+  . (period)               advance one generation
+  Return                   advance --step generations
+  + and -                  double or halve the generations per second
+  Tab                      show or hide the generation, population and rates
+  Mouse wheel              zoom in or out around the cursor
+  Arrows or middle drag    pan
+  0                        zoom out to fit the whole grid in the window
+  X                        start again from a row of dead cells
+  Backspace                go back to the starting row
+  N                        start again from a new random row with a fresh seed
+  Ctrl+Z or Ctrl+Y         undo or redo the last X, Backspace or N
+  F10                      start or stop recording a GIF into --image-dir
+  F12                      save a PNG screenshot of the grid into --image-dir
+",
+        rule = defaults.rule_no,
+        width = defaults.cols,
+        height = defaults.rows,
+        ups = defaults.ups,
+        max_ups = MAX_UPS,
+        fps = defaults.fps,
+        live = rgba_to_hex(defaults.live_color),
+        dead = rgba_to_hex(defaults.dead_color),
+        density = DEFAULT_DENSITY,
+        seed = DEFAULT_SEED,
+        image_dir = defaults.image_dir.display(),
+        scale = defaults.scale,
+        step = defaults.step,
+        generations = DEFAULT_GENERATIONS,
+    )
+}
+
+fn parse_rule_no(value: &str) -> Result<u8, OptionError> {
+    value
+        .parse()
+        .map_err(|_| invalid("rule", value, "must be a whole number from 0 to 255"))
+}
+
+/// The options that shape the first row, which only make sense together.
+struct RowOptions {
+    mode: String,
+    density: f64,
+    seed: u64,
+}
+
+impl RowOptions {
+    fn initial_row(&self) -> Result<InitialRow, OptionError> {
+        match self.mode.to_ascii_lowercase().as_str() {
+            "centre" | "center" => Ok(InitialRow::Centre),
+            "random" => Ok(InitialRow::Random {
+                density: self.density,
+                seed: self.seed,
+            }),
+            bits if !bits.is_empty() && bits.chars().all(|c| c == '0' || c == '1') => {
+                Ok(InitialRow::Bits(bits.bytes().map(|b| b - b'0').collect()))
+            }
+            _ => Err(invalid(
+                "init",
+                &self.mode,
+                "expected centre, random or a string of 0s and 1s",
+            )),
+        }
+    }
+}
+
+/// Applies the command line arguments, without the program name, to
+/// `defaults`.
+pub fn parse_args<I>(defaults: ElemAutomataApp, args: I) -> Result<Command, CliError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let help = usage(&defaults);
+    let mut app = defaults;
+    let mut row = RowOptions {
+        mode: "centre".to_string(),
+        density: DEFAULT_DENSITY,
+        seed: DEFAULT_SEED,
+    };
+    let mut positional_rule = false;
+    let mut args = args.into_iter().peekable();
+    let mut headless = args
+        .next_if(|arg| arg == "headless")
+        .map(|_| HeadlessOptions::default());
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help(help));
+        }
+        let Some(option) = arg.strip_prefix("--") else {
+            if arg.starts_with('-') {
+                return Err(OptionError::UnknownOption(arg).into());
+            }
+            if positional_rule {
+                return Err(CliError::UnexpectedArgument(arg));
+            }
+            positional_rule = true;
+            app = app.rule_no(parse_rule_no(&arg)?);
+            continue;
+        };
+
+        let (name, inline_value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (option, None),
+        };
+        let headless_options: &[&'static str] = match headless {
+            Some(_) => &HEADLESS_OPTIONS,
+            None => &[],
+        };
+        let option = *VALUE_OPTIONS
+            .iter()
+            .chain(headless_options)
+            .find(|&&known| known == name)
+            .ok_or_else(|| OptionError::UnknownOption(arg.clone()))?;
+        let value = match inline_value {
+            Some(value) => value,
+            None => args.next().ok_or(OptionError::MissingValue(option))?,
+        };
+        let color =
+            |value: &str| parse_color(value).map_err(|e| invalid(option, value, &e.to_string()));
+        app = match option {
+            "rule" => app.rule_no(parse_rule_no(&value)?),
+            "width" => app.cols(parse_positive(option, &value)?),
+            "height" => app.rows(parse_positive(option, &value)?),
+            "ups" => app.ups(parse_ups(option, &value)?),
+            "fps" => app.fps(parse_positive(option, &value)?),
+            "live-color" => app.live_color(color(&value)?),
+            "dead-color" => app.dead_color(color(&value)?),
+            "init" => {
+                row.mode = value;
+                app
+            }
+            "density" => {
+                row.density = parse_value(option, &value)?;
+                if !(0.0..=1.0).contains(&row.density) {
+                    return Err(invalid(option, &value, "must be between 0 and 1").into());
+                }
+                app
+            }
+            "seed" => {
+                row.seed = parse_value(option, &value)?;
+                app
+            }
+            "image-dir" => app.image_dir(value),
+            "scale" => app.scale(parse_positive(option, &value)?),
+            "step" => app.step(parse_positive(option, &value)?),
+            _ if HEADLESS_OPTIONS.contains(&option) => {
+                let options = headless.as_mut().expect("only headless takes these");
+                options.apply(option, &value)?;
+                app
+            }
+            _ => unreachable!("'{}' is not in VALUE_OPTIONS", option),
+        };
+    }
+
+    let initial_row = row.initial_row()?;
+    if let InitialRow::Bits(bits) = &initial_row {
+        if bits.len() > app.cols {
+            return Err(CliError::RowTooWide {
+                bits: bits.len(),
+                width: app.cols,
+            });
+        }
+    }
+    let app = app.initial_row(initial_row);
+    Ok(match headless {
+        Some(options) => Command::Headless(app, options),
+        None => Command::Run(app),
+    })
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn parse(args: &[&str]) -> Result<ElemAutomataApp, CliError> {
+        let args = args.iter().map(|arg| arg.to_string());
+        match parse_args(ElemAutomataApp::new(90), args)? {
+            Command::Run(app) => Ok(app),
+            _ => panic!("expected options to run the app"),
+        }
+    }
+
+    #[test]
+    fn no_arguments_keep_the_defaults() {
+        let app = parse(&[]).unwrap();
+        assert_eq!(90, app.rule_no);
+        assert_eq!(InitialRow::Centre, app.initial_row);
+    }
+
+    #[test]
+    fn every_builder_field_can_be_set() {
+        let app = parse(&[
+            "30",
+            "--width=40",
+            "--height",
+            "20",
+            "--ups",
+            "3",
+            "--fps",
+            "24",
+            "--live-color",
+            "#4ca8bf",
+            "--dead-color",
+            "bfa84c",
+            "--image-dir",
+            "shots",
+            "--scale",
+            "2",
+            "--step",
+            "50",
+        ])
+        .unwrap();
+        assert_eq!(30, app.rule_no);
+        assert_eq!((20, 40, 3, 24), (app.rows, app.cols, app.ups, app.fps));
+        assert_eq!("#4ca8bf", rgba_to_hex(app.live_color));
+        assert_eq!("#bfa84c", rgba_to_hex(app.dead_color));
+        assert_eq!((PathBuf::from("shots"), 2), (app.image_dir, app.scale));
+        assert_eq!(50, app.step);
+    }
+
+    #[test]
+    fn reads_each_initial_row_mode() {
+        let app = parse(&["--init", "random", "--seed", "7", "--density", "0.1"]).unwrap();
+        assert_eq!(
+            InitialRow::Random {
+                density: 0.1,
+                seed: 7
+            },
+            app.initial_row
+        );
+        let app = parse(&["--init", "1011"]).unwrap();
+        assert_eq!(InitialRow::Bits(vec![1, 0, 1, 1]), app.initial_row);
+        let app = parse(&["--init", "Center"]).unwrap();
+        assert_eq!(InitialRow::Centre, app.initial_row);
+    }
+
+    #[test]
+    fn help_lists_every_option_with_its_default() {
+        let args = ["--help"].map(String::from);
+        let Ok(Command::Help(usage)) = parse_args(ElemAutomataApp::new(110), args) else {
+            panic!("expected --help");
+        };
+        for option in VALUE_OPTIONS {
+            assert!(usage.contains(&format!("--{} <", option)), "{}", option);
+        }
+        assert!(usage.contains("Wolfram rule number, the same as giving RULE [default: 110]"));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert_eq!(
+            "invalid value '256' for '--rule': must be a whole number from 0 to 255",
+            parse(&["--rule", "256"]).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "invalid value '0' for '--width': must be at least 1",
+            parse(&["--width", "0"]).unwrap_err().to_string()
+        );
+        assert!(parse(&["--live-color", "#12345"]).is_err());
+        assert!(parse(&["--init", "10201"]).is_err());
+        assert!(parse(&["--density", "-0.5"]).is_err());
+        assert!(parse(&["--seed", "soon"]).is_err());
+    }
+
+    #[test]
+    fn rejects_an_initial_row_wider_than_the_grid() {
+        assert_eq!(
+            Some(CliError::RowTooWide { bits: 5, width: 4 }),
+            parse(&["--width", "4", "--init", "10101"]).err()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_command_lines() {
+        assert_eq!(
+            Some(CliError::Option(OptionError::UnknownOption(
+                "--rows".to_string()
+            ))),
+            parse(&["--rows", "3"]).err()
+        );
+        assert_eq!(
+            Some(CliError::Option(OptionError::MissingValue("init"))),
+            parse(&["--init"]).err()
+        );
+        assert_eq!(
+            Some(CliError::UnexpectedArgument("30".to_string())),
+            parse(&["90", "30"]).err()
+        );
+    }
+
+    #[test]
+    fn random_rows_follow_their_seed_and_density() {
+        let row = |density, seed| InitialRow::Random { density, seed }.cells(1000);
+        assert_eq!(row(0.5, 1), row(0.5, 1));
+        assert_ne!(row(0.5, 1), row(0.5, 2));
+        assert_eq!(0, row(0.0, 1).iter().filter(|&&c| c == 1).count());
+        assert_eq!(1000, row(1.0, 1).iter().filter(|&&c| c == 1).count());
+        let alive = row(0.3, 1).iter().filter(|&&c| c == 1).count();
+        assert!((250..350).contains(&alive), "{}", alive);
+    }
+
+    #[test]
+    fn headless_takes_its_own_options_and_the_app_options() {
+        let args = [
+            "headless",
+            "30",
+            "--generations=64",
+            "--output",
+            "row.txt",
+            "--stats",
+            "stats.csv",
+            "--record=run.png",
+            "--width",
+            "101",
+        ]
+        .map(String::from);
+        let Ok(Command::Headless(app, options)) = parse_args(ElemAutomataApp::new(90), args) else {
+            panic!("expected headless");
+        };
+        assert_eq!(
+            HeadlessOptions {
+                generations: 64,
+                output: Some(PathBuf::from("row.txt")),
+                stats: Some(PathBuf::from("stats.csv")),
+                record: Some(PathBuf::from("run.png")),
+            },
+            options
+        );
+        assert_eq!((30, 101), (app.rule_no, app.cols));
+    }
+
+    #[test]
+    fn headless_options_are_only_known_to_headless() {
+        assert_eq!(
+            Some(CliError::Option(OptionError::UnknownOption(
+                "--stats".to_string()
+            ))),
+            parse(&["--stats", "stats.csv"]).err()
+        );
+    }
+}
@@ -1,7 +1,9 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ElemAutomata {
     cells: Vec<u8>,
-    lookup_table: [u8; 8]
+    rule_no: u8,
+    lookup_table: [u8; 8],
+    generation: u64,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -12,21 +14,32 @@ pub enum CellState {
 
 impl ElemAutomata {
     pub fn new(rule_no: u8, init_cells: &[u8]) -> Self {
-        assert!(init_cells.len() > 0, "Must init with at lease one cell");
+        assert!(!init_cells.is_empty(), "Must init with at lease one cell");
         Self {
             cells: init_cells.to_vec(),
+            rule_no,
             lookup_table: Self::build_lookup_table(rule_no),
+            generation: 0,
         }
     }
 
+    pub fn rule_no(&self) -> u8 {
+        self.rule_no
+    }
+
+    /// How many times `next_gen` has been called.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     
     fn build_lookup_table(rule_no: u8) -> [u8; 8] {
         const BITS_PER_BYTE: usize = 8;
         let mut out = [0; BITS_PER_BYTE];
         let mut n = rule_no;
-        for i in 0..BITS_PER_BYTE {
-            out[i] = n & 1;
-            n = n >> 1;
+        for bit in out.iter_mut() {
+            *bit = n & 1;
+            n >>= 1;
         }
         out
     }
@@ -44,14 +57,14 @@ impl ElemAutomata {
 
         for chunk in padded_cells.windows(3) {
             let mut n = 0;
-            for &bit in chunk.into_iter() {
-                n = n << 1;
-                n = n | *bit as usize;
+            for &bit in chunk.iter() {
+                n <<= 1;
+                n |= *bit as usize;
             }
             new_cells.push(self.lookup_table[n]);
         }
         self.cells = new_cells;
-        
+        self.generation += 1;
     }
 
     pub fn cells(&self) -> Vec<CellState> {
@@ -81,5 +94,6 @@ mod elementary_tests {
         ];
         ea.next_gen();
         assert_eq!(expected, ea.cells);
+        assert_eq!(1, ea.generation());
     }
 }
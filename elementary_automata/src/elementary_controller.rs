@@ -1,40 +1,52 @@
-use automata_core::grid_view::{Controller, GridViewModel, Entity};
+use automata_core::model::{Controller, GridViewModel, Entity};
+use automata_core::rng::Rng;
 use std::collections::VecDeque;
+use std::mem;
 use crate::elementary::{CellState, ElemAutomata};
+use crate::InitialRow;
 
-pub struct ElemAutomataController {
-    model: ElemAutomata,
+/// The most recent generations, oldest first, as they are drawn from the
+/// top of the grid down.
+#[derive(Clone)]
+pub struct History {
     max_height: usize,
     width: usize,
-    live_color: [f32; 4],
-    dead_color: [f32; 4],
     generations: VecDeque<Vec<CellState>>,
 }
 
-impl ElemAutomataController {
-    pub fn new(
-        model: ElemAutomata,
-        max_height: usize,
-        live_color: [f32; 4],
-        dead_color: [f32; 4]
-    ) -> Self {
-        let width = model.cells().len();
+impl History {
+    pub fn new(max_height: usize, width: usize) -> Self {
         Self {
-            model,
             max_height,
-            live_color,
-            dead_color,
             width,
             generations: VecDeque::new(),
         }
     }
-    
-    fn build_view_model(&self) -> GridViewModel {
+
+    /// Adds a generation at the bottom, scrolling the oldest off the top
+    /// once the grid is full.
+    pub fn push(&mut self, cells: Vec<CellState>) {
+        self.generations.push_back(cells);
+        if self.generations.len() > self.max_height {
+            self.generations.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.generations.clear();
+    }
+
+    /// The generation at the bottom of the grid.
+    pub fn latest(&self) -> Option<&[CellState]> {
+        self.generations.back().map(Vec::as_slice)
+    }
+
+    pub fn view_model(&self, live_color: [f32; 4], dead_color: [f32; 4]) -> GridViewModel {
         let mut entities = Vec::new();
         for (r, row) in self.generations.iter().enumerate() {
             for (c, &cell) in row.iter().enumerate() {
                 if cell == CellState::Alive {
-                    let entity = Entity::new(self.live_color, r, c);
+                    let entity = Entity::new(live_color, r, c);
                     entities.push(entity);
                 }
             }
@@ -43,18 +55,94 @@ impl ElemAutomataController {
             self.max_height,
             self.width,
             entities,
-            self.dead_color
+            dead_color
+        )
+    }
+}
+
+pub struct ElemAutomataController {
+    model: ElemAutomata,
+    live_color: [f32; 4],
+    dead_color: [f32; 4],
+    history: History,
+    /// What `reset` goes back to: the automaton the controller was made
+    /// with, or the last new soup.
+    initial: ElemAutomata,
+    /// Seeds new soups.
+    rng: Rng,
+    /// The fraction of living cells in a new soup.
+    density: f64,
+    /// The automaton and grid from before the last clear, reset or new
+    /// soup, and from before that was undone.
+    undo: Option<(ElemAutomata, History)>,
+    redo: Option<(ElemAutomata, History)>,
+}
+
+impl ElemAutomataController {
+    pub fn new(
+        model: ElemAutomata,
+        max_height: usize,
+        live_color: [f32; 4],
+        dead_color: [f32; 4]
+    ) -> Self {
+        let width = model.cells().len();
+        Self {
+            initial: model.clone(),
+            model,
+            live_color,
+            dead_color,
+            history: History::new(max_height, width),
+            rng: Rng::new(0),
+            density: 0.5,
+            undo: None,
+            redo: None,
+        }
+    }
+
+    /// Seeds the random numbers that new soups take their seeds from.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            ..self
+        }
+    }
+
+    /// The fraction of living cells in a new soup.
+    pub fn with_density(self, density: f64) -> Self {
+        Self {
+            density,
+            ..self
+        }
+    }
+
+    /// Starts the grid again from `model`, keeping what was there to undo
+    /// back to.
+    fn replace(&mut self, model: ElemAutomata) {
+        let mut history = self.history.clone();
+        history.clear();
+        let old_model = mem::replace(&mut self.model, model);
+        let old_history = mem::replace(&mut self.history, history);
+        self.undo = Some((old_model, old_history));
+        self.redo = None;
+    }
+
+    /// Puts `state` back, returning what it replaced.
+    fn restore(&mut self, (model, history): (ElemAutomata, History)) -> (ElemAutomata, History) {
+        (
+            mem::replace(&mut self.model, model),
+            mem::replace(&mut self.history, history),
         )
     }
+    
+    fn build_view_model(&self) -> GridViewModel {
+        self.history.view_model(self.live_color, self.dead_color)
+    }
 }
 
 
 impl Controller for ElemAutomataController {
     fn update(&mut self) {
-        self.generations.push_back(self.model.cells());
-        if self.generations.len() > self.max_height {
-            self.generations.pop_front();
-        }
+        self.history.push(self.model.cells());
         self.model.next_gen();
     }
 
@@ -62,12 +150,117 @@ impl Controller for ElemAutomataController {
         // no-op
     }
 
+    /// Starts again from a row of dead cells.
     fn clear(&mut self) {
-        // no-op
+        let width = self.model.cells().len();
+        self.replace(ElemAutomata::new(self.model.rule_no(), &vec![0; width]));
+    }
+
+    fn reset(&mut self) {
+        self.replace(self.initial.clone());
+    }
+
+    /// Starts again from a random row seeded from the controller's own
+    /// random numbers, which then becomes what `reset` goes back to.
+    fn new_soup(&mut self) {
+        let seed = self.rng.next_rnd().into();
+        let row = InitialRow::Random { density: self.density, seed };
+        let cells = row.cells(self.model.cells().len());
+        self.initial = ElemAutomata::new(self.model.rule_no(), &cells);
+        self.replace(self.initial.clone());
+    }
+
+    fn undo(&mut self) {
+        if let Some(state) = self.undo.take() {
+            self.redo = Some(self.restore(state));
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.redo.take() {
+            self.undo = Some(self.restore(state));
+        }
     }
 
     fn model(&self) -> GridViewModel {
         self.build_view_model()
     }
+
+    fn status(&self) -> Vec<(String, String)> {
+        // The automaton is always a generation ahead of the bottom row.
+        let (generation, population) = match self.history.latest() {
+            Some(row) => {
+                let alive = row.iter().filter(|&&cell| cell == CellState::Alive).count();
+                (self.model.generation() - 1, alive)
+            }
+            None => (0, 0),
+        };
+        vec![
+            ("generation".to_string(), generation.to_string()),
+            ("population".to_string(), population.to_string()),
+            ("rule".to_string(), self.model.rule_no().to_string()),
+        ]
+    }
 }
 
+
+#[cfg(test)]
+mod elementary_controller_tests {
+    use super::*;
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+    fn controller() -> ElemAutomataController {
+        let model = ElemAutomata::new(90, &[0, 0, 0, 1, 0, 0, 0, 0]);
+        ElemAutomataController::new(model, 4, WHITE, BLACK)
+    }
+
+    fn population(controller: &ElemAutomataController) -> usize {
+        controller.model.cells().iter().filter(|&&cell| cell == CellState::Alive).count()
+    }
+
+    #[test]
+    fn clearing_kills_the_row_and_empties_the_grid() {
+        let mut controller = controller();
+        controller.update();
+        controller.clear();
+        assert_eq!(0, population(&controller));
+        assert_eq!(0, controller.model.generation());
+        assert!(controller.model().entities().is_empty());
+    }
+
+    #[test]
+    fn resets_and_undoes_back_to_where_it_was() {
+        let mut controller = controller();
+        let start = controller.model.cells();
+        controller.update();
+        controller.update();
+        let running = controller.model.cells();
+        controller.reset();
+        assert_eq!(start, controller.model.cells());
+        assert_eq!(0, controller.model.generation());
+
+        controller.undo();
+        assert_eq!(running, controller.model.cells());
+        assert_eq!(2, controller.history.generations.len());
+        controller.redo();
+        assert_eq!(start, controller.model.cells());
+    }
+
+    #[test]
+    fn new_soups_become_the_reset_point() {
+        let model = ElemAutomata::new(30, &[0; 32]);
+        let mut controller = ElemAutomataController::new(model, 4, WHITE, BLACK)
+            .with_seed(11)
+            .with_density(0.5);
+        controller.new_soup();
+        let soup = controller.model.cells();
+        controller.update();
+        controller.reset();
+        assert_eq!(soup, controller.model.cells());
+        controller.new_soup();
+        controller.new_soup();
+        assert_ne!(soup, controller.model.cells());
+    }
+}
@@ -0,0 +1,47 @@
+//! Lets an automaton be run with no window by `automata_core::headless`,
+//! for batch jobs on machines without a display.
+
+use crate::elementary::{CellState, ElemAutomata};
+use automata_core::headless::Automaton;
+
+impl Automaton for ElemAutomata {
+    fn step(&mut self) {
+        self.next_gen();
+    }
+
+    fn alive_cells(&self) -> Vec<bool> {
+        self.cells()
+            .into_iter()
+            .map(|cell| cell == CellState::Alive)
+            .collect()
+    }
+}
+
+/// Writes a row as `0`s and `1`s, the form `--init` reads back.
+pub fn row_to_string(cells: &[CellState]) -> String {
+    cells
+        .iter()
+        .map(|&cell| match cell {
+            CellState::Alive => '1',
+            CellState::Dead => '0',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod headless_tests {
+    use super::*;
+    use automata_core::headless::run_with_csv;
+
+    #[test]
+    fn writes_an_automaton_s_stats_as_csv() {
+        let mut automaton = ElemAutomata::new(90, &[0, 0, 1, 0, 0]);
+        let mut out = Vec::new();
+        run_with_csv(&mut automaton, 1, &mut out).unwrap();
+        assert_eq!(
+            "generation,population,births,deaths\n0,1,0,0\n1,2,2,1\n",
+            String::from_utf8(out).unwrap()
+        );
+        assert_eq!("01010", row_to_string(&automaton.cells()));
+    }
+}
@@ -1,10 +1,56 @@
+mod cli;
 mod elementary;
 mod elementary_controller;
+mod headless;
 
 use elementary::ElemAutomata;
-use elementary_controller::ElemAutomataController;
+use elementary_controller::{ElemAutomataController, History};
+use automata_core::color::hex_to_rgba;
+#[cfg(feature = "window")]
 use automata_core::grid_view::GridView;
+use automata_core::headless::{HeadlessOptions, Outputs};
+use automata_core::model::Controller;
+use automata_core::rng::Rng;
+use cli::Command;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 
+/// How the first row is filled in.
+#[derive(Debug, PartialEq, Clone)]
+pub enum InitialRow {
+    /// A single living cell in the middle.
+    Centre,
+    /// Each cell alive with probability `density`.
+    Random { density: f64, seed: u64 },
+    /// These cells, centred in the row.
+    Bits(Vec<u8>),
+}
+
+impl InitialRow {
+    fn cells(&self, width: usize) -> Vec<u8> {
+        let mut cells = vec![0; width];
+        match self {
+            Self::Centre => cells[width / 2] = 1,
+            Self::Random { density, seed } => {
+                let threshold = density * (u32::MAX as f64 + 1.0);
+                for (cell, n) in cells.iter_mut().zip(Rng::new(*seed)) {
+                    *cell = u8::from((n as f64) < threshold);
+                }
+            }
+            Self::Bits(bits) => {
+                assert!(bits.len() <= width, "initial row is wider than the grid");
+                let left = (width - bits.len()) / 2;
+                cells[left..left + bits.len()].copy_from_slice(bits);
+            }
+        }
+        cells
+    }
+}
+
+#[derive(Debug)]
 struct ElemAutomataApp {
     rule_no: u8,
     fps: u64,
@@ -13,6 +59,10 @@ struct ElemAutomataApp {
     cols: usize,
     live_color: [f32; 4],
     dead_color: [f32; 4],
+    initial_row: InitialRow,
+    image_dir: PathBuf,
+    scale: u32,
+    step: u64,
 }
 
 impl ElemAutomataApp {
@@ -25,78 +75,156 @@ impl ElemAutomataApp {
             cols: 90,
             live_color: [1.0, 1.0, 1.0, 1.0], // WHITE
             dead_color: [0.0, 0.0, 0.0, 1.0], //BLACK
+            initial_row: InitialRow::Centre,
+            image_dir: PathBuf::from("."),
+            scale: 1,
+            step: 10,
+        }
+    }
+
+    pub fn rule_no(self, rule_no: u8) -> Self {
+        Self {
+            rule_no,
+            ..self
         }
     }
 
-    pub fn fps(&self, fps: u64) -> Self {
+    pub fn fps(self, fps: u64) -> Self {
         Self {
             fps,
-            ..*self
+            ..self
         }
     }
 
-    pub fn ups(&self, ups: u64) -> Self {
+    pub fn ups(self, ups: u64) -> Self {
         Self {
             ups,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn rows(&self, rows: usize) -> Self {
+    pub fn rows(self, rows: usize) -> Self {
         Self {
             rows,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn cols(&self, cols: usize) -> Self {
+    pub fn cols(self, cols: usize) -> Self {
         Self {
             cols,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn live_color(&self, live_color: [f32; 4]) -> Self {
+    pub fn live_color(self, live_color: [f32; 4]) -> Self {
         Self {
             live_color,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn dead_color(&self, dead_color: [f32; 4]) -> Self {
+    pub fn dead_color(self, dead_color: [f32; 4]) -> Self {
         Self {
             dead_color,
-            ..*self
+            ..self
         }        
     }
+
+    /// Panics in `start` if a `Bits` row is wider than the grid.
+    pub fn initial_row(self, initial_row: InitialRow) -> Self {
+        Self {
+            initial_row,
+            ..self
+        }
+    }
+
+    /// Where recordings made with F10 and screenshots taken with F12 are
+    /// saved.
+    pub fn image_dir<P: Into<PathBuf>>(self, image_dir: P) -> Self {
+        Self {
+            image_dir: image_dir.into(),
+            ..self
+        }
+    }
+
+    /// Pixels per cell in recordings and screenshots.
+    pub fn scale(self, scale: u32) -> Self {
+        Self {
+            scale,
+            ..self
+        }
+    }
+
+    /// How many generations Return advances.
+    pub fn step(self, step: u64) -> Self {
+        Self {
+            step,
+            ..self
+        }
+    }
     
-    pub fn start(&self) {
-        let mut cells = vec![0; self.cols];
-        cells[self.cols / 2] = 1;
+    pub fn start(&self) -> Result<(), Box<dyn Error>> {
+        let cells = self.initial_row.cells(self.cols);
         let universe = ElemAutomata::new(self.rule_no, &cells);
-        let controller = ElemAutomataController::new(
+        let mut controller = ElemAutomataController::new(
                 universe, self.rows, self.live_color, self.dead_color);
+        if let InitialRow::Random { density, seed } = self.initial_row {
+            controller = controller.with_seed(seed).with_density(density);
+        }
+        self.run(&format!("Rule {}", self.rule_no), controller)
+    }
 
+    #[cfg(feature = "window")]
+    fn run<C: Controller>(&self, title: &str, controller: C) -> Result<(), Box<dyn Error>> {
         let mut view = GridView::new(
-            &format!("Rule {}", self.rule_no),
+            title,
             self.fps,
             self.ups,
             controller
-        );
-        view.game_loop();    
+        )
+        .with_output_dir(&self.image_dir)
+        .with_image_scale(self.scale)
+        .with_step_size(self.step);
+        view.game_loop()
+    }
+
+    #[cfg(not(feature = "window"))]
+    fn run<C: Controller>(&self, _title: &str, _controller: C) -> Result<(), Box<dyn Error>> {
+        Err("built without the window feature, so only headless runs are possible".into())
     }
-}
 
+    /// Runs without a window, writing the final row and, if asked for,
+    /// per-generation stats and a recording.
+    pub fn run_headless(&self, options: &HeadlessOptions) -> Result<(), Box<dyn Error>> {
+        let cells = self.initial_row.cells(self.cols);
+        let mut automaton = ElemAutomata::new(self.rule_no, &cells);
+        let in_file = |path: &Path, e: &dyn Error| format!("{}: {}", path.display(), e);
+        let mut outputs = Outputs::create(options, self.ups, self.scale)?;
+        let mut history = History::new(self.rows, self.cols);
+        let generations = options.generations;
+        let mut result = Ok(());
+        automata_core::headless::run(&mut automaton, generations, |automaton, generation| {
+            if result.is_ok() {
+                result = outputs.write(generation, || {
+                    history.push(automaton.cells());
+                    history.view_model(self.live_color, self.dead_color)
+                });
+            }
+        });
+        result?;
+        outputs.finish()?;
 
-fn hex_to_rgba(hex_color: u32) -> [f32; 4] {
-    //rrggbb
-    let r = (hex_color >> (8 * 2)) & 0xFF;
-    let g = (hex_color >> (8 * 1)) & 0xFF;
-    let b = (hex_color >> (8 * 0)) & 0xFF;
-    const MAX_HEX: f32 = 255.0;
-    [r as f32 / MAX_HEX, g as f32 / MAX_HEX, b as f32 / MAX_HEX, 1.0]
+        let row = headless::row_to_string(&automaton.cells());
+        match &options.output {
+            Some(path) => fs::write(path, row + "\n").map_err(|e| in_file(path, &e))?,
+            None => println!("{}", row),
+        }
+        Ok(())
+    }
 }
 
+
 fn main() {
     const RULE_NO: u8 = 90;
     const ROWS: usize = 10 * 25;
@@ -106,13 +234,32 @@ fn main() {
     let bluish: [f32; 4] = hex_to_rgba(0x4ca8bf);
     let golden: [f32; 4] = hex_to_rgba(0xbfa84c);
     
-    ElemAutomataApp::new(RULE_NO)
+    let defaults = ElemAutomataApp::new(RULE_NO)
         .rows(ROWS)
         .cols(COLS)
         .ups(UPS)
         .fps(FPS)
         .live_color(bluish)
-        .dead_color(golden)
-        .start();
-    
+        .dead_color(golden);
+
+    match cli::parse_args(defaults, env::args().skip(1)) {
+        Ok(Command::Help(usage)) => print!("{}", usage),
+        Ok(Command::Run(app)) => {
+            if let Err(e) = app.start() {
+                eprintln!("elementary_automata: {}", e);
+                process::exit(1);
+            }
+        }
+        Ok(Command::Headless(app, options)) => {
+            if let Err(e) = app.run_headless(&options) {
+                eprintln!("elementary_automata: {}", e);
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("elementary_automata: {}", e);
+            eprintln!("Try 'elementary_automata --help' for more information.");
+            process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,78 @@
+//! Compares stepping `Universe` with stepping the bit-packed `BitUniverse`,
+//! each on one thread. Run with `cargo bench`, or `cargo bench --features
+//! parallel` to also time the multi-threaded `par_next_gen` of each, with
+//! its speedup over one thread in brackets.
+
+use game_of_life::bit_universe::BitUniverse;
+use game_of_life::game_of_life::{Cell, Universe};
+use game_of_life::rng::Rng;
+use game_of_life::topology::Topology;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+fn soup(height: usize, width: usize) -> Universe {
+    let mut rng = Rng::new(96155);
+    let cells: Vec<Cell> = (0..height * width)
+        .map(|_| {
+            if rng.next_rnd() >> 16 & 1 == 1 {
+                Cell::Alive
+            } else {
+                Cell::Dead
+            }
+        })
+        .collect();
+    Universe::new(&cells, width).with_topology(Topology::Torus)
+}
+
+fn time_per_gen(generations: u32, mut step: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..generations {
+        step();
+    }
+    start.elapsed() / generations
+}
+
+fn main() {
+    println!(
+        "{:>11} {:>20} {:>20} {:>8}",
+        "grid", "Universe", "BitUniverse", "speedup"
+    );
+    for size in [128, 512, 1024] {
+        let generations = (1 << 22) / (size * size) as u32 + 4;
+        let mut universe = soup(size, size);
+        let mut bits = BitUniverse::from_universe(&universe);
+
+        let dense = time_per_gen(generations, || black_box(&mut universe).serial_next_gen());
+        let packed = time_per_gen(generations * 16, || black_box(&mut bits).serial_next_gen());
+        println!(
+            "{:>11} {:>20?} {:>20?} {:>7.1}x",
+            format!("{}x{}", size, size),
+            dense,
+            packed,
+            dense.as_secs_f64() / packed.as_secs_f64()
+        );
+
+        #[cfg(feature = "parallel")]
+        {
+            use game_of_life::engine::default_threads;
+            let threads = default_threads();
+            let par_dense = time_per_gen(generations, || {
+                black_box(&mut universe).par_next_gen(threads)
+            });
+            let par_packed = time_per_gen(generations * 16, || {
+                black_box(&mut bits).par_next_gen(threads)
+            });
+            let speedup = |serial: Duration, parallel: Duration| {
+                let ratio = serial.as_secs_f64() / parallel.as_secs_f64();
+                format!("{:?} ({:.1}x)", parallel, ratio)
+            };
+            println!(
+                "{:>11} {:>20} {:>20} {:>7.1}x",
+                format!("{} threads", threads),
+                speedup(dense, par_dense),
+                speedup(packed, par_packed),
+                par_dense.as_secs_f64() / par_packed.as_secs_f64()
+            );
+        }
+    }
+}
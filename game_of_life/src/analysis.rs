@@ -0,0 +1,298 @@
+//! Works out what a universe settles into by hashing its state every
+//! generation and watching for one to come round again, either exactly or
+//! moved along, as spaceships are. Any `LifeEngine` can be watched.
+
+use crate::engine::LifeEngine;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// What kind of object a cycle is.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CycleKind {
+    /// Unchanging from one generation to the next. A universe that has
+    /// died out is an empty still life.
+    StillLife,
+    /// Returns to the same cells after more than one generation.
+    Oscillator,
+    /// Returns to the same shape somewhere else.
+    Spaceship,
+}
+
+/// A state that repeats: the one at generation `start` comes round again
+/// every `period` generations, moved `displacement` rows and columns.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cycle {
+    pub start: u64,
+    pub period: u64,
+    pub displacement: (i64, i64),
+}
+
+impl Cycle {
+    pub fn kind(&self) -> CycleKind {
+        if self.displacement != (0, 0) {
+            CycleKind::Spaceship
+        } else if self.period == 1 {
+            CycleKind::StillLife
+        } else {
+            CycleKind::Oscillator
+        }
+    }
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind() {
+            CycleKind::StillLife => write!(f, "still life"),
+            CycleKind::Oscillator => write!(f, "oscillator of period {}", self.period),
+            CycleKind::Spaceship => {
+                let (rows, cols) = self.displacement;
+                write!(
+                    f,
+                    "spaceship of period {} moving ({}, {})",
+                    self.period, rows, cols
+                )
+            }
+        }?;
+        write!(f, " from generation {}", self.start)
+    }
+}
+
+/// The hash of an engine's live cells relative to the top left corner of
+/// their bounding box, and where that corner is. Two states with the same
+/// hash are the same shape, and the same cells too if their corners match.
+fn shape<E: LifeEngine>(engine: &E) -> (u64, (i64, i64)) {
+    let viewport = engine.default_viewport();
+    let mut live = engine.live_cells_in(&viewport);
+    // Engines list their cells in different orders
+    live.sort_unstable();
+    let top = live.iter().map(|&(row, _)| row).min().unwrap_or(0);
+    let left = live.iter().map(|&(_, col)| col).min().unwrap_or(0);
+    let mut hasher = DefaultHasher::new();
+    live.len().hash(&mut hasher);
+    for (row, col) in live {
+        (row - top, col - left).hash(&mut hasher);
+    }
+    let corner = (viewport.top + top as i64, viewport.left + left as i64);
+    (hasher.finish(), corner)
+}
+
+/// Remembers the states of an engine it is shown, one per generation, and
+/// reports the first to repeat.
+///
+/// Only hashes are kept, so a collision could in principle report a cycle
+/// that isn't there; with 64 bit hashes that is vanishingly unlikely.
+#[derive(Debug, Default)]
+pub struct CycleDetector {
+    /// The generation each state was first seen at, by shape and corner.
+    exact: HashMap<(u64, (i64, i64)), u64>,
+    /// The generation each shape was first seen at, and where it was.
+    shapes: HashMap<u64, (u64, (i64, i64))>,
+    cycle: Option<Cycle>,
+}
+
+impl CycleDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the engine's current state, returning the cycle it
+    /// completes, if any. A state seen before in the same place is an exact
+    /// repeat and wins over the same shape seen somewhere else. Once a cycle
+    /// is found the same one is returned without looking at any more states.
+    pub fn observe<E: LifeEngine>(&mut self, engine: &E) -> Option<Cycle> {
+        if self.cycle.is_some() {
+            return self.cycle;
+        }
+        let generation = engine.generation();
+        let (hash, corner) = shape(engine);
+        let (top, left) = corner;
+        let exact = self
+            .exact
+            .get(&(hash, corner))
+            .map(|&start| (start, corner));
+        let earlier = exact
+            .filter(|&(start, _)| start < generation)
+            .or_else(|| self.shapes.get(&hash).copied())
+            .filter(|&(start, _)| start < generation);
+        if let Some((start, (first_top, first_left))) = earlier {
+            self.cycle = Some(Cycle {
+                start,
+                period: generation - start,
+                displacement: (top - first_top, left - first_left),
+            });
+        }
+        self.exact.entry((hash, corner)).or_insert(generation);
+        self.shapes.entry(hash).or_insert((generation, corner));
+        self.cycle
+    }
+
+    /// The cycle found so far, if any.
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    /// Forgets every state, for watching an engine that was edited or
+    /// replaced.
+    pub fn clear(&mut self) {
+        self.exact.clear();
+        self.shapes.clear();
+        self.cycle = None;
+    }
+}
+
+/// Runs `engine` for up to `max_generations` and returns the first cycle it
+/// falls into, or `None` if it is still evolving. The engine is left at the
+/// generation the cycle was found.
+pub fn find_cycle<E: LifeEngine>(engine: &mut E, max_generations: u64) -> Option<Cycle> {
+    let mut detector = CycleDetector::new();
+    detector.observe(engine);
+    for _ in 0..max_generations {
+        engine.next_gen();
+        if let Some(cycle) = detector.observe(engine) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+    use crate::bit_universe::BitUniverse;
+    use crate::game_of_life::{Cell, Universe};
+    use crate::hashlife::HashLife;
+    use crate::sparse::SparseUniverse;
+
+    fn universe(rows: usize, cols: usize, live: &[(usize, usize)]) -> Universe {
+        let mut cells = vec![Cell::Dead; rows * cols];
+        for &(row, col) in live {
+            cells[row * cols + col] = Cell::Alive;
+        }
+        Universe::new(&cells, cols)
+    }
+
+    #[test]
+    fn a_block_is_a_still_life_from_the_start() {
+        let mut block = universe(6, 6, &[(2, 2), (2, 3), (3, 2), (3, 3)]);
+        let cycle = find_cycle(&mut block, 10).unwrap();
+        assert_eq!(
+            Cycle {
+                start: 0,
+                period: 1,
+                displacement: (0, 0)
+            },
+            cycle
+        );
+        assert_eq!(CycleKind::StillLife, cycle.kind());
+        assert_eq!("still life from generation 0", cycle.to_string());
+    }
+
+    #[test]
+    fn a_blinker_is_an_oscillator_of_period_2() {
+        let mut blinker = universe(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        let cycle = find_cycle(&mut blinker, 10).unwrap();
+        assert_eq!(
+            (0, 2, (0, 0)),
+            (cycle.start, cycle.period, cycle.displacement)
+        );
+        assert_eq!(CycleKind::Oscillator, cycle.kind());
+        assert_eq!(2, LifeEngine::generation(&blinker));
+    }
+
+    #[test]
+    fn a_glider_is_a_spaceship_moving_diagonally() {
+        let mut glider = universe(12, 12, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let cycle = find_cycle(&mut glider, 10).unwrap();
+        assert_eq!(
+            (0, 4, (1, 1)),
+            (cycle.start, cycle.period, cycle.displacement)
+        );
+        assert_eq!(CycleKind::Spaceship, cycle.kind());
+        assert_eq!(
+            "spaceship of period 4 moving (1, 1) from generation 0",
+            cycle.to_string()
+        );
+    }
+
+    #[test]
+    fn every_engine_finds_the_same_cycles() {
+        let glider = universe(12, 12, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let expected = find_cycle(&mut glider.clone(), 10);
+        assert_eq!(
+            expected,
+            find_cycle(&mut BitUniverse::from_universe(&glider), 10)
+        );
+        assert_eq!(
+            expected,
+            find_cycle(&mut SparseUniverse::from_universe(&glider).unwrap(), 10)
+        );
+        assert_eq!(
+            expected,
+            find_cycle(&mut HashLife::from_universe(&glider).unwrap(), 10)
+        );
+
+        let mut blinker =
+            SparseUniverse::from_universe(&universe(5, 5, &[(2, 1), (2, 2), (2, 3)])).unwrap();
+        let cycle = find_cycle(&mut blinker, 10).unwrap();
+        assert_eq!((2, CycleKind::Oscillator), (cycle.period, cycle.kind()));
+    }
+
+    #[test]
+    fn reports_the_generation_the_cycle_began() {
+        // Three cells in an L become a block a generation later.
+        let mut l = universe(6, 6, &[(2, 2), (2, 3), (3, 2)]);
+        let cycle = find_cycle(&mut l, 10).unwrap();
+        assert_eq!((1, 1), (cycle.start, cycle.period));
+
+        // A lone cell dies out straight away, leaving an empty still life.
+        let mut lone = universe(3, 3, &[(1, 1)]);
+        let cycle = find_cycle(&mut lone, 10).unwrap();
+        assert_eq!(
+            (1, 1, CycleKind::StillLife),
+            (cycle.start, cycle.period, cycle.kind())
+        );
+    }
+
+    #[test]
+    fn still_evolving_patterns_have_no_cycle_yet() {
+        let mut glider = universe(12, 12, &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(None, find_cycle(&mut glider, 3));
+        assert_eq!(3, LifeEngine::generation(&glider));
+    }
+
+    #[test]
+    fn the_detector_keeps_its_cycle_until_cleared() {
+        let mut blinker = universe(5, 5, &[(2, 1), (2, 2), (2, 3)]);
+        let mut detector = CycleDetector::new();
+        assert_eq!(None, detector.observe(&blinker));
+        assert_eq!(None, detector.observe(&blinker));
+        LifeEngine::next_gen(&mut blinker);
+        assert_eq!(None, detector.observe(&blinker));
+        LifeEngine::next_gen(&mut blinker);
+        let cycle = detector.observe(&blinker);
+        assert_eq!(Some(2), cycle.map(|cycle| cycle.period));
+        LifeEngine::next_gen(&mut blinker);
+        assert_eq!(cycle, detector.observe(&blinker));
+
+        detector.clear();
+        assert_eq!(None, detector.cycle());
+        assert_eq!(None, detector.observe(&blinker));
+    }
+
+    #[test]
+    fn exact_repeats_win_over_the_same_shape_elsewhere() {
+        let here = universe(6, 6, &[(1, 1), (1, 2), (2, 1), (2, 2)]);
+        let mut there = universe(6, 6, &[(3, 3), (3, 4), (4, 3), (4, 4)]);
+        let mut detector = CycleDetector::new();
+        assert_eq!(None, detector.observe(&here));
+        assert_eq!(None, detector.observe(&there));
+        LifeEngine::next_gen(&mut there);
+        let cycle = detector.observe(&there).unwrap();
+        assert_eq!(
+            (0, 1, CycleKind::StillLife),
+            (cycle.start, cycle.period, cycle.kind())
+        );
+    }
+}
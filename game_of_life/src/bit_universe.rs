@@ -0,0 +1,574 @@
+#[cfg(feature = "parallel")]
+use crate::engine::default_threads;
+use crate::engine::{LifeEngine, Viewport};
+use crate::game_of_life::{Cell, Universe};
+use crate::history::Snapshot;
+use crate::rule::Rule;
+use crate::topology::{Location, Topology};
+use std::mem;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A bounded universe that packs 64 cells into each `u64` and advances all
+/// of them at once with bit-sliced neighbor counting. It gives exactly the
+/// same results as `Universe`, rule and topology included.
+///
+/// The grid is stored with a one cell ghost border, so padded row `r + 1`
+/// and padded column `c + 1` hold cell `(r, c)`. Before each generation the
+/// border is filled in from the topology, which lets every interior word be
+/// computed the same way. Two buffers are swapped between generations so
+/// stepping never allocates.
+#[derive(Debug, Clone)]
+pub struct BitUniverse {
+    generation: u32,
+    height: usize,
+    width: usize,
+    stride: usize,
+    cells: Vec<u64>,
+    next: Vec<u64>,
+    rule: Rule,
+    topology: Topology,
+}
+
+impl BitUniverse {
+    /// The fewest cells `next_gen` steps on several threads with the
+    /// `parallel` feature. Packing 64 cells to a word makes each step so
+    /// quick that this is far more than `Universe` needs.
+    #[cfg(feature = "parallel")]
+    pub const PARALLEL_MIN_CELLS: usize = 1 << 20;
+
+    pub fn new(cell_slice: &[Cell], width: usize) -> Self {
+        let height = cell_slice.len() / width;
+        let stride = (width + 2).div_ceil(WORD_BITS);
+        let words = (height + 2) * stride;
+        let mut universe = Self {
+            generation: 0,
+            height,
+            width,
+            stride,
+            cells: vec![0; words],
+            next: vec![0; words],
+            rule: Rule::default(),
+            topology: Topology::default(),
+        };
+        for (index, cell) in cell_slice.iter().take(height * width).enumerate() {
+            universe.set_cell_at(index / width, index % width, *cell);
+        }
+        universe
+    }
+
+    pub fn from_universe(universe: &Universe) -> Self {
+        let cells: Vec<Cell> = universe.enumerate_cells().map(|(_, _, &c)| c).collect();
+        Self::new(&cells, universe.width())
+            .with_rule(universe.rule())
+            .with_topology(universe.topology())
+    }
+
+    pub fn with_rule(self, rule: Rule) -> Self {
+        Self { rule, ..self }
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn with_topology(self, topology: Topology) -> Self {
+        Self { topology, ..self }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    fn bit(&self, padded_row: usize, padded_col: usize) -> bool {
+        let word = self.cells[padded_row * self.stride + padded_col / WORD_BITS];
+        word >> (padded_col % WORD_BITS) & 1 == 1
+    }
+
+    fn set_bit(&mut self, padded_row: usize, padded_col: usize, alive: bool) {
+        let word = &mut self.cells[padded_row * self.stride + padded_col / WORD_BITS];
+        let mask = 1 << (padded_col % WORD_BITS);
+        if alive {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    /// Panics if the cell is off the grid; `neighbor_at` looks past the
+    /// edges.
+    pub fn cell_at(&self, row: usize, col: usize) -> &Cell {
+        assert!(
+            row < self.height && col < self.width,
+            "cell ({}, {}) is outside the {}x{} grid",
+            row,
+            col,
+            self.height,
+            self.width
+        );
+        if self.bit(row + 1, col + 1) {
+            &Cell::Alive
+        } else {
+            &Cell::Dead
+        }
+    }
+
+    /// Looks up a cell that may lie past the edge of the grid, resolving it
+    /// through the universe's topology.
+    pub fn neighbor_at(&self, row: isize, col: isize) -> &Cell {
+        match self.topology.locate(row, col, self.height, self.width) {
+            Location::Inside(r, c) if self.bit(r + 1, c + 1) => &Cell::Alive,
+            Location::Inside(_, _) => &Cell::Dead,
+            Location::Outside(Cell::Alive) => &Cell::Alive,
+            Location::Outside(Cell::Dead) => &Cell::Dead,
+        }
+    }
+
+    fn on_grid(&self, row: i64, col: i64) -> bool {
+        let (h, w) = (self.height as i64, self.width as i64);
+        (0..h).contains(&row) && (0..w).contains(&col)
+    }
+
+    pub fn set_cell_at(&mut self, row: usize, col: usize, new_cell: Cell) {
+        assert!(row < self.height && col < self.width, "cell out of bounds");
+        self.set_bit(row + 1, col + 1, new_cell.is_alive());
+    }
+
+    pub fn toggle_cell_at(&mut self, row: usize, col: usize) {
+        let toggled = self.cell_at(row, col).toggle();
+        self.set_cell_at(row, col, toggled);
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(0);
+        self.generation = 0;
+    }
+
+    pub fn population(&self) -> usize {
+        (1..=self.height)
+            .map(|padded_row| {
+                let start = padded_row * self.stride;
+                self.cells[start..start + self.stride]
+                    .iter()
+                    .enumerate()
+                    .map(|(w, word)| (word & interior_mask(self.width, w)).count_ones() as usize)
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    fn ghost_value(&self, padded_row: usize, padded_col: usize) -> bool {
+        let (row, col) = (padded_row as isize - 1, padded_col as isize - 1);
+        match self.topology.locate(row, col, self.height, self.width) {
+            Location::Inside(r, c) => self.bit(r + 1, c + 1),
+            Location::Outside(cell) => cell.is_alive(),
+        }
+    }
+
+    /// Copies the cells the topology says lie just past each edge into the
+    /// ghost border.
+    fn fill_ghosts(&mut self) {
+        for padded_row in [0, self.height + 1] {
+            for padded_col in 0..self.width + 2 {
+                let alive = self.ghost_value(padded_row, padded_col);
+                self.set_bit(padded_row, padded_col, alive);
+            }
+        }
+        for padded_row in 1..=self.height {
+            for padded_col in [0, self.width + 1] {
+                let alive = self.ghost_value(padded_row, padded_col);
+                self.set_bit(padded_row, padded_col, alive);
+            }
+        }
+    }
+
+    /// Advances a generation, on several threads with the `parallel`
+    /// feature once the grid has `PARALLEL_MIN_CELLS`.
+    pub fn next_gen(&mut self) {
+        #[cfg(feature = "parallel")]
+        if self.height * self.width >= Self::PARALLEL_MIN_CELLS {
+            self.par_next_gen(default_threads());
+            return;
+        }
+        self.serial_next_gen();
+    }
+
+    /// Advances a generation on the calling thread, whatever the grid's
+    /// size.
+    pub fn serial_next_gen(&mut self) {
+        self.fill_ghosts();
+        let mut band = std::mem::take(&mut self.next);
+        let stride = self.stride;
+        step_band(self, 1, &mut band[stride..(self.height + 1) * stride]);
+        self.next = band;
+
+        std::mem::swap(&mut self.cells, &mut self.next);
+        self.generation += 1;
+    }
+
+    /// Advances a generation with the rows split into `threads` bands that
+    /// are stepped concurrently. The result is identical to
+    /// `serial_next_gen`.
+    #[cfg(feature = "parallel")]
+    pub fn par_next_gen(&mut self, threads: usize) {
+        self.fill_ghosts();
+        let mut next = std::mem::take(&mut self.next);
+        let band_rows = self.height.div_ceil(threads.max(1)).max(1);
+        let stride = self.stride;
+        let universe = &*self;
+        std::thread::scope(|scope| {
+            let interior = &mut next[stride..(universe.height + 1) * stride];
+            for (i, band) in interior.chunks_mut(band_rows * stride).enumerate() {
+                scope.spawn(move || step_band(universe, 1 + i * band_rows, band));
+            }
+        });
+        self.next = next;
+
+        std::mem::swap(&mut self.cells, &mut self.next);
+        self.generation += 1;
+    }
+}
+
+/// The bits of word `w` in a padded row that belong to real cells.
+fn interior_mask(width: usize, w: usize) -> u64 {
+    let first = w * WORD_BITS;
+    let bits_below = |padded_col: usize| -> u64 {
+        match padded_col.saturating_sub(first) {
+            0 => 0,
+            n if n >= WORD_BITS => u64::MAX,
+            n => (1 << n) - 1,
+        }
+    };
+    bits_below(width + 1) & !bits_below(1)
+}
+
+/// Fills `band` with the next generation of the padded rows starting at
+/// `first_padded_row`. The ghost border must already be filled in.
+fn step_band(universe: &BitUniverse, first_padded_row: usize, band: &mut [u64]) {
+    let stride = universe.stride;
+    let cells = &universe.cells;
+    for (i, next_row) in band.chunks_mut(stride).enumerate() {
+        let padded_row = first_padded_row + i;
+        let up = &cells[(padded_row - 1) * stride..padded_row * stride];
+        let mid = &cells[padded_row * stride..(padded_row + 1) * stride];
+        let down = &cells[(padded_row + 1) * stride..(padded_row + 2) * stride];
+        for (w, next) in next_row.iter_mut().enumerate() {
+            *next = step_word(up, mid, down, w, &universe.rule) & interior_mask(universe.width, w);
+        }
+    }
+}
+
+/// The contents of word `w` shifted so that each bit holds its left
+/// (`west`) or right (`east`) neighbor.
+fn west(row: &[u64], w: usize) -> u64 {
+    let carry = if w > 0 {
+        row[w - 1] >> (WORD_BITS - 1)
+    } else {
+        0
+    };
+    (row[w] << 1) | carry
+}
+
+fn east(row: &[u64], w: usize) -> u64 {
+    let carry = if w + 1 < row.len() {
+        row[w + 1] << (WORD_BITS - 1)
+    } else {
+        0
+    };
+    (row[w] >> 1) | carry
+}
+
+fn half_adder(a: u64, b: u64) -> (u64, u64) {
+    (a ^ b, a & b)
+}
+
+fn full_adder(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+    (partial ^ c, (a & b) | (partial & c))
+}
+
+/// Applies `rule` to the 64 cells of word `w` of the middle row.
+fn step_word(up: &[u64], mid: &[u64], down: &[u64], w: usize, rule: &Rule) -> u64 {
+    // Sum the eight neighbor bit-planes into a four bit count per cell
+    let (sum_a, carry_a) = full_adder(west(up, w), up[w], east(up, w));
+    let (sum_b, carry_b) = full_adder(west(mid, w), east(mid, w), west(down, w));
+    let (sum_c, carry_c) = half_adder(down[w], east(down, w));
+    let (ones, carry_d) = full_adder(sum_a, sum_b, sum_c);
+    let (partial_twos, carry_e) = full_adder(carry_a, carry_b, carry_c);
+    let (twos, carry_f) = half_adder(partial_twos, carry_d);
+    let (fours, eights) = half_adder(carry_e, carry_f);
+    let count_bits = [ones, twos, fours, eights];
+
+    let alive = mid[w];
+    let mut next = 0;
+    for n in 0..=8u8 {
+        let (births, survives) = (rule.births(n), rule.survives(n));
+        if !births && !survives {
+            continue;
+        }
+        let has_count = count_bits
+            .iter()
+            .enumerate()
+            .fold(u64::MAX, |acc, (bit, &plane)| {
+                if n >> bit & 1 == 1 {
+                    acc & plane
+                } else {
+                    acc & !plane
+                }
+            });
+        if births {
+            next |= has_count & !alive;
+        }
+        if survives {
+            next |= has_count & alive;
+        }
+    }
+    next
+}
+
+impl Snapshot for BitUniverse {
+    fn memory_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + (self.cells.capacity() + self.next.capacity()) * mem::size_of::<u64>()
+    }
+}
+
+impl LifeEngine for BitUniverse {
+    fn next_gen(&mut self) {
+        BitUniverse::next_gen(self);
+    }
+
+    fn generation(&self) -> u64 {
+        BitUniverse::generation(self).into()
+    }
+
+    fn cell(&self, row: i64, col: i64) -> Cell {
+        if self.on_grid(row, col) {
+            *self.cell_at(row as usize, col as usize)
+        } else {
+            Cell::Dead
+        }
+    }
+
+    fn set_cell(&mut self, row: i64, col: i64, cell: Cell) {
+        if self.on_grid(row, col) {
+            self.set_cell_at(row as usize, col as usize, cell);
+        }
+    }
+
+    fn clear(&mut self) {
+        BitUniverse::clear(self);
+    }
+
+    fn population(&self) -> u64 {
+        BitUniverse::population(self) as u64
+    }
+
+    fn rule(&self) -> Rule {
+        BitUniverse::rule(self)
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        Some(BitUniverse::topology(self))
+    }
+
+    fn default_viewport(&self) -> Viewport {
+        Viewport::new(0, 0, self.height, self.width)
+    }
+}
+
+#[cfg(test)]
+mod bit_universe_tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    const TOPOLOGIES: [Topology; 5] = [
+        Topology::Bounded,
+        Topology::Torus,
+        Topology::KleinBottle,
+        Topology::CrossSurface,
+        Topology::AliveEdge,
+    ];
+
+    const RULES: [&str; 6] = [
+        "B3/S23",
+        "B36/S23",
+        "B2/S",
+        "B3678/S34678",
+        "B1357/S02468",
+        "B0123478/S01234678",
+    ];
+
+    fn soup(height: usize, width: usize, seed: u64) -> Universe {
+        let mut rng = Rng::new(seed);
+        let cells: Vec<Cell> = (0..height * width)
+            .map(|_| {
+                if rng.next_rnd() >> 16 & 1 == 1 {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            })
+            .collect();
+        Universe::new(&cells, width)
+    }
+
+    fn assert_same_cells(expected: &Universe, actual: &BitUniverse, context: &str) {
+        for (r, c, cell) in expected.enumerate_cells() {
+            assert_eq!(
+                cell,
+                actual.cell_at(r, c),
+                "cell ({}, {}) {}",
+                r,
+                c,
+                context
+            );
+        }
+    }
+
+    fn check(height: usize, width: usize, rule: &str, topology: Topology, generations: u32) {
+        let seed = (height * 1_000 + width) as u64;
+        let mut universe = soup(height, width, seed)
+            .with_rule(rule.parse().unwrap())
+            .with_topology(topology);
+        let mut bits = BitUniverse::from_universe(&universe);
+        for generation in 1..=generations {
+            universe.next_gen();
+            bits.next_gen();
+            let context = format!(
+                "of {}x{} {} {:?} at generation {}",
+                height, width, rule, topology, generation
+            );
+            assert_same_cells(&universe, &bits, &context);
+        }
+        let population = universe
+            .enumerate_cells()
+            .filter(|(_, _, c)| c.is_alive())
+            .count();
+        assert_eq!(population, bits.population());
+    }
+
+    #[test]
+    fn matches_universe_for_every_rule_and_topology() {
+        for rule in RULES {
+            for topology in TOPOLOGIES {
+                check(17, 23, rule, topology, 12);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_universe_across_word_boundaries() {
+        for width in [62, 63, 64, 65, 126, 127, 128, 129] {
+            for topology in TOPOLOGIES {
+                check(9, width, "B3/S23", topology, 8);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_universe_on_degenerate_grids() {
+        for (height, width) in [(1, 1), (1, 7), (7, 1), (2, 2), (3, 64)] {
+            for rule in RULES {
+                for topology in TOPOLOGIES {
+                    check(height, width, rule, topology, 4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_universe_over_a_long_run() {
+        check(64, 100, "B3/S23", Topology::Torus, 200);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_stepping_matches_serial_stepping() {
+        for topology in TOPOLOGIES {
+            for threads in [1, 2, 3, 7, 64, 100] {
+                let universe = soup(67, 130, 11).with_topology(topology);
+                let mut serial = BitUniverse::from_universe(&universe);
+                let mut parallel = BitUniverse::from_universe(&universe);
+                for _ in 0..10 {
+                    serial.serial_next_gen();
+                    parallel.par_next_gen(threads);
+                }
+                assert_eq!(serial.cells, parallel.cells, "{:?} {}", topology, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn looks_cells_up_like_universe_on_and_off_the_grid() {
+        for topology in TOPOLOGIES {
+            let universe = soup(5, 7, 2).with_topology(topology);
+            let bits = BitUniverse::from_universe(&universe);
+            for row in -2..7 {
+                for col in -2..9 {
+                    let context = format!("({}, {}) {:?}", row, col, topology);
+                    assert_eq!(
+                        LifeEngine::cell(&universe, row, col),
+                        LifeEngine::cell(&bits, row, col),
+                        "{}",
+                        context
+                    );
+                    let (row, col) = (row as isize, col as isize);
+                    assert_eq!(
+                        universe.neighbor_at(row, col),
+                        bits.neighbor_at(row, col),
+                        "{}",
+                        context
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the 5x7 grid")]
+    fn cell_at_panics_off_the_grid_whatever_the_topology() {
+        let universe = soup(5, 7, 2).with_topology(Topology::Torus);
+        BitUniverse::from_universe(&universe).cell_at(5, 0);
+    }
+
+    #[test]
+    fn stepping_reuses_its_buffers() {
+        let mut bits = BitUniverse::from_universe(&soup(40, 200, 7));
+        let buffers = (bits.cells.as_ptr(), bits.next.as_ptr());
+        bits.next_gen();
+        bits.next_gen();
+        assert_eq!(buffers, (bits.cells.as_ptr(), bits.next.as_ptr()));
+    }
+
+    #[test]
+    fn cells_can_be_set_and_toggled() {
+        let mut bits = BitUniverse::new(&vec![Cell::Dead; 3 * 70], 70);
+        bits.set_cell_at(2, 69, Cell::Alive);
+        assert_eq!(&Cell::Alive, bits.cell_at(2, 69));
+        bits.toggle_cell_at(2, 69);
+        assert_eq!(&Cell::Dead, bits.cell_at(2, 69));
+        assert_eq!(0, bits.population());
+    }
+
+    #[test]
+    fn clearing_kills_every_cell() {
+        let mut bits = BitUniverse::from_universe(&soup(10, 10, 3));
+        bits.next_gen();
+        bits.clear();
+        assert_eq!(0, bits.population());
+        assert_eq!(0, bits.generation());
+    }
+}
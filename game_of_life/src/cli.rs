@@ -0,0 +1,567 @@
+//! Command line parsing for the game of life app. Each option overrides one
+//! `GameOfLifeApp` builder field, starting from the defaults `main` sets up.
+
+use crate::GameOfLifeApp;
+use automata_core::cli::{invalid, parse_positive, parse_ups, parse_value, OptionError};
+use automata_core::color::{parse_color, rgba_to_hex};
+use automata_core::headless::{HeadlessOptions, DEFAULT_GENERATIONS, HEADLESS_OPTIONS};
+use automata_core::model::MAX_UPS;
+use game_of_life::soup::Symmetry;
+use game_of_life::topology::Topology;
+use std::error::Error;
+use std::fmt;
+
+/// Options that take a value, as written after the `--`.
+const VALUE_OPTIONS: [&str; 18] = [
+    "rows",
+    "cols",
+    "ups",
+    "fps",
+    "live-color",
+    "dead-color",
+    "rule",
+    "topology",
+    "pattern",
+    "offset",
+    "seed",
+    "density",
+    "symmetry",
+    "soup-size",
+    "image-dir",
+    "scale",
+    "step",
+    "history",
+];
+
+pub enum Command {
+    Run(GameOfLifeApp),
+    Headless(GameOfLifeApp, HeadlessOptions),
+    Help(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliError {
+    Option(OptionError),
+    UnexpectedArgument(String),
+    NotHeadless(&'static str),
+    UnboundedTopology(Topology),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Option(e) => e.fmt(f),
+            Self::UnexpectedArgument(arg) => write!(
+                f,
+                "unexpected argument '{}', only one pattern file can be given",
+                arg
+            ),
+            Self::NotHeadless(option) => {
+                write!(f, "option '--{}' cannot be used with headless", option)
+            }
+            Self::UnboundedTopology(topology) => write!(
+                f,
+                "option '--unbounded' cannot be used with '--topology {}'",
+                topology
+            ),
+        }
+    }
+}
+
+impl Error for CliError {}
+
+impl From<OptionError> for CliError {
+    fn from(e: OptionError) -> Self {
+        Self::Option(e)
+    }
+}
+
+fn usage(defaults: &GameOfLifeApp) -> String {
+    let rule = match defaults.rule {
+        Some(rule) => rule.to_string(),
+        None => "the pattern's rule, else B3/S23".to_string(),
+    };
+    let topologies: Vec<String> = Topology::ALL.iter().map(Topology::to_string).collect();
+    let symmetries: Vec<String> = Symmetry::ALL.iter().map(Symmetry::to_string).collect();
+    format!(
+        "\
+Usage: game_of_life [OPTIONS] [PATTERN]
+       game_of_life headless [OPTIONS] [PATTERN]
+
+Runs a life-like cellular automaton in a window, starting from PATTERN (an
+RLE, plaintext, Life 1.05/1.06 or macrocell file) or from a random soup.
+Macrocell patterns run in the window on an unbounded plane with HashLife.
+The headless subcommand runs without a window instead, for batch jobs.
+
+Options:
+      --rows <N>           grid height in cells [default: {rows}]
+      --cols <N>           grid width in cells [default: {cols}]
+      --ups <N>            generations per second, at most {max_ups} [default: {ups}]
+      --fps <N>            frames drawn per second [default: {fps}]
+      --live-color <COLOR> #rrggbb, #rrggbbaa or a colour name [default: {live}]
+      --dead-color <COLOR> colour of dead cells [default: {dead}]
+      --rule <RULE>        rulestring such as B36/S23 [default: {rule}]
+      --topology <NAME>    {topologies} [default: {topology}]
+      --unbounded          run on an unbounded plane, showing a rows x cols window;
+                           patterns may be any size, and there are no edges to join
+      --pattern <FILE>     the same as giving PATTERN
+      --offset <ROW,COL>   put the pattern's top left corner here instead of centring it
+      --seed <N>           random soup seed [default: {seed}]
+      --density <P>        chance of each soup cell being alive [default: {density}]
+      --symmetry <NAME>    make the soup symmetric: {symmetries} [default: {symmetry}]
+      --soup-size <RxC>    fill only a ROWSxCOLS region in the middle with the soup
+      --image-dir <DIR>    where F10, F12 and E save recordings, screenshots and
+                           the clipboard
+                           [default: {image_dir}]
+      --scale <N>          pixels per cell in recordings and screenshots [default: {scale}]
+      --step <N>           generations Return advances [default: {step}]
+      --history <MB>       memory kept for stepping back through generations, or 0
+                           for none [default: {history}]
+  -h, --help               print this help
+
+Headless options:
+      --generations <N>    generations to run [default: {generations}]
+      --output <FILE>      save the final state here, in the format its extension
+                           names (.rle, .cells, .lif, .mc) [default: RLE to stdout]
+      --stats <FILE>       write population, births and deaths per generation as CSV
+      --record <FILE>      save every generation as an animated .gif or .png
+      --detect-cycles      report to standard error whether the universe settled
+                           into a still life, an oscillator or a spaceship
+
+Keys:
+  Space                    pause or resume
+  . (period)               advance one generation
+  , (comma)                go back a generation while paused, up to --history
+  Return                   advance --step generations
+  + and -                  double or halve the generations per second
+  Tab                      show or hide the generation, population and rates
+  Mouse wheel              zoom in or out around the cursor
+  Arrows or middle drag    pan
+  0                        zoom out to fit the whole grid in the window
+  X                        kill every cell
+  Backspace                go back to the starting pattern or soup
+  N                        start again from a new random soup with a fresh seed
+  Left click or drag       toggle a cell, or paint its new state along the drag
+  Right drag               kill every cell along the drag
+  Shift+left drag          select a rectangle of cells
+  Ctrl+C or Ctrl+X         copy or cut the selection to the clipboard
+  Ctrl+V                   paste: the clipboard follows the cursor until a left
+                           click drops it or a right click cancels
+  R, M or U                rotate 90 degrees clockwise, flip left to right or
+                           flip top to bottom the clipboard while pasting, or
+                           else the selection
+  Delete                   kill every cell in the selection
+  F                        fill the selection with a soup like --density/--symmetry
+  E                        save the clipboard as RLE into --image-dir
+  Ctrl+Z                   undo the last edit
+  Ctrl+Y or Ctrl+Shift+Z   redo the last edit undone
+  F10                      start or stop recording a GIF into --image-dir
+  F12                      save a PNG screenshot of the grid into --image-dir
+",
+        rows = defaults.rows,
+        cols = defaults.cols,
+        ups = defaults.ups,
+        max_ups = MAX_UPS,
+        fps = defaults.fps,
+        live = rgba_to_hex(defaults.live_color),
+        dead = rgba_to_hex(defaults.dead_color),
+        rule = rule,
+        topologies = topologies.join(", "),
+        topology = defaults.topology,
+        seed = match defaults.seed {
+            Some(seed) => seed.to_string(),
+            None => "picked at random and printed".to_string(),
+        },
+        density = defaults.density,
+        symmetries = symmetries.join(", "),
+        symmetry = defaults.symmetry,
+        image_dir = defaults.image_dir.display(),
+        scale = defaults.scale,
+        step = defaults.step,
+        history = defaults.history_memory >> 20,
+        generations = DEFAULT_GENERATIONS,
+    )
+}
+
+fn apply(
+    app: GameOfLifeApp,
+    option: &'static str,
+    value: &str,
+) -> Result<GameOfLifeApp, OptionError> {
+    let color =
+        |value: &str| parse_color(value).map_err(|e| invalid(option, value, &e.to_string()));
+    Ok(match option {
+        "rows" => app.rows(parse_positive(option, value)?),
+        "cols" => app.cols(parse_positive(option, value)?),
+        "ups" => app.ups(parse_ups(option, value)?),
+        "fps" => app.fps(parse_positive(option, value)?),
+        "live-color" => app.live_color(color(value)?),
+        "dead-color" => app.dead_color(color(value)?),
+        "rule" => app.rule(parse_value(option, value)?),
+        "topology" => app.topology(parse_value(option, value)?),
+        "pattern" => app.pattern(value),
+        "offset" => {
+            let (row, col) = value
+                .split_once(',')
+                .ok_or_else(|| invalid(option, value, "expected ROW,COL"))?;
+            app.offset(
+                parse_value(option, row.trim())?,
+                parse_value(option, col.trim())?,
+            )
+        }
+        "seed" => app.seed(parse_value(option, value)?),
+        "density" => {
+            let density: f64 = parse_value(option, value)?;
+            if !(0.0..=1.0).contains(&density) {
+                return Err(invalid(option, value, "must be between 0 and 1"));
+            }
+            app.density(density)
+        }
+        "symmetry" => app.symmetry(parse_value(option, value)?),
+        "soup-size" => {
+            let (rows, cols) = value
+                .split_once(['x', 'X'])
+                .ok_or_else(|| invalid(option, value, "expected ROWSxCOLS"))?;
+            app.soup_size(
+                parse_positive(option, rows.trim())?,
+                parse_positive(option, cols.trim())?,
+            )
+        }
+        "image-dir" => app.image_dir(value),
+        "scale" => app.scale(parse_positive(option, value)?),
+        "step" => app.step(parse_positive(option, value)?),
+        "history" => {
+            let megabytes: usize = parse_value(option, value)?;
+            let bytes = megabytes
+                .checked_mul(1 << 20)
+                .ok_or_else(|| invalid(option, value, "too large"))?;
+            app.history_memory(bytes)
+        }
+        _ => unreachable!("'{}' is not in VALUE_OPTIONS", option),
+    })
+}
+
+/// Applies the command line arguments, without the program name, to
+/// `defaults`.
+pub fn parse_args<I>(defaults: GameOfLifeApp, args: I) -> Result<Command, CliError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let help = usage(&defaults);
+    let mut app = defaults;
+    let mut args = args.into_iter().peekable();
+    let mut headless = args
+        .next_if(|arg| arg == "headless")
+        .map(|_| HeadlessOptions::default());
+    let mut positional_pattern = false;
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help(help));
+        }
+        let Some(option) = arg.strip_prefix("--") else {
+            if arg.starts_with('-') && arg != "-" {
+                return Err(OptionError::UnknownOption(arg).into());
+            }
+            if positional_pattern {
+                return Err(CliError::UnexpectedArgument(arg));
+            }
+            positional_pattern = true;
+            app = app.pattern(arg);
+            continue;
+        };
+
+        let (name, inline_value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (option, None),
+        };
+        if name == "unbounded" {
+            if headless.is_some() {
+                return Err(CliError::NotHeadless("unbounded"));
+            }
+            if inline_value.is_some() {
+                return Err(OptionError::UnexpectedValue("unbounded").into());
+            }
+            app = app.unbounded(true);
+            continue;
+        }
+        if name == "detect-cycles" && headless.is_some() {
+            if inline_value.is_some() {
+                return Err(OptionError::UnexpectedValue("detect-cycles").into());
+            }
+            app = app.detect_cycles(true);
+            continue;
+        }
+        let headless_options: &[&'static str] = match headless {
+            Some(_) => &HEADLESS_OPTIONS,
+            None => &[],
+        };
+        let option = *VALUE_OPTIONS
+            .iter()
+            .chain(headless_options)
+            .find(|&&known| known == name)
+            .ok_or_else(|| OptionError::UnknownOption(arg.clone()))?;
+        let value = match inline_value {
+            Some(value) => value,
+            None => args.next().ok_or(OptionError::MissingValue(option))?,
+        };
+        match headless.as_mut() {
+            Some(options) if HEADLESS_OPTIONS.contains(&option) => options.apply(option, &value)?,
+            _ => app = apply(app, option, &value)?,
+        }
+    }
+    if app.unbounded && app.topology != Topology::Bounded {
+        return Err(CliError::UnboundedTopology(app.topology));
+    }
+    Ok(match headless {
+        Some(options) => Command::Headless(app, options),
+        None => Command::Run(app),
+    })
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+    use game_of_life::rule::Rule;
+    use std::path::PathBuf;
+
+    fn parse(args: &[&str]) -> Result<GameOfLifeApp, CliError> {
+        let args = args.iter().map(|arg| arg.to_string());
+        match parse_args(GameOfLifeApp::new(), args)? {
+            Command::Run(app) => Ok(app),
+            _ => panic!("expected options to run the app"),
+        }
+    }
+
+    fn parse_headless(args: &[&str]) -> Result<HeadlessOptions, CliError> {
+        let args = ["headless"].iter().chain(args).map(|arg| arg.to_string());
+        match parse_args(GameOfLifeApp::new(), args)? {
+            Command::Headless(_, options) => Ok(options),
+            _ => panic!("expected headless options"),
+        }
+    }
+
+    #[test]
+    fn no_arguments_keep_the_defaults() {
+        let app = parse(&[]).unwrap();
+        let defaults = GameOfLifeApp::new();
+        assert_eq!((defaults.rows, defaults.cols), (app.rows, app.cols));
+        assert_eq!(None, app.pattern);
+    }
+
+    #[test]
+    fn every_builder_field_can_be_set() {
+        let app = parse(&[
+            "--rows",
+            "20",
+            "--cols=30",
+            "--ups",
+            "7",
+            "--fps",
+            "25",
+            "--live-color",
+            "#ff0000",
+            "--dead-color",
+            "blue",
+            "--rule",
+            "B36/S23",
+            "--topology",
+            "torus",
+            "--offset",
+            "2,3",
+            "--seed",
+            "42",
+            "--density",
+            "0.25",
+            "--symmetry",
+            "d8",
+            "--soup-size",
+            "16x20",
+            "--image-dir",
+            "shots",
+            "--scale",
+            "3",
+            "--step",
+            "30",
+            "--history",
+            "2",
+            "glider.rle",
+        ])
+        .unwrap();
+        assert_eq!((20, 30, 7, 25), (app.rows, app.cols, app.ups, app.fps));
+        assert_eq!([1.0, 0.0, 0.0, 1.0], app.live_color);
+        assert_eq!([0.0, 0.0, 1.0, 1.0], app.dead_color);
+        assert_eq!(Some("B36/S23".parse::<Rule>().unwrap()), app.rule);
+        assert_eq!(Topology::Torus, app.topology);
+        assert_eq!(Some((2, 3)), app.offset);
+        assert_eq!((Some(42), 0.25), (app.seed, app.density));
+        assert_eq!(
+            (Symmetry::D8, Some((16, 20))),
+            (app.symmetry, app.soup_size)
+        );
+        assert_eq!((PathBuf::from("shots"), 3), (app.image_dir, app.scale));
+        assert_eq!((30, 2 << 20), (app.step, app.history_memory));
+        assert_eq!(Some(PathBuf::from("glider.rle")), app.pattern);
+    }
+
+    #[test]
+    fn help_lists_every_option_with_its_default() {
+        let args = ["--rows", "12", "--help"].map(String::from);
+        let Ok(Command::Help(usage)) = parse_args(GameOfLifeApp::new(), args) else {
+            panic!("expected --help");
+        };
+        for option in VALUE_OPTIONS {
+            assert!(usage.contains(&format!("--{} <", option)), "{}", option);
+        }
+        assert!(usage.contains("--unbounded"));
+        assert!(usage.contains("grid height in cells [default: 50]"));
+        assert!(usage.contains("[default: #ffffff]"));
+    }
+
+    #[test]
+    fn rejects_zero_sizes_and_rates() {
+        for option in ["--rows", "--cols", "--ups", "--fps", "--scale", "--step"] {
+            let err = parse(&[option, "0"]).unwrap_err();
+            assert_eq!(
+                format!("invalid value '0' for '{}': must be at least 1", option),
+                err.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unparseable_values() {
+        let err = parse(&["--live-color", "mauve"]).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid value 'mauve' for '--live-color': 'mauve' is not a colour"));
+        let err = parse(&["--rows", "ten"]).unwrap_err();
+        assert_eq!(
+            "invalid value 'ten' for '--rows': invalid digit found in string",
+            err.to_string()
+        );
+        assert!(parse(&["--rule", "B3"]).is_err());
+        assert!(parse(&["--topology", "sphere"]).is_err());
+        assert!(parse(&["--offset", "3"]).is_err());
+        assert!(parse(&["--density", "1.5"]).is_err());
+        assert!(parse(&["--density", "NaN"]).is_err());
+        assert!(parse(&["--symmetry", "C3"]).is_err());
+        assert!(parse(&["--soup-size", "16"]).is_err());
+        assert!(parse(&["--soup-size", "0x16"]).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_command_lines() {
+        assert_eq!(
+            Some(CliError::Option(OptionError::UnknownOption(
+                "--colour".to_string()
+            ))),
+            parse(&["--colour", "red"]).err()
+        );
+        assert_eq!(
+            Some(CliError::Option(OptionError::UnknownOption(
+                "-r".to_string()
+            ))),
+            parse(&["-r"]).err()
+        );
+        assert_eq!(
+            Some(CliError::Option(OptionError::MissingValue("rows"))),
+            parse(&["--rows"]).err()
+        );
+        assert_eq!(
+            Some(CliError::Option(OptionError::UnexpectedValue("unbounded"))),
+            parse(&["--unbounded=yes"]).err()
+        );
+        assert_eq!(
+            Some(CliError::UnexpectedArgument("b.rle".to_string())),
+            parse(&["a.rle", "b.rle"]).err()
+        );
+    }
+
+    #[test]
+    fn headless_takes_its_own_options_and_the_app_options() {
+        let args = [
+            "headless",
+            "--generations",
+            "500",
+            "--output=final.mc",
+            "--stats",
+            "stats.csv",
+            "--record",
+            "run.gif",
+            "--rows",
+            "12",
+            "gun.rle",
+        ]
+        .map(String::from);
+        let Ok(Command::Headless(app, options)) = parse_args(GameOfLifeApp::new(), args) else {
+            panic!("expected headless");
+        };
+        assert_eq!(
+            HeadlessOptions {
+                generations: 500,
+                output: Some(PathBuf::from("final.mc")),
+                stats: Some(PathBuf::from("stats.csv")),
+                record: Some(PathBuf::from("run.gif")),
+            },
+            options
+        );
+        assert_eq!(12, app.rows);
+        assert_eq!(Some(PathBuf::from("gun.rle")), app.pattern);
+    }
+
+    #[test]
+    fn headless_defaults_to_stdout_and_no_stats() {
+        let options = parse_headless(&[]).unwrap();
+        assert_eq!(DEFAULT_GENERATIONS, options.generations);
+        assert_eq!((None, None), (options.output, options.stats));
+        assert_eq!(None, options.record);
+    }
+
+    #[test]
+    fn headless_options_are_only_known_to_headless() {
+        assert_eq!(
+            Some(CliError::Option(OptionError::UnknownOption(
+                "--generations".to_string()
+            ))),
+            parse(&["--generations", "5"]).err()
+        );
+        assert_eq!(
+            Some(CliError::NotHeadless("unbounded")),
+            parse_headless(&["--unbounded"]).err()
+        );
+        assert!(parse_headless(&["--generations", "-1"]).is_err());
+        assert_eq!(
+            Some(CliError::Option(OptionError::UnknownOption(
+                "--detect-cycles".to_string()
+            ))),
+            parse(&["--detect-cycles"]).err()
+        );
+    }
+
+    #[test]
+    fn cycles_are_only_detected_when_asked_for() {
+        let app = |args: &[&str]| {
+            let args = ["headless"].iter().chain(args).map(|arg| arg.to_string());
+            match parse_args(GameOfLifeApp::new(), args).unwrap() {
+                Command::Headless(app, _) => app,
+                _ => panic!("expected headless options"),
+            }
+        };
+        assert!(!app(&[]).detect_cycles);
+        assert!(app(&["--detect-cycles"]).detect_cycles);
+    }
+
+    #[test]
+    fn unbounded_planes_have_no_edges_to_join() {
+        let app = parse(&["--unbounded", "--topology", "bounded"]).unwrap();
+        assert!(app.unbounded);
+        let err = parse(&["--topology", "torus", "--unbounded"])
+            .err()
+            .unwrap();
+        assert_eq!(CliError::UnboundedTopology(Topology::Torus), err);
+        assert_eq!(
+            "option '--unbounded' cannot be used with '--topology torus'",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::game_of_life::{Cell, Universe};
+use crate::history::Snapshot;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+/// A rectangular window onto an engine's coordinate space. `top` and `left`
+/// are signed so that unbounded engines can be viewed anywhere.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Viewport {
+    pub top: i64,
+    pub left: i64,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Viewport {
+    pub fn new(top: i64, left: i64, rows: usize, cols: usize) -> Self {
+        Self {
+            top,
+            left,
+            rows,
+            cols,
+        }
+    }
+
+    pub fn contains(&self, row: i64, col: i64) -> bool {
+        row >= self.top
+            && row < self.top + self.rows as i64
+            && col >= self.left
+            && col < self.left + self.cols as i64
+    }
+
+    /// Returns a viewport of the same size centred on `(row, col)`.
+    pub fn centred_on(&self, row: i64, col: i64) -> Self {
+        Self {
+            top: row - self.rows as i64 / 2,
+            left: col - self.cols as i64 / 2,
+            ..*self
+        }
+    }
+}
+
+/// Why a universe cannot be run by an engine on an unbounded plane.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnboundedError {
+    /// Births on zero neighbors would fill the plane in one generation.
+    BirthOnZero(Rule),
+    /// The plane has no edges to join or keep alive.
+    Topology(Topology),
+}
+
+impl fmt::Display for UnboundedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BirthOnZero(rule) => {
+                write!(f, "rule {} cannot be run on an unbounded plane", rule)
+            }
+            Self::Topology(topology) => {
+                write!(
+                    f,
+                    "topology {} cannot be used on an unbounded plane",
+                    topology
+                )
+            }
+        }
+    }
+}
+
+impl Error for UnboundedError {}
+
+/// The number of bands engines split each generation into when built with
+/// the `parallel` feature.
+#[cfg(feature = "parallel")]
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// The common surface of the Game of Life engines, addressed with signed
+/// coordinates so that bounded and unbounded engines are interchangeable.
+pub trait LifeEngine {
+    fn next_gen(&mut self);
+    fn generation(&self) -> u64;
+    fn cell(&self, row: i64, col: i64) -> Cell;
+    fn set_cell(&mut self, row: i64, col: i64, cell: Cell);
+    fn clear(&mut self);
+    fn population(&self) -> u64;
+    fn rule(&self) -> Rule;
+
+    /// How the edges of the grid are joined, or `None` for engines on an
+    /// unbounded plane.
+    fn topology(&self) -> Option<Topology>;
+
+    /// The area worth looking at when nothing else has been asked for.
+    fn default_viewport(&self) -> Viewport;
+
+    fn toggle_cell(&mut self, row: i64, col: i64) {
+        let toggled = self.cell(row, col).toggle();
+        self.set_cell(row, col, toggled);
+    }
+
+    /// Coordinates of the living cells inside `viewport`, relative to its
+    /// top left corner.
+    fn live_cells_in(&self, viewport: &Viewport) -> Vec<(usize, usize)> {
+        let mut live = Vec::new();
+        for r in 0..viewport.rows {
+            for c in 0..viewport.cols {
+                let (row, col) = (viewport.top + r as i64, viewport.left + c as i64);
+                if self.cell(row, col).is_alive() {
+                    live.push((r, c));
+                }
+            }
+        }
+        live
+    }
+}
+
+impl Snapshot for Universe {
+    fn memory_size(&self) -> usize {
+        mem::size_of::<Self>() + self.height() * self.width() * mem::size_of::<Cell>()
+    }
+}
+
+impl LifeEngine for Universe {
+    fn next_gen(&mut self) {
+        Universe::next_gen(self);
+    }
+
+    fn generation(&self) -> u64 {
+        Universe::generation(self).into()
+    }
+
+    fn cell(&self, row: i64, col: i64) -> Cell {
+        if self.in_bounds(row as isize, col as isize) {
+            *self.cell_at(row as usize, col as usize)
+        } else {
+            Cell::Dead
+        }
+    }
+
+    fn set_cell(&mut self, row: i64, col: i64, cell: Cell) {
+        if self.in_bounds(row as isize, col as isize) {
+            self.set_cell_at(row as usize, col as usize, cell);
+        }
+    }
+
+    fn clear(&mut self) {
+        Universe::clear(self);
+    }
+
+    fn population(&self) -> u64 {
+        self.enumerate_cells()
+            .filter(|(_, _, cell)| cell.is_alive())
+            .count() as u64
+    }
+
+    fn rule(&self) -> Rule {
+        Universe::rule(self)
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        Some(Universe::topology(self))
+    }
+
+    fn default_viewport(&self) -> Viewport {
+        Viewport::new(0, 0, self.height(), self.width())
+    }
+}
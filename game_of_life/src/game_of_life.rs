@@ -1,3 +1,7 @@
+#[cfg(feature = "parallel")]
+use crate::engine::default_threads;
+use crate::rule::Rule;
+use crate::topology::{Location, Topology};
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -7,24 +11,10 @@ pub enum Cell {
 }
 
 impl Cell {
+    /// The cell's next state under Conway's rule. `Rule::next_cell_state`
+    /// applies any rule.
     pub fn next_cell_state(&self, living_neighbors: u8) -> Self {
-        match self {
-            Cell::Alive => {
-                if living_neighbors < 2 || living_neighbors > 3 {
-                    Cell::Dead
-                } else {
-                    Cell::Alive
-                }
-            }
-
-            Cell::Dead => {
-                if living_neighbors == 3 {
-                    Cell::Alive
-                } else {
-                    Cell::Dead
-                }
-            }
-        }
+        Rule::conway().next_cell_state(*self, living_neighbors)
     }
 
     pub fn is_alive(&self) -> bool {
@@ -42,14 +32,35 @@ impl Cell {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Universe {
     generation: u32,
     height: usize,
     width: usize,
     cells: Vec<Cell>,
+    rule: Rule,
+    topology: Topology,
 }
 
 impl Universe {
+    /// The fewest cells `next_gen` steps on several threads with the
+    /// `parallel` feature; smaller grids are stepped before the threads
+    /// would have started.
+    #[cfg(feature = "parallel")]
+    pub const PARALLEL_MIN_CELLS: usize = 1 << 14;
+
+    /// The most cells a universe read from a pattern may have, so that a
+    /// few cells far apart can't take all the memory there is.
+    pub const MAX_CELLS: usize = 1 << 28;
+
+    /// How many cells a `height` by `width` grid has, or `None` if that is
+    /// more than `MAX_CELLS`.
+    pub fn grid_size(height: usize, width: usize) -> Option<usize> {
+        height
+            .checked_mul(width)
+            .filter(|&cells| cells <= Self::MAX_CELLS)
+    }
+
     fn display_grid(&self) -> String {
         let symbols: Vec<char> = self
             .cells
@@ -78,9 +89,27 @@ impl Universe {
             height,
             width,
             cells,
+            rule: Rule::default(),
+            topology: Topology::default(),
         }
     }
 
+    pub fn with_rule(self, rule: Rule) -> Self {
+        Self { rule, ..self }
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn with_topology(self, topology: Topology) -> Self {
+        Self { topology, ..self }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
     pub fn generation(&self) -> u32 {
         self.generation
     }
@@ -93,13 +122,38 @@ impl Universe {
         self.width
     }
 
+    /// The index of an on-grid cell in `cells`.
+    fn index(&self, row: usize, col: usize) -> usize {
+        assert!(
+            row < self.height && col < self.width,
+            "cell ({}, {}) is outside the {}x{} grid",
+            row,
+            col,
+            self.height,
+            self.width
+        );
+        row * self.width + col
+    }
+
+    /// Panics if the cell is off the grid; `neighbor_at` looks past the
+    /// edges.
     pub fn cell_at(&self, row: usize, col: usize) -> &Cell {
-        let index = row * self.width + col;
-        &self.cells[index]
+        &self.cells[self.index(row, col)]
+    }
+
+    /// Looks up a cell that may lie past the edge of the grid, resolving it
+    /// through the universe's topology.
+    pub fn neighbor_at(&self, row: isize, col: isize) -> &Cell {
+        match self.topology.locate(row, col, self.height, self.width) {
+            Location::Inside(r, c) => &self.cells[r * self.width + c],
+            Location::Outside(Cell::Alive) => &Cell::Alive,
+            Location::Outside(Cell::Dead) => &Cell::Dead,
+        }
     }
 
+    /// Panics if the cell is off the grid.
     pub fn set_cell_at(&mut self, row: usize, col: usize, new_cell: Cell) {
-        let index = row * self.width + col;
+        let index = self.index(row, col);
         self.cells[index] = new_cell;
     }
 
@@ -119,43 +173,87 @@ impl Universe {
         row >= 0 && row < self.height as isize && col >= 0 && col < self.width as isize
     }
 
-    pub fn enumerate_cells(&self) -> EnumerateCells<impl Iterator<Item=&Cell> + '_> {
+    pub fn enumerate_cells(&self) -> EnumerateCells<impl Iterator<Item = &Cell> + '_> {
         EnumerateCells::new(self.cells.iter(), self.width())
     }
 
-    fn increment_neighbors(&self, row: usize, col: usize, living_neighbors: &mut [u8]) {
+    pub fn living_neighbors(&self, row: usize, col: usize) -> u8 {
+        if row > 0 && col > 0 && row + 1 < self.height && col + 1 < self.width {
+            // Every neighbor is on the grid, so the topology can be skipped.
+            let alive = |start: usize, len: usize| {
+                self.cells[start..start + len]
+                    .iter()
+                    .filter(|cell| cell.is_alive())
+                    .count() as u8
+            };
+            let middle = row * self.width + col;
+            return alive(middle - self.width - 1, 3)
+                + alive(middle - 1, 1)
+                + alive(middle + 1, 1)
+                + alive(middle + self.width - 1, 3);
+        }
+        let mut count = 0;
         for dr in -1..=1 {
             for dc in -1..=1 {
                 if dr != 0 || dc != 0 {
                     let (irow, icol) = (dr + row as isize, dc + col as isize);
-                    if self.in_bounds(irow, icol) {
-                        let index = irow as usize * self.width + icol as usize;
-                        living_neighbors[index] += 1;
+                    if self.neighbor_at(irow, icol).is_alive() {
+                        count += 1;
                     }
                 }
             }
         }
+        count
     }
 
-    pub fn next_gen(&mut self) {
-        let mut new_cells: Vec<Cell> = Vec::with_capacity(self.cells.len());
-        let mut living_neighbors: Vec<u8> = vec![0; self.cells.len()];
-        
-        for (r, c, cell) in self.enumerate_cells() {
-            if cell.is_alive() {
-                self.increment_neighbors(r, c, &mut living_neighbors)
-            }
+    /// Fills `band` with the next generation of the rows starting at
+    /// `first_row`.
+    fn next_gen_band(&self, first_row: usize, band: &mut [Cell]) {
+        for (i, new_cell) in band.iter_mut().enumerate() {
+            let (r, c) = (first_row + i / self.width, i % self.width);
+            let count = self.living_neighbors(r, c);
+            *new_cell = self.rule.next_cell_state(self.cells[r * self.width + c], count);
         }
+    }
 
-        for (cell, count) in self.cells.iter().zip(living_neighbors.iter()) {
-            let new_cell = cell.next_cell_state(*count);
-            new_cells.push(new_cell);
+    /// Advances a generation, on several threads with the `parallel`
+    /// feature once the grid has `PARALLEL_MIN_CELLS`.
+    pub fn next_gen(&mut self) {
+        #[cfg(feature = "parallel")]
+        if self.cells.len() >= Self::PARALLEL_MIN_CELLS {
+            self.par_next_gen(default_threads());
+            return;
         }
+        self.serial_next_gen();
+    }
+
+    /// Advances a generation on the calling thread, whatever the grid's
+    /// size.
+    pub fn serial_next_gen(&mut self) {
+        let mut new_cells = vec![Cell::Dead; self.cells.len()];
+        self.next_gen_band(0, &mut new_cells);
         self.generation += 1;
         self.cells = new_cells;
     }
-}
 
+    /// Advances a generation with the rows split into `threads` bands that
+    /// are stepped concurrently. The result is identical to
+    /// `serial_next_gen`.
+    #[cfg(feature = "parallel")]
+    pub fn par_next_gen(&mut self, threads: usize) {
+        let band_rows = self.height.div_ceil(threads.max(1)).max(1);
+        let mut new_cells = vec![Cell::Dead; self.cells.len()];
+        let universe = &*self;
+        std::thread::scope(|scope| {
+            let bands = new_cells.chunks_mut((band_rows * universe.width).max(1));
+            for (i, band) in bands.enumerate() {
+                scope.spawn(move || universe.next_gen_band(i * band_rows, band));
+            }
+        });
+        self.generation += 1;
+        self.cells = new_cells;
+    }
+}
 
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -190,7 +288,7 @@ impl<I> EnumerateCells<I> {
 
 impl<I> Iterator for EnumerateCells<I>
 where
-    I: Iterator
+    I: Iterator,
 {
     type Item = (usize, usize, <I as Iterator>::Item);
 
@@ -250,7 +348,6 @@ mod game_of_life_tests {
         Universe::new(&cells, width)
     }
 
-
     fn simple_universe_string() -> String {
         format!(
             "{}\n{}\n{}\n{}\n{}\n{}",
@@ -332,4 +429,149 @@ mod game_of_life_tests {
         let expected_next_gen = format!("{}\n{}\n{}\n{}", "Generation 1:", "2 2", "**", "**");
         assert_eq!(expected_next_gen, universe.to_string());
     }
+
+    #[test]
+    fn a_universe_uses_conway_rules_by_default() {
+        assert_eq!(Rule::conway(), simple_universe().rule());
+    }
+
+    #[test]
+    fn a_universe_advances_using_its_rule() {
+        // A dead cell with six neighbors is only born under HighLife
+        let universe_string = format!(
+            "{}\n{}\n{}\n{}\n{}",
+            "Generation 0:", "3 3", "**.", "*.*", "**."
+        );
+        let mut conway = from_string(&universe_string);
+        let mut highlife = from_string(&universe_string).with_rule("B36/S23".parse().unwrap());
+        conway.next_gen();
+        highlife.next_gen();
+        assert_eq!(&Cell::Dead, conway.cell_at(1, 1));
+        assert_eq!(&Cell::Alive, highlife.cell_at(1, 1));
+    }
+
+    fn glider_universe(topology: Topology) -> Universe {
+        let universe_string = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            "Generation 0:", "5 5", ".....", "..*..", "...*.", ".***.", "....."
+        );
+        from_string(&universe_string).with_topology(topology)
+    }
+
+    fn live_cells(universe: &Universe) -> Vec<(usize, usize)> {
+        universe
+            .enumerate_cells()
+            .filter(|(_, _, c)| c.is_alive())
+            .map(|(r, c, _)| (r, c))
+            .collect()
+    }
+
+    #[test]
+    fn a_universe_is_bounded_by_default() {
+        assert_eq!(Topology::Bounded, simple_universe().topology());
+    }
+
+    #[test]
+    fn a_glider_dies_against_a_bounded_edge() {
+        let mut universe = glider_universe(Topology::Bounded);
+        for _ in 0..20 {
+            universe.next_gen();
+        }
+        // It runs into the bottom right corner and settles as a block
+        assert_eq!(vec![(3, 3), (3, 4), (4, 3), (4, 4)], live_cells(&universe));
+    }
+
+    #[test]
+    fn a_glider_returns_to_its_start_on_a_torus() {
+        // A glider moves one cell diagonally every four generations
+        let mut universe = glider_universe(Topology::Torus);
+        for _ in 0..20 {
+            universe.next_gen();
+        }
+        let expected = glider_universe(Topology::Torus).to_string();
+        assert_eq!(
+            expected.replace("Generation 0", "Generation 20"),
+            universe.to_string()
+        );
+    }
+
+    #[test]
+    fn cell_lookups_past_the_edge_follow_the_topology() {
+        let torus = glider_universe(Topology::Torus);
+        assert_eq!(&Cell::Alive, torus.neighbor_at(6, 2));
+        assert_eq!(&Cell::Alive, torus.neighbor_at(-2, 3));
+        let klein = glider_universe(Topology::KleinBottle);
+        assert_eq!(&Cell::Alive, klein.neighbor_at(-2, 1));
+        let bounded = glider_universe(Topology::Bounded);
+        assert_eq!(&Cell::Dead, bounded.neighbor_at(-2, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the 5x5 grid")]
+    fn cell_at_panics_off_the_grid_whatever_the_topology() {
+        glider_universe(Topology::Torus).cell_at(6, 2);
+    }
+
+    #[test]
+    fn alive_edges_count_as_neighbors() {
+        let universe = glider_universe(Topology::AliveEdge);
+        assert_eq!(5, universe.living_neighbors(0, 0));
+        assert_eq!(4, universe.living_neighbors(0, 2));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_stepping_matches_serial_stepping() {
+        let mut rng = crate::rng::Rng::new(42);
+        let cells: Vec<Cell> = (0..61 * 37)
+            .map(|_| match rng.next_rnd() >> 16 & 1 {
+                0 => Cell::Dead,
+                _ => Cell::Alive,
+            })
+            .collect();
+        for topology in [Topology::Bounded, Topology::Torus, Topology::KleinBottle] {
+            for threads in [1, 2, 3, 8, 61, 100] {
+                let mut serial = Universe::new(&cells, 37).with_topology(topology);
+                let mut parallel = Universe::new(&cells, 37).with_topology(topology);
+                for _ in 0..10 {
+                    serial.serial_next_gen();
+                    parallel.par_next_gen(threads);
+                }
+                assert_eq!(serial.to_string(), parallel.to_string());
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn large_grids_step_the_same_in_parallel() {
+        let mut rng = crate::rng::Rng::new(7);
+        let cells: Vec<Cell> = (0..Universe::PARALLEL_MIN_CELLS)
+            .map(|_| match rng.next_rnd() >> 16 & 1 {
+                0 => Cell::Dead,
+                _ => Cell::Alive,
+            })
+            .collect();
+        let mut serial = Universe::new(&cells, 128).with_topology(Topology::Torus);
+        let mut parallel = serial.clone();
+        for _ in 0..3 {
+            serial.serial_next_gen();
+            parallel.next_gen();
+        }
+        assert_eq!(serial.to_string(), parallel.to_string());
+    }
+
+    #[test]
+    fn a_glider_crossing_a_klein_bottle_edge_is_mirrored() {
+        let mut universe = glider_universe(Topology::KleinBottle);
+        for _ in 0..12 {
+            universe.next_gen();
+        }
+        // The glider survives the crossing but comes back mirrored, where on
+        // a torus it would be at (0, 1), (1, 0), (1, 1), (1, 4), (4, 0)
+        assert_eq!(
+            vec![(0, 3), (1, 0), (1, 3), (1, 4), (4, 0)],
+            live_cells(&universe)
+        );
+    }
 }
@@ -1,54 +1,749 @@
+use crate::engine::{LifeEngine, Viewport};
 use crate::game_of_life::{Cell, Universe};
-use automata_core::grid_view::{Controller, Entity, GridViewModel};
+use crate::history::{EditHistory, History, Snapshot, DEFAULT_HISTORY_MEMORY};
+use crate::rng::Rng;
+use crate::soup::Soup;
+use automata_core::model::{Controller, Entity, GridViewModel, PaintButton};
+use automata_core::selection::{Edit, Region, Transform};
+use std::collections::HashSet;
 
-pub struct GameOfLifeController {
-    model: Universe,
+pub struct GameOfLifeController<E = Universe>
+where
+    E: LifeEngine + Snapshot,
+{
+    model: E,
+    viewport: Viewport,
     live_color: [f32; 4],
     dead_color: [f32; 4],
+    /// What a drag in progress sets cells to.
+    paint: Option<Cell>,
+    /// Cells copied or cut from a selection, with the engine's rule.
+    clipboard: Option<Universe>,
+    /// What `reset` goes back to: the engine the controller was made with,
+    /// or the last new soup.
+    initial: E,
+    /// Seeds random fills of selections and new soups.
+    rng: Rng,
+    /// How random fills and new soups are made, apart from their seeds.
+    soup: Soup,
+    /// The seed of the soup the engine started from, if it did.
+    soup_seed: Option<u64>,
+    edits: EditHistory,
+    /// The engine as it was before each of the last few generations.
+    history: History<E>,
 }
 
-impl GameOfLifeController {
-    pub fn new(model: Universe, live_color: [f32; 4], dead_color: [f32; 4]) -> Self {
+impl<E> GameOfLifeController<E>
+where
+    E: LifeEngine + Snapshot,
+{
+    pub fn new(model: E, live_color: [f32; 4], dead_color: [f32; 4]) -> Self {
+        let viewport = model.default_viewport();
         Self {
+            initial: model.clone(),
             model,
+            viewport,
             live_color,
             dead_color,
+            paint: None,
+            clipboard: None,
+            rng: Rng::new(0),
+            soup: Soup::new(0),
+            soup_seed: None,
+            edits: EditHistory::new(),
+            history: History::new(DEFAULT_HISTORY_MEMORY),
         }
     }
 
-    fn build_view_model(&self) -> GridViewModel {
-        let mut entities = Vec::new();
-        for row in 0..self.model.height() {
-            for col in 0..self.model.width() {
-                if let Cell::Alive = self.model.cell_at(row, col) {
-                    let entity = Entity::new(self.live_color, row, col);
-                    entities.push(entity);
-                }
+    /// Caps the memory kept past generations take up, in bytes. Zero turns
+    /// stepping back off.
+    pub fn with_history_memory(self, max_memory: usize) -> Self {
+        Self {
+            history: History::new(max_memory),
+            ..self
+        }
+    }
+
+    /// Seeds the random numbers that random fills and new soups take their
+    /// seeds from.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            ..self
+        }
+    }
+
+    /// Makes random fills and new soups with the density and symmetry of
+    /// `soup`, and new soups its size.
+    pub fn with_soup(self, soup: Soup) -> Self {
+        Self { soup, ..self }
+    }
+
+    /// Says the engine was filled with `soup`, so that its seed is shown,
+    /// and makes new soups like it.
+    pub fn with_starting_soup(self, soup: Soup) -> Self {
+        Self {
+            soup_seed: Some(soup.seed()),
+            ..self.with_soup(soup)
+        }
+    }
+
+    pub fn with_viewport(self, viewport: Viewport) -> Self {
+        Self { viewport, ..self }
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// The engine coordinates of a cell in the viewport.
+    fn engine_coords(&self, row: usize, col: usize) -> (i64, i64) {
+        (
+            self.viewport.top + row as i64,
+            self.viewport.left + col as i64,
+        )
+    }
+
+    /// Sets a cell as part of the open edit.
+    fn set(&mut self, row: i64, col: i64, cell: Cell) {
+        let before = self.model.cell(row, col);
+        self.model.set_cell(row, col, cell);
+        self.edits
+            .record(row, col, before, self.model.cell(row, col));
+    }
+
+    /// Switches to `engine`, recording the cells that differ as an edit so
+    /// that undo brings the old ones back.
+    fn replace(&mut self, engine: E) {
+        let live = |engine: &E| {
+            let viewport = engine.default_viewport();
+            engine
+                .live_cells_in(&viewport)
+                .into_iter()
+                .map(|(row, col)| (viewport.top + row as i64, viewport.left + col as i64))
+                .collect::<HashSet<_>>()
+        };
+        let (before, after) = (live(&self.model), live(&engine));
+        for &(row, col) in before.difference(&after) {
+            self.edits.record(row, col, Cell::Alive, Cell::Dead);
+        }
+        for &(row, col) in after.difference(&before) {
+            self.edits.record(row, col, Cell::Dead, Cell::Alive);
+        }
+        self.edits.commit();
+        self.model = engine;
+    }
+
+    /// Remembers the engine as it is, to step back to. An engine too big
+    /// for the history isn't copied at all, and leaves it empty, as stepping
+    /// back past it would skip a generation.
+    fn remember(&mut self) {
+        if self.history.fits(&self.model) {
+            self.history.push(self.model.clone());
+        } else {
+            self.history.clear();
+        }
+    }
+
+    /// The cells of `region` of the viewport as a pattern.
+    fn copy(&self, region: Region) -> Universe {
+        let cells: Vec<Cell> = region
+            .cells()
+            .map(|(row, col)| {
+                let (row, col) = self.engine_coords(row, col);
+                self.model.cell(row, col)
+            })
+            .collect();
+        Universe::new(&cells, region.cols).with_rule(self.model.rule())
+    }
+
+    fn fill(&mut self, region: Region, cell: Cell) {
+        for (row, col) in region.cells() {
+            let (row, col) = self.engine_coords(row, col);
+            self.set(row, col, cell);
+        }
+    }
+
+    /// The soup settings with a fresh seed.
+    fn next_soup(&mut self) -> Soup {
+        self.soup.with_seed(self.rng.next_u64())
+    }
+
+    /// Brings `pattern`'s live cells to life with its top left corner at
+    /// `(row, col)` of the viewport.
+    fn stamp(&mut self, pattern: &Universe, row: usize, col: usize) {
+        for (r, c, cell) in pattern.enumerate_cells() {
+            if cell.is_alive() {
+                let (row, col) = self.engine_coords(row + r, col + c);
+                self.set(row, col, Cell::Alive);
             }
         }
-        GridViewModel::new(
-            self.model.height(),
-            self.model.width(),
-            entities,
+    }
+
+    fn build_view_model(&self) -> GridViewModel {
+        view_model(
+            &self.model,
+            &self.viewport,
+            self.live_color,
             self.dead_color,
         )
     }
 }
 
-impl Controller for GameOfLifeController {
+fn transformed(pattern: &Universe, transform: Transform) -> Universe {
+    match transform {
+        Transform::RotateClockwise => pattern.rotated_clockwise(),
+        Transform::FlipHorizontal => pattern.flipped_horizontally(),
+        Transform::FlipVertical => pattern.flipped_vertically(),
+    }
+}
+
+/// Shows the part of `engine` inside `viewport`, as `GameOfLifeController`
+/// does, for drawing a universe that isn't being run in a window.
+pub fn view_model<E: LifeEngine>(
+    engine: &E,
+    viewport: &Viewport,
+    live_color: [f32; 4],
+    dead_color: [f32; 4],
+) -> GridViewModel {
+    let entities = engine
+        .live_cells_in(viewport)
+        .into_iter()
+        .map(|(row, col)| Entity::new(live_color, row, col))
+        .collect();
+    GridViewModel::new(viewport.rows, viewport.cols, entities, dead_color)
+}
+
+impl<E> Controller for GameOfLifeController<E>
+where
+    E: LifeEngine + Snapshot,
+{
+    /// Edits can't be undone once the generation moves on, as the cells
+    /// they changed have evolved since.
     fn update(&mut self) {
+        self.remember();
         self.model.next_gen();
+        self.edits.clear();
     }
 
     fn mouse_click(&mut self, row: usize, col: usize) {
-        self.model.toggle_cell_at(row, col);
+        let (row, col) = self.engine_coords(row, col);
+        self.set(row, col, self.model.cell(row, col).toggle());
+        self.edits.commit();
+    }
+
+    /// A left drag paints whatever the first cell was toggled to along its
+    /// path, and a right drag kills every cell it crosses.
+    fn mouse_press(&mut self, button: PaintButton, row: usize, col: usize) {
+        let (row, col) = self.engine_coords(row, col);
+        let paint = match button {
+            PaintButton::Left => self.model.cell(row, col).toggle(),
+            PaintButton::Right => Cell::Dead,
+        };
+        self.set(row, col, paint);
+        self.paint = Some(paint);
+    }
+
+    fn mouse_drag(&mut self, _button: PaintButton, row: usize, col: usize) {
+        if let Some(paint) = self.paint {
+            let (row, col) = self.engine_coords(row, col);
+            self.set(row, col, paint);
+        }
     }
 
+    fn mouse_release(&mut self, _button: PaintButton) {
+        self.paint = None;
+        self.edits.commit();
+    }
+
+    /// Clearing also starts the generations again from zero, so the cleared
+    /// engine is remembered as well for stepping back to. Undo brings back
+    /// only the cells, leaving the generation at zero; stepping back
+    /// restores both.
     fn clear(&mut self) {
+        self.remember();
+        let viewport = self.model.default_viewport();
+        for (row, col) in self.model.live_cells_in(&viewport) {
+            let (row, col) = (viewport.top + row as i64, viewport.left + col as i64);
+            self.edits.record(row, col, Cell::Alive, Cell::Dead);
+        }
+        self.edits.commit();
         self.model.clear();
     }
 
+    /// Goes back to the starting engine. The engine being left is
+    /// remembered, so stepping back returns to it, generation and all, and
+    /// its cells can be brought back with undo, which leaves the generation
+    /// at zero.
+    fn reset(&mut self) {
+        self.remember();
+        self.replace(self.initial.clone());
+    }
+
+    /// Fills the viewport with a soup seeded from the controller's own
+    /// random numbers, which then becomes what `reset` goes back to. Like
+    /// `reset`, it can be stepped back from and undone.
+    fn new_soup(&mut self) {
+        self.remember();
+        let soup = self.next_soup();
+        let mut engine = self.model.clone();
+        engine.clear();
+        for (row, col) in soup.live_cells(self.viewport.rows, self.viewport.cols) {
+            let (row, col) = self.engine_coords(row, col);
+            engine.set_cell(row, col, Cell::Alive);
+        }
+        self.initial = engine.clone();
+        self.soup_seed = Some(soup.seed());
+        self.replace(engine);
+    }
+
+    fn undo(&mut self) {
+        for (row, col, cell) in self.edits.undo() {
+            self.model.set_cell(row, col, cell);
+        }
+    }
+
+    fn redo(&mut self) {
+        for (row, col, cell) in self.edits.redo() {
+            self.model.set_cell(row, col, cell);
+        }
+    }
+
+    fn step_back(&mut self) {
+        if let Some(state) = self.history.pop() {
+            self.model = state;
+            self.edits.clear();
+        }
+    }
+
     fn model(&self) -> GridViewModel {
         self.build_view_model()
     }
+
+    fn edit(&mut self, edit: Edit) {
+        match edit {
+            Edit::Copy(region) => self.clipboard = Some(self.copy(region)),
+            Edit::Cut(region) => {
+                self.clipboard = Some(self.copy(region));
+                self.fill(region, Cell::Dead);
+            }
+            Edit::Clear(region) => self.fill(region, Cell::Dead),
+            Edit::FillRandom(region) => {
+                let soup = self.next_soup().with_size(region.rows, region.cols);
+                self.fill(region, Cell::Dead);
+                for (row, col) in soup.live_cells(region.rows, region.cols) {
+                    let (row, col) = self.engine_coords(region.top + row, region.left + col);
+                    self.set(row, col, Cell::Alive);
+                }
+            }
+            Edit::Transform(region, transform) => {
+                let pattern = transformed(&self.copy(region), transform);
+                // Rotating a region that isn't square moves it onto cells
+                // it didn't cover, which are cleared too.
+                self.fill(region, Cell::Dead);
+                self.fill(region.transformed(transform), Cell::Dead);
+                self.stamp(&pattern, region.top, region.left);
+            }
+            Edit::TransformClipboard(transform) => {
+                self.clipboard = self
+                    .clipboard
+                    .as_ref()
+                    .map(|clipboard| transformed(clipboard, transform));
+            }
+            Edit::Paste { row, col } => {
+                if let Some(clipboard) = self.clipboard.take() {
+                    self.stamp(&clipboard, row, col);
+                    self.clipboard = Some(clipboard);
+                }
+            }
+        }
+        self.edits.commit();
+    }
+
+    fn clipboard(&self) -> Option<GridViewModel> {
+        self.clipboard.as_ref().map(|clipboard| {
+            view_model(
+                clipboard,
+                &clipboard.default_viewport(),
+                self.live_color,
+                self.dead_color,
+            )
+        })
+    }
+
+    fn clipboard_text(&self) -> Option<String> {
+        self.clipboard.as_ref().map(Universe::to_rle)
+    }
+
+    fn status(&self) -> Vec<(String, String)> {
+        let topology = match self.model.topology() {
+            Some(topology) => topology.to_string(),
+            None => "unbounded".to_string(),
+        };
+        let status = [
+            ("generation", self.model.generation().to_string()),
+            ("population", self.model.population().to_string()),
+            ("rule", self.model.rule().to_string()),
+            ("topology", topology),
+            ("rewind", format!("{} generations", self.history.len())),
+        ];
+        let seed = self.soup_seed.map(|seed| ("seed", seed.to_string()));
+        status
+            .into_iter()
+            .chain(seed)
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod game_of_life_controller_tests {
+    use super::*;
+    use crate::sparse::SparseUniverse;
+    use crate::topology::Topology;
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+    fn value<'a>(status: &'a [(String, String)], key: &str) -> &'a str {
+        let (_, value) = status.iter().find(|(k, _)| k == key).unwrap();
+        value
+    }
+
+    #[test]
+    fn status_reports_generation_population_rule_and_topology() {
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o$5b$5b!")
+            .unwrap()
+            .with_topology(Topology::Torus);
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.update();
+        let status = controller.status();
+        assert_eq!("1", value(&status, "generation"));
+        assert_eq!("3", value(&status, "population"));
+        assert_eq!("B3/S23", value(&status, "rule"));
+        assert_eq!("torus", value(&status, "topology"));
+    }
+
+    #[test]
+    fn dragging_paints_the_state_the_first_cell_was_toggled_to() {
+        let universe = Universe::from_rle("x = 3, y = 2\nbo$3o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.mouse_press(PaintButton::Left, 0, 0);
+        controller.mouse_drag(PaintButton::Left, 0, 1);
+        controller.mouse_drag(PaintButton::Left, 0, 2);
+        controller.mouse_release(PaintButton::Left);
+        assert_eq!(6, controller.model.population());
+
+        controller.mouse_press(PaintButton::Left, 1, 0);
+        controller.mouse_drag(PaintButton::Left, 1, 1);
+        controller.mouse_release(PaintButton::Left);
+        assert_eq!(4, controller.model.population());
+        assert!(controller.model.cell(1, 2).is_alive());
+
+        controller.mouse_drag(PaintButton::Left, 1, 2);
+        assert!(controller.model.cell(1, 2).is_alive());
+    }
+
+    #[test]
+    fn right_dragging_erases() {
+        let universe = Universe::from_rle("x = 3, y = 1\nobo!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.mouse_press(PaintButton::Right, 0, 0);
+        controller.mouse_drag(PaintButton::Right, 0, 1);
+        controller.mouse_drag(PaintButton::Right, 0, 2);
+        controller.mouse_release(PaintButton::Right);
+        assert_eq!(0, controller.model.population());
+    }
+
+    fn live_cells<E: LifeEngine + Snapshot>(
+        controller: &GameOfLifeController<E>,
+    ) -> Vec<(usize, usize)> {
+        controller.model.live_cells_in(&controller.viewport)
+    }
+
+    #[test]
+    fn copies_and_pastes_the_live_cells_of_a_selection() {
+        let universe = Universe::from_rle("x = 6, y = 4\n2o$bo$$5bo!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.edit(Edit::Copy(Region::new(0, 0, 2, 2)));
+        assert_eq!(
+            Some("x = 2, y = 2, rule = B3/S23\n2o$bo!\n".to_string()),
+            controller.clipboard_text()
+        );
+        let preview = controller.clipboard().unwrap();
+        assert_eq!(
+            (2, 2, 3),
+            (preview.rows(), preview.cols(), preview.entities().len())
+        );
+
+        controller.edit(Edit::Paste { row: 2, col: 4 });
+        assert_eq!(
+            vec![(0, 0), (0, 1), (1, 1), (2, 4), (2, 5), (3, 5)],
+            live_cells(&controller)
+        );
+    }
+
+    #[test]
+    fn cutting_and_clearing_kill_the_selection() {
+        let universe = Universe::from_rle("x = 3, y = 3\n3o$3o$3o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.edit(Edit::Cut(Region::new(0, 0, 1, 3)));
+        controller.edit(Edit::Clear(Region::new(1, 2, 2, 1)));
+        assert_eq!(
+            vec![(1, 0), (1, 1), (2, 0), (2, 1)],
+            live_cells(&controller)
+        );
+        assert_eq!(
+            Some("x = 3, y = 1, rule = B3/S23\n3o!\n".to_string()),
+            controller.clipboard_text()
+        );
+    }
+
+    #[test]
+    fn transforms_selections_in_place_and_the_clipboard() {
+        let universe = Universe::from_rle("x = 4, y = 4\n3o$o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.edit(Edit::Transform(
+            Region::new(0, 0, 2, 3),
+            Transform::RotateClockwise,
+        ));
+        assert_eq!(
+            vec![(0, 0), (0, 1), (1, 1), (2, 1)],
+            live_cells(&controller)
+        );
+
+        controller.edit(Edit::Copy(Region::new(0, 0, 3, 2)));
+        controller.edit(Edit::TransformClipboard(Transform::FlipVertical));
+        assert_eq!(
+            Some("x = 2, y = 3, rule = B3/S23\nbo$bo$2o!\n".to_string()),
+            controller.clipboard_text()
+        );
+    }
+
+    #[test]
+    fn rotated_selections_replace_the_cells_under_their_new_shape() {
+        let universe = Universe::from_rle("x = 4, y = 4\n3o$o$o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.edit(Edit::Transform(
+            Region::new(0, 0, 2, 3),
+            Transform::RotateClockwise,
+        ));
+        assert_eq!(
+            vec![(0, 0), (0, 1), (1, 1), (2, 1)],
+            live_cells(&controller)
+        );
+    }
+
+    #[test]
+    fn random_fills_stay_inside_the_selection() {
+        let universe = Universe::new(&[Cell::Dead; 100], 10);
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK).with_seed(7);
+        let region = Region::new(2, 3, 5, 4);
+        controller.edit(Edit::FillRandom(region));
+        let live = live_cells(&controller);
+        assert!(live.iter().all(|&(row, col)| region.contains(row, col)));
+        assert!(live.len() > 2 && live.len() < 18, "{}", live.len());
+    }
+
+    #[test]
+    fn undoes_and_redoes_clicks_drags_and_clears() {
+        let universe = Universe::from_rle("x = 3, y = 3\no!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.mouse_click(2, 2);
+        controller.mouse_press(PaintButton::Left, 1, 0);
+        controller.mouse_drag(PaintButton::Left, 1, 1);
+        controller.mouse_release(PaintButton::Left);
+        controller.clear();
+        assert!(live_cells(&controller).is_empty());
+
+        controller.undo();
+        assert_eq!(
+            vec![(0, 0), (1, 0), (1, 1), (2, 2)],
+            live_cells(&controller)
+        );
+        controller.undo();
+        assert_eq!(vec![(0, 0), (2, 2)], live_cells(&controller));
+        controller.undo();
+        assert_eq!(vec![(0, 0)], live_cells(&controller));
+        controller.undo();
+        assert_eq!(vec![(0, 0)], live_cells(&controller));
+
+        controller.redo();
+        controller.redo();
+        assert_eq!(
+            vec![(0, 0), (1, 0), (1, 1), (2, 2)],
+            live_cells(&controller)
+        );
+    }
+
+    #[test]
+    fn edits_cannot_be_undone_once_the_generation_moves_on() {
+        let start = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let mut controller = GameOfLifeController::new(start.clone(), WHITE, BLACK);
+        controller.mouse_click(0, 4);
+        controller.update();
+        controller.undo();
+        let mut next = start;
+        next.set_cell_at(0, 4, Cell::Alive);
+        LifeEngine::next_gen(&mut next);
+        assert_eq!(
+            next.live_cells_in(&next.default_viewport()),
+            live_cells(&controller)
+        );
+
+        controller.mouse_click(4, 4);
+        controller.step_back();
+        let stepped_back = live_cells(&controller);
+        controller.undo();
+        assert_eq!(stepped_back, live_cells(&controller));
+        assert!(!controller.model.cell(4, 4).is_alive());
+    }
+
+    #[test]
+    fn steps_back_through_remembered_generations() {
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        let start = live_cells(&controller);
+        controller.update();
+        controller.update();
+        controller.update();
+        assert_eq!("3 generations", value(&controller.status(), "rewind"));
+        controller.step_back();
+        assert_eq!(2, controller.model.generation());
+        controller.step_back();
+        controller.step_back();
+        assert_eq!(0, controller.model.generation());
+        assert_eq!(start, live_cells(&controller));
+        controller.step_back();
+        assert_eq!(0, controller.model.generation());
+    }
+
+    #[test]
+    fn stepping_back_undoes_a_clear_and_can_be_turned_off() {
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.update();
+        controller.clear();
+        controller.step_back();
+        assert_eq!(1, controller.model.generation());
+        assert_eq!(3, controller.model.population());
+
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let mut controller =
+            GameOfLifeController::new(universe, WHITE, BLACK).with_history_memory(0);
+        controller.update();
+        controller.step_back();
+        assert_eq!(1, controller.model.generation());
+    }
+
+    #[test]
+    fn engines_too_big_for_the_history_are_not_remembered() {
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let size = universe.memory_size();
+        let mut controller =
+            GameOfLifeController::new(universe.clone(), WHITE, BLACK).with_history_memory(size);
+        controller.update();
+        controller.update();
+        assert_eq!("1 generations", value(&controller.status(), "rewind"));
+
+        let mut controller =
+            GameOfLifeController::new(universe, WHITE, BLACK).with_history_memory(size - 1);
+        controller.update();
+        assert_eq!("0 generations", value(&controller.status(), "rewind"));
+    }
+
+    #[test]
+    fn undoing_a_clear_brings_back_only_the_cells() {
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        controller.update();
+        let cells = live_cells(&controller);
+        controller.clear();
+        controller.undo();
+        assert_eq!(cells, live_cells(&controller));
+        assert_eq!(0, controller.model.generation());
+    }
+
+    #[test]
+    fn resets_to_the_starting_cells_and_generation() {
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK);
+        let start = live_cells(&controller);
+        controller.update();
+        controller.mouse_click(0, 0);
+        controller.reset();
+        assert_eq!(0, controller.model.generation());
+        assert_eq!(start, live_cells(&controller));
+        controller.step_back();
+        assert_eq!(1, controller.model.generation());
+        assert!(controller.model.cell(0, 0).is_alive());
+    }
+
+    #[test]
+    fn new_soups_fill_the_viewport_and_become_the_reset_point() {
+        let universe = Universe::new(&[Cell::Dead; 400], 20);
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK)
+            .with_seed(3)
+            .with_soup(Soup::new(0).with_density(0.25));
+        controller.update();
+        controller.new_soup();
+        let soup = live_cells(&controller);
+        assert_eq!(0, controller.model.generation());
+        // The seed shown is enough to make the same soup again.
+        let seed = value(&controller.status(), "seed").parse().unwrap();
+        let again = Soup::new(seed).with_density(0.25).live_cells(20, 20);
+        assert_eq!(again, soup);
+        assert!(soup.len() > 60 && soup.len() < 140, "{}", soup.len());
+
+        controller.new_soup();
+        assert_ne!(soup, live_cells(&controller));
+        let second = live_cells(&controller);
+        controller.update();
+        controller.reset();
+        assert_eq!(second, live_cells(&controller));
+    }
+
+    #[test]
+    fn new_soups_and_resets_can_be_undone_without_history() {
+        let universe = Universe::from_rle("x = 5, y = 5\n5b$5b$b3o!").unwrap();
+        let mut controller = GameOfLifeController::new(universe, WHITE, BLACK)
+            .with_seed(3)
+            .with_history_memory(0);
+        let start = live_cells(&controller);
+        controller.clear();
+        controller.new_soup();
+        let soup = live_cells(&controller);
+        assert!(!soup.is_empty());
+        controller.undo();
+        assert!(live_cells(&controller).is_empty());
+        controller.undo();
+        assert_eq!(start, live_cells(&controller));
+
+        controller.redo();
+        controller.redo();
+        assert_eq!(soup, live_cells(&controller));
+        controller.update();
+        let evolved = live_cells(&controller);
+        assert_ne!(soup, evolved);
+        controller.reset();
+        assert_eq!(soup, live_cells(&controller));
+        controller.undo();
+        assert_eq!(evolved, live_cells(&controller));
+    }
+
+    #[test]
+    fn shows_the_seed_of_a_starting_soup() {
+        let soup = Soup::new(1234).with_size(5, 5);
+        let controller =
+            GameOfLifeController::new(soup.universe(10, 10), WHITE, BLACK).with_starting_soup(soup);
+        assert_eq!("1234", value(&controller.status(), "seed"));
+        let controller = GameOfLifeController::new(soup.universe(10, 10), WHITE, BLACK);
+        assert!(controller.status().iter().all(|(key, _)| key != "seed"));
+    }
+
+    #[test]
+    fn unbounded_engines_have_no_topology() {
+        let controller = GameOfLifeController::new(SparseUniverse::new(), WHITE, BLACK);
+        assert_eq!("unbounded", value(&controller.status(), "topology"));
+    }
 }
@@ -0,0 +1,832 @@
+use crate::engine::{LifeEngine, UnboundedError, Viewport};
+use crate::game_of_life::{Cell, Universe};
+use crate::history::Snapshot;
+use crate::pattern::PatternError;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+mod macrocell;
+
+pub use macrocell::{MacrocellError, MacrocellErrorKind};
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// A pattern grew, or was asked to jump, further than the `2^61` cells
+/// either side of the origin that a `HashLife` root can reach.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OutOfRangeError;
+
+impl fmt::Display for OutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pattern has grown too large for HashLife")
+    }
+}
+
+impl Error for OutOfRangeError {}
+
+/// A canonical quadtree node. Level 0 nodes are single cells; a node at
+/// level `k` covers a `2^k x 2^k` square made of four level `k - 1` nodes.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+    level: u8,
+    population: u64,
+}
+
+/// A memoized quadtree (HashLife) engine. Identical sub-patterns share a
+/// single node and their futures are cached, so regular patterns can be
+/// advanced by enormous numbers of generations at once.
+///
+/// The root is always centred on the origin: a root at level `k` covers rows
+/// and columns `-2^(k-1)..2^(k-1)`.
+#[derive(Debug, Clone)]
+pub struct HashLife {
+    nodes: Vec<Node>,
+    index: HashMap<[NodeId; 4], NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+    root: NodeId,
+    generation: u64,
+    rule: Rule,
+    cache_limit: Option<usize>,
+}
+
+impl HashLife {
+    const MIN_ROOT_LEVEL: u8 = 3;
+    const MAX_ROOT_LEVEL: u8 = 62;
+    /// The biggest jump `step_pow2` can make: it needs a root two levels
+    /// above the step, plus one more of padding.
+    pub const MAX_STEP_LOG: u8 = Self::MAX_ROOT_LEVEL - 3;
+
+    pub fn new() -> Self {
+        let leaf = |population| Node {
+            nw: DEAD,
+            ne: DEAD,
+            sw: DEAD,
+            se: DEAD,
+            level: 0,
+            population,
+        };
+        let mut life = Self {
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            generation: 0,
+            rule: Rule::default(),
+            cache_limit: None,
+        };
+        life.root = life.empty_node(Self::MIN_ROOT_LEVEL);
+        life
+    }
+
+    /// Places the living cells of `universe` at the same coordinates,
+    /// keeping its rule. Only bounded universes can be placed, as the plane
+    /// has no edges for other topologies to join.
+    pub fn from_universe(universe: &Universe) -> Result<Self, UnboundedError> {
+        if universe.topology() != Topology::Bounded {
+            return Err(UnboundedError::Topology(universe.topology()));
+        }
+        let mut life = Self::new().with_rule(universe.rule())?;
+        for (r, c, cell) in universe.enumerate_cells() {
+            if cell.is_alive() {
+                life.set_cell_at(r as i64, c as i64, Cell::Alive);
+            }
+        }
+        Ok(life)
+    }
+
+    /// Copies the living cells into the smallest `Universe` that holds them
+    /// all. Fails when there are none, or when they are spread over more
+    /// than `Universe::MAX_CELLS`.
+    pub fn to_universe(&self) -> Result<Universe, PatternError> {
+        let bounds = self.bounding_box().ok_or(PatternError::EmptyPattern)?;
+        let size = Universe::grid_size(bounds.rows, bounds.cols)
+            .ok_or(PatternError::TooLarge(bounds.rows, bounds.cols))?;
+        let mut cells = vec![Cell::Dead; size];
+        for (r, c) in self.live_cells_in(&bounds) {
+            cells[r * bounds.cols + c] = Cell::Alive;
+        }
+        Ok(Universe::new(&cells, bounds.cols).with_rule(self.rule))
+    }
+
+    /// Fails for rules with births on zero neighbors, which would fill the
+    /// plane in one generation.
+    pub fn with_rule(mut self, rule: Rule) -> Result<Self, UnboundedError> {
+        if rule.births(0) {
+            return Err(UnboundedError::BirthOnZero(rule));
+        }
+        if rule != self.rule {
+            self.results.clear();
+        }
+        self.rule = rule;
+        Ok(self)
+    }
+
+    /// Collects garbage whenever a step leaves more than `max_nodes` nodes
+    /// in the cache. Only the nodes of the current pattern survive a
+    /// collection, so a pattern bigger than the limit is still kept whole.
+    pub fn with_cache_limit(self, max_nodes: usize) -> Self {
+        Self {
+            cache_limit: Some(max_nodes),
+            ..self
+        }
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u64 {
+        self.node(self.root).population
+    }
+
+    /// The number of distinct nodes currently held, live or cached.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id as usize]
+    }
+
+    fn level(&self, id: NodeId) -> u8 {
+        self.node(id).level
+    }
+
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        let key = [nw, ne, sw, se];
+        if let Some(&id) = self.index.get(&key) {
+            return id;
+        }
+        let population = key.iter().map(|&child| self.node(child).population).sum();
+        let node = Node {
+            nw,
+            ne,
+            sw,
+            se,
+            level: self.level(nw) + 1,
+            population,
+        };
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.index.insert(key, id);
+        id
+    }
+
+    fn empty_node(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = self.empty[self.empty.len() - 1];
+            let bigger = self.join(e, e, e, e);
+            self.empty.push(bigger);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Half the side length of the root, i.e. the distance from the origin
+    /// to its edges.
+    fn root_radius(&self) -> i64 {
+        1 << (self.level(self.root) - 1)
+    }
+
+    /// Surrounds the root with empty space, doubling its side length.
+    fn expand(&mut self) -> Result<(), OutOfRangeError> {
+        let Node {
+            nw,
+            ne,
+            sw,
+            se,
+            level,
+            ..
+        } = *self.node(self.root);
+        if level >= Self::MAX_ROOT_LEVEL {
+            return Err(OutOfRangeError);
+        }
+        let e = self.empty_node(level - 1);
+        let nw = self.join(e, e, e, nw);
+        let ne = self.join(e, e, ne, e);
+        let sw = self.join(e, sw, e, e);
+        let se = self.join(se, e, e, e);
+        self.root = self.join(nw, ne, sw, se);
+        Ok(())
+    }
+
+    /// Whether every living cell is inside the middle half of the root.
+    fn is_padded(&self) -> bool {
+        let root = self.node(self.root);
+        let inner = [
+            self.node(root.nw).se,
+            self.node(root.ne).sw,
+            self.node(root.sw).ne,
+            self.node(root.se).nw,
+        ];
+        let inner_population: u64 = inner.iter().map(|&id| self.node(id).population).sum();
+        inner_population == root.population
+    }
+
+    fn contains(&self, row: i64, col: i64) -> bool {
+        let radius = self.root_radius();
+        (-radius..radius).contains(&row) && (-radius..radius).contains(&col)
+    }
+
+    fn cell_in(&self, mut id: NodeId, mut row: i64, mut col: i64) -> Cell {
+        // row and col are relative to the node's top left corner
+        loop {
+            let node = self.node(id);
+            if node.population == 0 {
+                return Cell::Dead;
+            }
+            if node.level == 0 {
+                return Cell::Alive;
+            }
+            let half = 1 << (node.level - 1);
+            id = match (row < half, col < half) {
+                (true, true) => node.nw,
+                (true, false) => node.ne,
+                (false, true) => node.sw,
+                (false, false) => node.se,
+            };
+            row %= half;
+            col %= half;
+        }
+    }
+
+    fn with_cell(&mut self, id: NodeId, row: i64, col: i64, cell: Cell) -> NodeId {
+        let node = *self.node(id);
+        if node.level == 0 {
+            return match cell {
+                Cell::Alive => ALIVE,
+                Cell::Dead => DEAD,
+            };
+        }
+        let half = 1 << (node.level - 1);
+        let (r, c) = (row % half, col % half);
+        let Node { nw, ne, sw, se, .. } = node;
+        match (row < half, col < half) {
+            (true, true) => {
+                let nw = self.with_cell(nw, r, c, cell);
+                self.join(nw, ne, sw, se)
+            }
+            (true, false) => {
+                let ne = self.with_cell(ne, r, c, cell);
+                self.join(nw, ne, sw, se)
+            }
+            (false, true) => {
+                let sw = self.with_cell(sw, r, c, cell);
+                self.join(nw, ne, sw, se)
+            }
+            (false, false) => {
+                let se = self.with_cell(se, r, c, cell);
+                self.join(nw, ne, sw, se)
+            }
+        }
+    }
+
+    pub fn cell_at(&self, row: i64, col: i64) -> &Cell {
+        if !self.contains(row, col) {
+            return &Cell::Dead;
+        }
+        let radius = self.root_radius();
+        match self.cell_in(self.root, row + radius, col + radius) {
+            Cell::Alive => &Cell::Alive,
+            Cell::Dead => &Cell::Dead,
+        }
+    }
+
+    /// Cells more than `2^61` from the origin are out of reach and stay
+    /// dead.
+    pub fn set_cell_at(&mut self, row: i64, col: i64, new_cell: Cell) {
+        while !self.contains(row, col) {
+            if self.expand().is_err() {
+                return;
+            }
+        }
+        let radius = self.root_radius();
+        self.root = self.with_cell(self.root, row + radius, col + radius, new_cell);
+    }
+
+    pub fn toggle_cell_at(&mut self, row: i64, col: i64) {
+        let toggled = self.cell_at(row, col).toggle();
+        self.set_cell_at(row, col, toggled);
+    }
+
+    pub fn clear(&mut self) {
+        self.root = self.empty_node(Self::MIN_ROOT_LEVEL);
+        self.generation = 0;
+        self.collect_garbage();
+    }
+
+    /// The middle `level - 1` node of a node, not advanced in time.
+    fn centre(&mut self, id: NodeId) -> NodeId {
+        let Node { nw, ne, sw, se, .. } = *self.node(id);
+        let (nw, ne, sw, se) = (
+            self.node(nw).se,
+            self.node(ne).sw,
+            self.node(sw).ne,
+            self.node(se).nw,
+        );
+        self.join(nw, ne, sw, se)
+    }
+
+    /// The node straddling the boundary between two horizontally adjacent
+    /// nodes.
+    fn centre_horizontal(&mut self, w: NodeId, e: NodeId) -> NodeId {
+        let (w, e) = (*self.node(w), *self.node(e));
+        self.join(w.ne, e.nw, w.se, e.sw)
+    }
+
+    /// The node straddling the boundary between two vertically adjacent
+    /// nodes.
+    fn centre_vertical(&mut self, n: NodeId, s: NodeId) -> NodeId {
+        let (n, s) = (*self.node(n), *self.node(s));
+        self.join(n.sw, n.se, s.nw, s.ne)
+    }
+
+    /// Advances the middle 2x2 of a level 2 node by a single generation.
+    fn base_successor(&mut self, id: NodeId) -> NodeId {
+        let mut grid = [[false; 4]; 4];
+        for (r, row) in grid.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                *cell = self.cell_in(id, r as i64, c as i64).is_alive();
+            }
+        }
+        let mut next = [DEAD; 4];
+        for (i, (r, c)) in [(1, 1), (1, 2), (2, 1), (2, 2)].into_iter().enumerate() {
+            let mut count = 0;
+            for dr in 0..3 {
+                for dc in 0..3 {
+                    if (dr, dc) != (1, 1) && grid[r + dr - 1][c + dc - 1] {
+                        count += 1;
+                    }
+                }
+            }
+            let cell = if grid[r][c] { Cell::Alive } else { Cell::Dead };
+            if self.rule.next_cell_state(cell, count).is_alive() {
+                next[i] = ALIVE;
+            }
+        }
+        self.join(next[0], next[1], next[2], next[3])
+    }
+
+    /// The middle `level - 1` node of `id` advanced `2^step_log`
+    /// generations, where `step_log <= level - 2`.
+    fn successor(&mut self, id: NodeId, step_log: u8) -> NodeId {
+        let node = *self.node(id);
+        debug_assert!(node.level >= 2 && step_log <= node.level - 2);
+        if node.population == 0 {
+            return self.empty_node(node.level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, step_log)) {
+            return result;
+        }
+
+        let result = if node.level == 2 {
+            self.base_successor(id)
+        } else {
+            let n01 = self.centre_horizontal(node.nw, node.ne);
+            let n10 = self.centre_vertical(node.nw, node.sw);
+            let n11 = self.centre(id);
+            let n12 = self.centre_vertical(node.ne, node.se);
+            let n21 = self.centre_horizontal(node.sw, node.se);
+            let nine = [node.nw, n01, node.ne, n10, n11, n12, node.sw, n21, node.se];
+
+            // At full speed both halves of the jump advance time; otherwise
+            // the first half does all of it and the second just recentres.
+            let full_speed = step_log == node.level - 2;
+            let first_step = if full_speed { step_log - 1 } else { step_log };
+            let mut r = [DEAD; 9];
+            for (slot, &sub) in r.iter_mut().zip(nine.iter()) {
+                *slot = self.successor(sub, first_step);
+            }
+
+            let quads = [
+                self.join(r[0], r[1], r[3], r[4]),
+                self.join(r[1], r[2], r[4], r[5]),
+                self.join(r[3], r[4], r[6], r[7]),
+                self.join(r[4], r[5], r[7], r[8]),
+            ];
+            let mut out = [DEAD; 4];
+            for (slot, &quad) in out.iter_mut().zip(quads.iter()) {
+                *slot = if full_speed {
+                    self.successor(quad, step_log - 1)
+                } else {
+                    self.centre(quad)
+                };
+            }
+            self.join(out[0], out[1], out[2], out[3])
+        };
+
+        self.results.insert((id, step_log), result);
+        result
+    }
+
+    /// Advances the pattern by exactly `2^k` generations. Fails, leaving
+    /// the pattern as it was, if `k` is above `MAX_STEP_LOG` or the pattern
+    /// would grow out of reach.
+    pub fn step_pow2(&mut self, k: u8) -> Result<(), OutOfRangeError> {
+        if k > Self::MAX_STEP_LOG {
+            return Err(OutOfRangeError);
+        }
+        while self.level(self.root) < k + 2 || !self.is_padded() {
+            self.expand()?;
+        }
+        self.expand()?;
+        self.root = self.successor(self.root, k);
+        self.generation = self.generation.saturating_add(1 << k);
+        if self
+            .cache_limit
+            .is_some_and(|limit| self.nodes.len() > limit)
+        {
+            self.collect_garbage();
+        }
+        Ok(())
+    }
+
+    /// Advances the pattern by `generations` generations, a power of two
+    /// at a time and at most `2^MAX_STEP_LOG` at once. If the pattern grows
+    /// out of reach it stops there, at the generation it got to.
+    pub fn advance(&mut self, generations: u64) -> Result<(), OutOfRangeError> {
+        for k in 0..u64::BITS as u8 {
+            if generations & (1 << k) != 0 {
+                let step_log = k.min(Self::MAX_STEP_LOG);
+                for _ in 0..1u64 << (k - step_log) {
+                    self.step_pow2(step_log)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn next_gen(&mut self) -> Result<(), OutOfRangeError> {
+        self.step_pow2(0)
+    }
+
+    /// Drops every cached result and every node not reachable from the
+    /// root.
+    pub fn collect_garbage(&mut self) {
+        let old_nodes = std::mem::take(&mut self.nodes);
+        self.nodes = old_nodes[..=ALIVE as usize].to_vec();
+        self.index.clear();
+        self.results.clear();
+        self.empty = vec![DEAD];
+
+        let mut remap = HashMap::new();
+        self.root = self.copy_node(&old_nodes, self.root, &mut remap);
+    }
+
+    fn copy_node(
+        &mut self,
+        old_nodes: &[Node],
+        id: NodeId,
+        remap: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        let node = old_nodes[id as usize];
+        if node.level == 0 {
+            return id;
+        }
+        if let Some(&new_id) = remap.get(&id) {
+            return new_id;
+        }
+        let nw = self.copy_node(old_nodes, node.nw, remap);
+        let ne = self.copy_node(old_nodes, node.ne, remap);
+        let sw = self.copy_node(old_nodes, node.sw, remap);
+        let se = self.copy_node(old_nodes, node.se, remap);
+        let new_id = self.join(nw, ne, sw, se);
+        remap.insert(id, new_id);
+        new_id
+    }
+
+    /// The smallest `Viewport` holding every living cell.
+    pub fn bounding_box(&self) -> Option<Viewport> {
+        let radius = self.root_radius();
+        let mut bounds = None;
+        self.extend_bounds(self.root, -radius, -radius, &mut bounds);
+        bounds.map(|(top, left, bottom, right)| {
+            Viewport::new(
+                top,
+                left,
+                (bottom - top + 1) as usize,
+                (right - left + 1) as usize,
+            )
+        })
+    }
+
+    fn extend_bounds(
+        &self,
+        id: NodeId,
+        top: i64,
+        left: i64,
+        bounds: &mut Option<(i64, i64, i64, i64)>,
+    ) {
+        let node = self.node(id);
+        if node.population == 0 {
+            return;
+        }
+        let size = 1i64 << node.level;
+        if let Some((t, l, b, r)) = *bounds {
+            // Nothing in here can grow the box any further
+            if top >= t && left >= l && top + size - 1 <= b && left + size - 1 <= r {
+                return;
+            }
+        }
+        if node.level == 0 {
+            *bounds = Some(match *bounds {
+                None => (top, left, top, left),
+                Some((t, l, b, r)) => (t.min(top), l.min(left), b.max(top), r.max(left)),
+            });
+            return;
+        }
+        let half = size / 2;
+        self.extend_bounds(node.nw, top, left, bounds);
+        self.extend_bounds(node.ne, top, left + half, bounds);
+        self.extend_bounds(node.sw, top + half, left, bounds);
+        self.extend_bounds(node.se, top + half, left + half, bounds);
+    }
+
+    fn collect_live(
+        &self,
+        id: NodeId,
+        top: i64,
+        left: i64,
+        viewport: &Viewport,
+        live: &mut Vec<(usize, usize)>,
+    ) {
+        let node = self.node(id);
+        let size = 1i64 << node.level;
+        let overlaps = top < viewport.top + viewport.rows as i64
+            && top + size > viewport.top
+            && left < viewport.left + viewport.cols as i64
+            && left + size > viewport.left;
+        if node.population == 0 || !overlaps {
+            return;
+        }
+        if node.level == 0 {
+            live.push((
+                (top - viewport.top) as usize,
+                (left - viewport.left) as usize,
+            ));
+            return;
+        }
+        let half = size / 2;
+        self.collect_live(node.nw, top, left, viewport, live);
+        self.collect_live(node.ne, top, left + half, viewport, live);
+        self.collect_live(node.sw, top + half, left, viewport, live);
+        self.collect_live(node.se, top + half, left + half, viewport, live);
+    }
+}
+
+impl Snapshot for HashLife {
+    fn memory_size(&self) -> usize {
+        let entry = mem::size_of::<([NodeId; 4], NodeId)>();
+        mem::size_of::<Self>()
+            + self.nodes.capacity() * mem::size_of::<Node>()
+            + (self.index.capacity() + self.results.capacity()) * entry
+            + self.empty.capacity() * mem::size_of::<NodeId>()
+    }
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LifeEngine for HashLife {
+    fn next_gen(&mut self) {
+        // A pattern about to grow out of reach has nowhere left to go, so
+        // it stays at the last generation that fit.
+        let _ = HashLife::next_gen(self);
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn cell(&self, row: i64, col: i64) -> Cell {
+        *self.cell_at(row, col)
+    }
+
+    fn set_cell(&mut self, row: i64, col: i64, cell: Cell) {
+        self.set_cell_at(row, col, cell);
+    }
+
+    fn clear(&mut self) {
+        HashLife::clear(self);
+    }
+
+    fn population(&self) -> u64 {
+        HashLife::population(self)
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        None
+    }
+
+    fn default_viewport(&self) -> Viewport {
+        self.bounding_box()
+            .unwrap_or_else(|| Viewport::new(0, 0, 1, 1))
+    }
+
+    fn live_cells_in(&self, viewport: &Viewport) -> Vec<(usize, usize)> {
+        let radius = self.root_radius();
+        let mut live = Vec::new();
+        self.collect_live(self.root, -radius, -radius, viewport, &mut live);
+        live
+    }
+}
+
+#[cfg(test)]
+mod hashlife_tests {
+    use super::*;
+    use crate::sparse::SparseUniverse;
+
+    fn place(cells: &[(i64, i64)]) -> (HashLife, SparseUniverse) {
+        let mut hashlife = HashLife::new();
+        let mut sparse = SparseUniverse::new();
+        for &(row, col) in cells {
+            hashlife.set_cell_at(row, col, Cell::Alive);
+            sparse.set_cell_at(row, col, Cell::Alive);
+        }
+        (hashlife, sparse)
+    }
+
+    fn glider() -> HashLife {
+        place(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]).0
+    }
+
+    fn r_pentomino() -> [(i64, i64); 5] {
+        [(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)]
+    }
+
+    fn assert_same_cells(hashlife: &HashLife, sparse: &SparseUniverse) {
+        assert_eq!(sparse.population() as u64, hashlife.population());
+        for (row, col) in sparse.live_cells() {
+            assert_eq!(&Cell::Alive, hashlife.cell_at(row, col));
+        }
+    }
+
+    #[test]
+    fn cells_can_be_set_and_read_far_from_the_origin() {
+        let mut life = HashLife::new();
+        life.set_cell_at(-5_000_000, 7_000_000, Cell::Alive);
+        assert_eq!(&Cell::Alive, life.cell_at(-5_000_000, 7_000_000));
+        assert_eq!(&Cell::Dead, life.cell_at(-5_000_000, 7_000_001));
+        assert_eq!(1, life.population());
+        life.toggle_cell_at(-5_000_000, 7_000_000);
+        assert_eq!(0, life.population());
+    }
+
+    #[test]
+    fn single_generations_match_the_sparse_engine() {
+        let (mut hashlife, mut sparse) = place(&r_pentomino());
+        for _ in 0..64 {
+            hashlife.next_gen().unwrap();
+            sparse.next_gen();
+            assert_same_cells(&hashlife, &sparse);
+        }
+        assert_eq!(64, hashlife.generation());
+    }
+
+    #[test]
+    fn power_of_two_steps_match_the_sparse_engine() {
+        let (mut hashlife, mut sparse) = place(&r_pentomino());
+        hashlife.step_pow2(7).unwrap();
+        for _ in 0..128 {
+            sparse.next_gen();
+        }
+        assert_eq!(128, hashlife.generation());
+        assert_same_cells(&hashlife, &sparse);
+    }
+
+    #[test]
+    fn the_r_pentomino_stabilises_at_116_cells() {
+        let (mut hashlife, _) = place(&r_pentomino());
+        hashlife.advance(1103).unwrap();
+        assert_eq!(1103, hashlife.generation());
+        assert_eq!(116, hashlife.population());
+    }
+
+    #[test]
+    fn a_glider_can_be_advanced_a_million_generations() {
+        let mut life = glider();
+        life.advance(1_000_000).unwrap();
+        assert_eq!(1_000_000, life.generation());
+        assert_eq!(5, life.population());
+        // A glider moves one cell diagonally every four generations
+        assert_eq!(
+            Some(Viewport::new(250_000, 250_000, 3, 3)),
+            life.bounding_box()
+        );
+    }
+
+    #[test]
+    fn other_life_like_rules_are_supported() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        let (hashlife, sparse) = place(&r_pentomino());
+        let mut hashlife = hashlife.with_rule(highlife).unwrap();
+        let mut sparse = sparse.with_rule(highlife).unwrap();
+        hashlife.advance(100).unwrap();
+        for _ in 0..100 {
+            sparse.next_gen();
+        }
+        assert_same_cells(&hashlife, &sparse);
+    }
+
+    #[test]
+    fn b0_rules_are_rejected() {
+        let b0: Rule = "B03/S23".parse().unwrap();
+        assert_eq!(
+            Some(UnboundedError::BirthOnZero(b0)),
+            HashLife::new().with_rule(b0).err()
+        );
+        let universe = Universe::new(&[Cell::Alive], 1).with_rule(b0);
+        assert_eq!(
+            Some(UnboundedError::BirthOnZero(b0)),
+            HashLife::from_universe(&universe).err()
+        );
+    }
+
+    #[test]
+    fn jumps_beyond_the_largest_step_are_an_error() {
+        let mut life = glider();
+        assert_eq!(
+            Err(OutOfRangeError),
+            life.step_pow2(HashLife::MAX_STEP_LOG + 1)
+        );
+        assert_eq!(Err(OutOfRangeError), life.step_pow2(u8::MAX));
+        assert_eq!(0, life.generation());
+        assert_eq!(5, life.population());
+    }
+
+    #[test]
+    fn still_lifes_can_be_advanced_to_the_last_generation() {
+        let (mut block, _) = place(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        block.advance(u64::MAX).unwrap();
+        assert_eq!(u64::MAX, block.generation());
+        assert_eq!(Some(Viewport::new(0, 0, 2, 2)), block.bounding_box());
+    }
+
+    #[test]
+    fn spaceships_stop_when_they_travel_out_of_reach() {
+        let mut life = glider();
+        assert_eq!(Err(OutOfRangeError), life.advance(u64::MAX));
+        assert_eq!(5, life.population());
+        assert!(life.generation() > 1 << HashLife::MAX_STEP_LOG);
+    }
+
+    #[test]
+    fn garbage_collection_keeps_the_pattern() {
+        let (hashlife, mut sparse) = place(&r_pentomino());
+        let mut hashlife = hashlife.with_cache_limit(1_000);
+        for _ in 0..300 {
+            hashlife.next_gen().unwrap();
+            sparse.next_gen();
+        }
+        assert_same_cells(&hashlife, &sparse);
+    }
+
+    #[test]
+    fn garbage_collection_frees_cached_nodes() {
+        let (mut hashlife, _) = place(&r_pentomino());
+        hashlife.advance(500).unwrap();
+        let before = hashlife.node_count();
+        hashlife.collect_garbage();
+        assert!(hashlife.node_count() < before);
+    }
+
+    #[test]
+    fn live_cells_in_a_viewport_are_relative_to_its_corner() {
+        let life = glider();
+        let mut live = life.live_cells_in(&Viewport::new(1, 1, 5, 5));
+        live.sort();
+        assert_eq!(vec![(0, 1), (1, 0), (1, 1)], live);
+    }
+
+    #[test]
+    fn clearing_empties_the_universe() {
+        let mut life = glider();
+        life.advance(10).unwrap();
+        life.clear();
+        assert_eq!(0, life.population());
+        assert_eq!(0, life.generation());
+    }
+}
@@ -0,0 +1,463 @@
+//! Golly's macrocell (`.mc`) format, which writes out a quadtree node by
+//! node so that huge but regular patterns stay small on disk. Reading and
+//! writing go straight to and from `HashLife` nodes without ever expanding
+//! the pattern into a grid of cells.
+//!
+//! After an `[M2]` header and optional `#` lines, each line defines the
+//! next node, numbered from 1. A line of `.`, `*` and `$` is an 8x8 leaf
+//! written like a tiny plaintext pattern with `$` ending each row; a line
+//! `k nw ne sw se` is a level `k` node built from earlier nodes, with 0
+//! standing for an empty quadrant. The last node is the root, centred on
+//! the origin.
+
+use super::{HashLife, NodeId, ALIVE, DEAD};
+use crate::game_of_life::Cell;
+use crate::rule::{ParseRuleError, Rule};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+const HEADER: &str = "[M2]";
+const LEAF_LEVEL: u8 = 3;
+const LEAF_SIZE: usize = 1 << LEAF_LEVEL;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MacrocellErrorKind {
+    MissingHeader,
+    InvalidRule(ParseRuleError),
+    UnsupportedRule(Rule),
+    InvalidGeneration(String),
+    UnexpectedChar(char),
+    LeafTooLarge,
+    InvalidNode(String),
+    UnsupportedLevel(u8),
+    UnknownNode(usize),
+    LevelMismatch { node: usize, expected: u8 },
+}
+
+/// A problem found while reading a macrocell pattern, with the 1-based line
+/// where it was found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MacrocellError {
+    pub line: usize,
+    pub kind: MacrocellErrorKind,
+}
+
+impl MacrocellError {
+    fn new(line: usize, kind: MacrocellErrorKind) -> Self {
+        Self { line, kind }
+    }
+}
+
+impl fmt::Display for MacrocellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            MacrocellErrorKind::MissingHeader => write!(f, "expected '{}'", HEADER),
+            MacrocellErrorKind::InvalidRule(e) => write!(f, "invalid rule: {}", e),
+            MacrocellErrorKind::UnsupportedRule(rule) => {
+                write!(f, "rule {} cannot be run on an unbounded plane", rule)
+            }
+            MacrocellErrorKind::InvalidGeneration(s) => {
+                write!(f, "'{}' is not a generation number", s)
+            }
+            MacrocellErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+            MacrocellErrorKind::LeafTooLarge => {
+                write!(f, "leaf is larger than {0}x{0} cells", LEAF_SIZE)
+            }
+            MacrocellErrorKind::InvalidNode(s) => {
+                write!(f, "'{}' is not a 'level nw ne sw se' node", s)
+            }
+            MacrocellErrorKind::UnsupportedLevel(level) => {
+                write!(f, "nodes at level {} are not supported", level)
+            }
+            MacrocellErrorKind::UnknownNode(node) => {
+                write!(f, "node {} has not been defined yet", node)
+            }
+            MacrocellErrorKind::LevelMismatch { node, expected } => {
+                write!(f, "node {} is not at level {}", node, expected)
+            }
+        }
+    }
+}
+
+impl Error for MacrocellError {}
+
+fn parse_leaf(
+    line: &str,
+    line_no: usize,
+) -> Result<[[bool; LEAF_SIZE]; LEAF_SIZE], MacrocellError> {
+    let too_large = || MacrocellError::new(line_no, MacrocellErrorKind::LeafTooLarge);
+    let mut bits = [[false; LEAF_SIZE]; LEAF_SIZE];
+    let (mut row, mut col) = (0, 0);
+    for c in line.chars() {
+        match c {
+            '$' => {
+                row += 1;
+                col = 0;
+                continue;
+            }
+            '.' => {}
+            '*' => {
+                *bits
+                    .get_mut(row)
+                    .ok_or_else(too_large)?
+                    .get_mut(col)
+                    .ok_or_else(too_large)? = true
+            }
+            c => {
+                return Err(MacrocellError::new(
+                    line_no,
+                    MacrocellErrorKind::UnexpectedChar(c),
+                ))
+            }
+        }
+        col += 1;
+    }
+    Ok(bits)
+}
+
+impl HashLife {
+    /// Reads a pattern in Golly's macrocell format. `#R` sets the rule and
+    /// `#G` the generation; other `#` lines are ignored.
+    pub fn from_macrocell(text: &str) -> Result<HashLife, MacrocellError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        match lines.next() {
+            Some((_, header)) if header.starts_with(HEADER) => {}
+            _ => return Err(MacrocellError::new(1, MacrocellErrorKind::MissingHeader)),
+        }
+
+        let mut life = HashLife::new();
+        // Node n of the file is defined[n - 1]
+        let mut defined: Vec<NodeId> = Vec::new();
+        for (line_no, line) in lines {
+            let error = |kind| MacrocellError::new(line_no, kind);
+            if let Some(rule) = line.strip_prefix("#R") {
+                let rule: Rule = rule
+                    .trim()
+                    .parse()
+                    .map_err(|e| error(MacrocellErrorKind::InvalidRule(e)))?;
+                life = life
+                    .with_rule(rule)
+                    .map_err(|_| error(MacrocellErrorKind::UnsupportedRule(rule)))?;
+            } else if let Some(generation) = line.strip_prefix("#G") {
+                let generation = generation.trim();
+                life.generation = generation.parse().map_err(|_| {
+                    error(MacrocellErrorKind::InvalidGeneration(
+                        generation.to_string(),
+                    ))
+                })?;
+            } else if line.starts_with('#') || line.is_empty() {
+                // Comments and Golly's other annotations
+            } else if line.starts_with(['.', '*', '$']) {
+                let bits = parse_leaf(line, line_no)?;
+                let leaf = life.build_leaf(&bits, 0, 0, LEAF_LEVEL);
+                defined.push(leaf);
+            } else {
+                let node = life.parse_node(line, &defined).map_err(error)?;
+                defined.push(node);
+            }
+        }
+
+        if let Some(&root) = defined.last() {
+            life.root = root;
+        }
+        Ok(life)
+    }
+
+    /// Writes the pattern in Golly's macrocell format. Each distinct node
+    /// is written once, so the output is as compact as the quadtree.
+    pub fn to_macrocell(&self) -> String {
+        let mut out = format!("{} (game_of_life)\n#R {}\n", HEADER, self.rule);
+        if self.generation > 0 {
+            out.push_str(&format!("#G {}\n", self.generation));
+        }
+        if self.population() > 0 {
+            let mut numbers = HashMap::new();
+            self.write_node(self.root, &mut numbers, &mut out);
+        }
+        out
+    }
+
+    fn build_leaf(
+        &mut self,
+        bits: &[[bool; LEAF_SIZE]; LEAF_SIZE],
+        top: usize,
+        left: usize,
+        level: u8,
+    ) -> NodeId {
+        if level == 0 {
+            return if bits[top][left] { ALIVE } else { DEAD };
+        }
+        let half = 1 << (level - 1);
+        let nw = self.build_leaf(bits, top, left, level - 1);
+        let ne = self.build_leaf(bits, top, left + half, level - 1);
+        let sw = self.build_leaf(bits, top + half, left, level - 1);
+        let se = self.build_leaf(bits, top + half, left + half, level - 1);
+        self.join(nw, ne, sw, se)
+    }
+
+    fn parse_node(&mut self, line: &str, defined: &[NodeId]) -> Result<NodeId, MacrocellErrorKind> {
+        let fields: Vec<usize> = line
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| MacrocellErrorKind::InvalidNode(line.to_string()))?;
+        let &[level, nw, ne, sw, se] = fields.as_slice() else {
+            return Err(MacrocellErrorKind::InvalidNode(line.to_string()));
+        };
+        // Lower levels only appear in Golly's multi-state files
+        if level <= LEAF_LEVEL as usize || level > Self::MAX_ROOT_LEVEL as usize {
+            return Err(MacrocellErrorKind::UnsupportedLevel(
+                level.min(u8::MAX as usize) as u8,
+            ));
+        }
+        let child_level = level as u8 - 1;
+        let mut child = |node: usize| match node {
+            0 => Ok(self.empty_node(child_level)),
+            n if n > defined.len() => Err(MacrocellErrorKind::UnknownNode(n)),
+            n if self.level(defined[n - 1]) != child_level => {
+                Err(MacrocellErrorKind::LevelMismatch {
+                    node: n,
+                    expected: child_level,
+                })
+            }
+            n => Ok(defined[n - 1]),
+        };
+        let (nw, ne, sw, se) = (child(nw)?, child(ne)?, child(sw)?, child(se)?);
+        Ok(self.join(nw, ne, sw, se))
+    }
+
+    /// Writes the non-empty node `id` after its children, returning its
+    /// number in the file.
+    fn write_node(
+        &self,
+        id: NodeId,
+        numbers: &mut HashMap<NodeId, usize>,
+        out: &mut String,
+    ) -> usize {
+        if let Some(&number) = numbers.get(&id) {
+            return number;
+        }
+        let node = *self.node(id);
+        if node.level == LEAF_LEVEL {
+            let rows: Vec<String> = (0..LEAF_SIZE as i64)
+                .map(|row| {
+                    let line: String = (0..LEAF_SIZE as i64)
+                        .map(|col| match self.cell_in(id, row, col) {
+                            Cell::Alive => '*',
+                            Cell::Dead => '.',
+                        })
+                        .collect();
+                    line.trim_end_matches('.').to_string()
+                })
+                .collect();
+            let used = rows
+                .iter()
+                .rposition(|row| !row.is_empty())
+                .map_or(0, |i| i + 1);
+            for row in &rows[..used] {
+                out.push_str(row);
+                out.push('$');
+            }
+        } else {
+            let mut child = |child: NodeId| {
+                if self.node(child).population == 0 {
+                    0
+                } else {
+                    self.write_node(child, numbers, out)
+                }
+            };
+            let (nw, ne, sw, se) = (
+                child(node.nw),
+                child(node.ne),
+                child(node.sw),
+                child(node.se),
+            );
+            out.push_str(&format!("{} {} {} {} {}", node.level, nw, ne, sw, se));
+        }
+        out.push('\n');
+        let number = numbers.len() + 1;
+        numbers.insert(id, number);
+        number
+    }
+}
+
+#[cfg(test)]
+mod macrocell_tests {
+    use super::*;
+    use crate::engine::LifeEngine;
+
+    const R_PENTOMINO: &str = include_str!("../../patterns/r-pentomino.mc");
+    const ACORN: &str = include_str!("../../patterns/acorn.mc");
+
+    fn live_cells(life: &HashLife) -> Vec<(i64, i64)> {
+        let Some(bounds) = life.bounding_box() else {
+            return Vec::new();
+        };
+        let mut live: Vec<(i64, i64)> = life
+            .live_cells_in(&bounds)
+            .into_iter()
+            .map(|(r, c)| (bounds.top + r as i64, bounds.left + c as i64))
+            .collect();
+        live.sort();
+        live
+    }
+
+    #[test]
+    fn nodes_place_their_leaves_around_the_origin() {
+        // Leaves in three quadrants, under two levels of nodes each
+        assert!(R_PENTOMINO.lines().any(|line| line.starts_with("6 ")));
+        let life = HashLife::from_macrocell(R_PENTOMINO).unwrap();
+        assert_eq!(
+            vec![(-1, 0), (-1, 1), (0, -1), (0, 0), (1, 0)],
+            live_cells(&life)
+        );
+        assert_eq!(Rule::conway(), life.rule());
+    }
+
+    #[test]
+    fn the_r_pentomino_file_reaches_116_cells_at_generation_1103() {
+        let mut life = HashLife::from_macrocell(R_PENTOMINO).unwrap();
+        life.advance(1103).unwrap();
+        assert_eq!(116, life.population());
+    }
+
+    #[test]
+    fn the_acorn_file_reaches_633_cells_at_generation_5206() {
+        let mut life = HashLife::from_macrocell(ACORN).unwrap();
+        assert_eq!(7, life.population());
+        life.advance(5206).unwrap();
+        assert_eq!(633, life.population());
+    }
+
+    #[test]
+    fn nodes_refer_to_earlier_lines_and_zero_is_empty() {
+        // Two blocks, in the north west and south east quadrants of a
+        // level 4 root
+        let text = "[M2]\n#R B3/S23\n**$**$\n4 1 0 0 1\n";
+        let mut life = HashLife::from_macrocell(text).unwrap();
+        assert_eq!(
+            vec![
+                (-8, -8),
+                (-8, -7),
+                (-7, -8),
+                (-7, -7),
+                (0, 0),
+                (0, 1),
+                (1, 0),
+                (1, 1)
+            ],
+            live_cells(&life)
+        );
+        life.advance(100).unwrap();
+        assert_eq!(8, life.population());
+    }
+
+    #[test]
+    fn the_generation_and_rule_are_read() {
+        let text = "[M2]\n#R B36/S23\n#G 42\n*$\n";
+        let life = HashLife::from_macrocell(text).unwrap();
+        assert_eq!(42, life.generation());
+        assert_eq!("B36/S23".parse::<Rule>().unwrap(), life.rule());
+    }
+
+    #[test]
+    fn round_trips_without_repeating_shared_nodes() {
+        let mut life = HashLife::from_macrocell(ACORN).unwrap();
+        life.advance(1000).unwrap();
+        let text = life.to_macrocell();
+        assert!(text.starts_with("[M2] (game_of_life)\n#R B3/S23\n#G 1000\n"));
+        let copy = HashLife::from_macrocell(&text).unwrap();
+        assert_eq!(life.generation(), copy.generation());
+        assert_eq!(live_cells(&life), live_cells(&copy));
+
+        let lines: Vec<&str> = text.lines().skip(3).collect();
+        let mut unique = lines.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(lines.len(), unique.len());
+    }
+
+    #[test]
+    fn a_huge_sparse_pattern_stays_small() {
+        let mut life = HashLife::new();
+        life.set_cell_at(0, 0, Cell::Alive);
+        life.set_cell_at(1 << 40, 1 << 40, Cell::Alive);
+        let text = life.to_macrocell();
+        assert!(text.lines().count() < 100);
+        let copy = HashLife::from_macrocell(&text).unwrap();
+        assert_eq!(&Cell::Alive, copy.cell_at(1 << 40, 1 << 40));
+        assert_eq!(2, copy.population());
+    }
+
+    #[test]
+    fn an_empty_pattern_round_trips() {
+        let text = HashLife::new().to_macrocell();
+        assert_eq!(0, HashLife::from_macrocell(&text).unwrap().population());
+    }
+
+    #[test]
+    fn reports_a_missing_header() {
+        let err = HashLife::from_macrocell("**$**$\n").unwrap_err();
+        assert_eq!(
+            MacrocellError::new(1, MacrocellErrorKind::MissingHeader),
+            err
+        );
+    }
+
+    #[test]
+    fn reports_problems_with_their_line() {
+        let cases = [
+            ("[M2]\n*x\n", 2, MacrocellErrorKind::UnexpectedChar('x')),
+            ("[M2]\n*********\n", 2, MacrocellErrorKind::LeafTooLarge),
+            (
+                "[M2]\n*\n4 1 0 0\n",
+                3,
+                MacrocellErrorKind::InvalidNode("4 1 0 0".into()),
+            ),
+            (
+                "[M2]\n*\n4 1 0 0 2\n",
+                3,
+                MacrocellErrorKind::UnknownNode(2),
+            ),
+            (
+                "[M2]\n*\n1 1 0 0 1\n",
+                3,
+                MacrocellErrorKind::UnsupportedLevel(1),
+            ),
+            (
+                "[M2]\n*\n5 1 0 0 0\n",
+                3,
+                MacrocellErrorKind::LevelMismatch {
+                    node: 1,
+                    expected: 4,
+                },
+            ),
+            (
+                "[M2]\n#G soon\n",
+                2,
+                MacrocellErrorKind::InvalidGeneration("soon".into()),
+            ),
+        ];
+        for (text, line, kind) in cases {
+            assert_eq!(
+                MacrocellError::new(line, kind),
+                HashLife::from_macrocell(text).unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn reports_rules_with_births_on_zero_neighbours() {
+        let err = HashLife::from_macrocell("[M2]\n#R B03/S23\n*\n").unwrap_err();
+        assert_eq!(2, err.line);
+        assert_eq!(
+            "line 2: rule B03/S23 cannot be run on an unbounded plane",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,36 @@
+//! Lets a universe be run with no window by `automata_core::headless`, for
+//! batch jobs on machines without a display.
+
+use crate::engine::LifeEngine;
+use crate::game_of_life::Universe;
+use automata_core::headless::Automaton;
+
+impl Automaton for Universe {
+    fn step(&mut self) {
+        LifeEngine::next_gen(self);
+    }
+
+    fn alive_cells(&self) -> Vec<bool> {
+        self.enumerate_cells()
+            .map(|(_, _, cell)| cell.is_alive())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod headless_tests {
+    use super::*;
+    use automata_core::headless::run_with_csv;
+
+    #[test]
+    fn writes_a_universe_s_stats_as_csv() {
+        let mut universe = Universe::from_rle("x = 3, y = 3\n3b$3o$3b!").unwrap();
+        let mut out = Vec::new();
+        run_with_csv(&mut universe, 2, &mut out).unwrap();
+        assert_eq!(
+            "generation,population,births,deaths\n0,3,0,0\n1,3,2,2\n2,3,2,2\n",
+            String::from_utf8(out).unwrap()
+        );
+        assert_eq!(2, universe.generation());
+    }
+}
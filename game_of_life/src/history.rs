@@ -0,0 +1,261 @@
+//! What `GameOfLifeController` remembers so that it can go back: the cells
+//! each edit changed, for undo and redo, and copies of the engine from
+//! past generations, for stepping a paused run backwards.
+
+use crate::game_of_life::Cell;
+use std::collections::VecDeque;
+
+/// How much memory past generations may take up unless told otherwise.
+pub const DEFAULT_HISTORY_MEMORY: usize = 64 << 20;
+
+/// Engines that can be copied into a `History`.
+pub trait Snapshot: Clone {
+    /// Roughly how many bytes a copy of the engine takes up.
+    fn memory_size(&self) -> usize;
+}
+
+/// The most recent states of an engine, oldest first, dropping the oldest
+/// once they take up more than a set amount of memory.
+#[derive(Debug)]
+pub struct History<S> {
+    states: VecDeque<S>,
+    memory: usize,
+    max_memory: usize,
+}
+
+impl<S: Snapshot> History<S> {
+    pub fn new(max_memory: usize) -> Self {
+        Self {
+            states: VecDeque::new(),
+            memory: 0,
+            max_memory,
+        }
+    }
+
+    pub fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+
+    /// The memory the kept states take up.
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Whether `state` fits in the history on its own, so that it is
+    /// worth copying to push.
+    pub fn fits(&self, state: &S) -> bool {
+        state.memory_size() <= self.max_memory
+    }
+
+    /// Keeps `state` as the most recent one. A state too big to fit on its
+    /// own leaves the history empty.
+    pub fn push(&mut self, state: S) {
+        self.memory += state.memory_size();
+        self.states.push_back(state);
+        while self.memory > self.max_memory {
+            match self.states.pop_front() {
+                Some(oldest) => self.memory -= oldest.memory_size(),
+                None => break,
+            }
+        }
+    }
+
+    /// Takes back the most recent state.
+    pub fn pop(&mut self) -> Option<S> {
+        let state = self.states.pop_back()?;
+        self.memory -= state.memory_size();
+        Some(state)
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.memory = 0;
+    }
+}
+
+/// A cell an edit changed, and what it was before and after.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CellChange {
+    pub row: i64,
+    pub col: i64,
+    pub before: Cell,
+    pub after: Cell,
+}
+
+/// Edits as lists of the cells they changed. Changes are recorded into an
+/// open edit until `commit` closes it, so that a whole drag is undone at
+/// once.
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    open: Vec<CellChange>,
+    undo: Vec<Vec<CellChange>>,
+    redo: Vec<Vec<CellChange>>,
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a change to the open edit. Cells that didn't change are left
+    /// out.
+    pub fn record(&mut self, row: i64, col: i64, before: Cell, after: Cell) {
+        if before != after {
+            self.open.push(CellChange {
+                row,
+                col,
+                before,
+                after,
+            });
+        }
+    }
+
+    /// Closes the open edit, making it the one to undo next. Redoing is no
+    /// longer possible after a new edit.
+    pub fn commit(&mut self) {
+        if !self.open.is_empty() {
+            self.undo.push(std::mem::take(&mut self.open));
+            self.redo.clear();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.open.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The cells to set to undo the last edit, in the order to set them.
+    pub fn undo(&mut self) -> Vec<(i64, i64, Cell)> {
+        self.commit();
+        let Some(edit) = self.undo.pop() else {
+            return Vec::new();
+        };
+        let cells = edit
+            .iter()
+            .rev()
+            .map(|change| (change.row, change.col, change.before))
+            .collect();
+        self.redo.push(edit);
+        cells
+    }
+
+    /// The cells to set to redo the last edit undone, in the order to set
+    /// them.
+    pub fn redo(&mut self) -> Vec<(i64, i64, Cell)> {
+        let Some(edit) = self.redo.pop() else {
+            return Vec::new();
+        };
+        let cells = edit
+            .iter()
+            .map(|change| (change.row, change.col, change.after))
+            .collect();
+        self.undo.push(edit);
+        cells
+    }
+
+    /// Forgets every edit, for when the cells they changed have moved on.
+    pub fn clear(&mut self) {
+        self.open.clear();
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct State(usize);
+
+    impl Snapshot for State {
+        fn memory_size(&self) -> usize {
+            self.0
+        }
+    }
+
+    #[test]
+    fn pops_states_newest_first() {
+        let mut history = History::new(100);
+        for size in [1, 2, 3] {
+            history.push(State(size));
+        }
+        assert_eq!((3, 6), (history.len(), history.memory()));
+        assert_eq!(Some(State(3)), history.pop());
+        assert_eq!(Some(State(2)), history.pop());
+        assert_eq!(1, history.memory());
+        assert_eq!(Some(State(1)), history.pop());
+        assert_eq!(None, history.pop());
+        assert_eq!(0, history.memory());
+    }
+
+    #[test]
+    fn drops_the_oldest_states_to_stay_under_the_cap() {
+        let mut history = History::new(10);
+        for size in [4, 4, 4] {
+            history.push(State(size));
+        }
+        assert_eq!((2, 8), (history.len(), history.memory()));
+        assert!(history.fits(&State(10)));
+        assert!(!history.fits(&State(11)));
+        history.push(State(11));
+        assert!(history.is_empty());
+        assert_eq!(0, history.memory());
+    }
+
+    #[test]
+    fn undoes_and_redoes_whole_edits() {
+        let mut edits = EditHistory::new();
+        edits.record(0, 0, Cell::Dead, Cell::Alive);
+        edits.record(0, 1, Cell::Alive, Cell::Alive);
+        edits.record(0, 0, Cell::Alive, Cell::Dead);
+        edits.commit();
+        edits.record(5, 5, Cell::Dead, Cell::Alive);
+        assert_eq!(vec![(5, 5, Cell::Dead)], edits.undo());
+        assert_eq!(vec![(0, 0, Cell::Alive), (0, 0, Cell::Dead)], edits.undo());
+        assert!(!edits.can_undo());
+        assert!(edits.undo().is_empty());
+        assert_eq!(vec![(0, 0, Cell::Alive), (0, 0, Cell::Dead)], edits.redo());
+        assert_eq!(vec![(5, 5, Cell::Alive)], edits.redo());
+        assert!(!edits.can_redo());
+    }
+
+    #[test]
+    fn new_edits_forget_what_was_undone() {
+        let mut edits = EditHistory::new();
+        edits.record(1, 1, Cell::Dead, Cell::Alive);
+        edits.commit();
+        edits.undo();
+        assert!(edits.can_redo());
+        edits.commit();
+        assert!(edits.can_redo());
+        edits.record(2, 2, Cell::Dead, Cell::Alive);
+        edits.commit();
+        assert!(!edits.can_redo());
+    }
+
+    #[test]
+    fn clearing_forgets_every_edit() {
+        let mut edits = EditHistory::new();
+        edits.record(1, 1, Cell::Dead, Cell::Alive);
+        edits.commit();
+        edits.record(2, 2, Cell::Dead, Cell::Alive);
+        edits.commit();
+        edits.undo();
+        edits.record(3, 3, Cell::Dead, Cell::Alive);
+        edits.clear();
+        assert!(!edits.can_undo());
+        assert!(!edits.can_redo());
+    }
+}
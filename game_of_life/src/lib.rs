@@ -0,0 +1,17 @@
+pub mod analysis;
+pub mod bit_universe;
+pub mod engine;
+pub mod game_of_life;
+pub mod game_of_life_controller;
+pub mod hashlife;
+pub mod headless;
+pub mod history;
+pub mod life_1_0x;
+pub mod pattern;
+pub mod plaintext;
+pub mod rle;
+pub mod rng;
+pub mod rule;
+pub mod soup;
+pub mod sparse;
+pub mod topology;
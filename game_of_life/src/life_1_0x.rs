@@ -0,0 +1,365 @@
+//! The Life 1.05 (blocks of cells at offsets) and Life 1.06 (one
+//! coordinate pair per living cell) formats. Both place cells relative to
+//! an origin rather than on a fixed grid, so reading one yields the
+//! smallest `Universe` holding every living cell.
+
+use crate::game_of_life::{Cell, Universe};
+use crate::rule::{ParseRuleError, Rule};
+use std::error::Error;
+use std::fmt;
+
+const LIFE_105_HEADER: &str = "#Life 1.05";
+const LIFE_106_HEADER: &str = "#Life 1.06";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LifeErrorKind {
+    MissingHeader(&'static str),
+    InvalidCoordinates(String),
+    InvalidRule(ParseRuleError),
+    CellsBeforeBlock,
+    UnexpectedChar(char),
+    EmptyPattern,
+    PatternTooLarge,
+}
+
+/// A problem found while reading a Life 1.05 or 1.06 pattern, with the
+/// 1-based line where it was found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LifeError {
+    pub line: usize,
+    pub kind: LifeErrorKind,
+}
+
+impl LifeError {
+    fn new(line: usize, kind: LifeErrorKind) -> Self {
+        Self { line, kind }
+    }
+}
+
+impl fmt::Display for LifeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            LifeErrorKind::MissingHeader(header) => write!(f, "expected '{}'", header),
+            LifeErrorKind::InvalidCoordinates(s) => write!(f, "'{}' is not an 'x y' pair", s),
+            LifeErrorKind::InvalidRule(e) => write!(f, "invalid rule: {}", e),
+            LifeErrorKind::CellsBeforeBlock => write!(f, "cells must follow a '#P x y' line"),
+            LifeErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+            LifeErrorKind::EmptyPattern => write!(f, "pattern has no living cells"),
+            LifeErrorKind::PatternTooLarge => write!(f, "pattern is too large to hold"),
+        }
+    }
+}
+
+impl Error for LifeError {}
+
+fn parse_coordinates(s: &str) -> Option<(i64, i64)> {
+    let mut parts = s.split_whitespace().map(str::parse::<i64>);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// Builds the smallest universe holding every `(x, y)` living cell, as long
+/// as it has no more than `Universe::MAX_CELLS`.
+fn universe_around(live: &[(i64, i64)], line: usize) -> Result<Universe, LifeError> {
+    let min_x = live.iter().map(|&(x, _)| x).min();
+    let min_y = live.iter().map(|&(_, y)| y).min();
+    let (Some(min_x), Some(min_y)) = (min_x, min_y) else {
+        return Err(LifeError::new(line, LifeErrorKind::EmptyPattern));
+    };
+    let too_large = || LifeError::new(line, LifeErrorKind::PatternTooLarge);
+    let span = |min: i64, max: i64| {
+        max.checked_sub(min)
+            .and_then(|n| n.checked_add(1))
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(too_large)
+    };
+    let width = span(min_x, live.iter().map(|&(x, _)| x).max().unwrap())?;
+    let height = span(min_y, live.iter().map(|&(_, y)| y).max().unwrap())?;
+    let size = Universe::grid_size(height, width).ok_or_else(too_large)?;
+    let mut cells = vec![Cell::Dead; size];
+    for &(x, y) in live {
+        cells[(y - min_y) as usize * width + (x - min_x) as usize] = Cell::Alive;
+    }
+    Ok(Universe::new(&cells, width))
+}
+
+fn live_cells(universe: &Universe) -> impl Iterator<Item = (usize, usize)> + '_ {
+    universe
+        .enumerate_cells()
+        .filter(|(_, _, cell)| cell.is_alive())
+        .map(|(r, c, _)| (r, c))
+}
+
+/// Life 1.05 writes rules survival first, as in `23/3`.
+fn survival_birth(rule: &Rule) -> String {
+    let digits = |f: &dyn Fn(u8) -> bool| -> String {
+        (0..=8u8)
+            .filter(|&n| f(n))
+            .map(|n| char::from(b'0' + n))
+            .collect()
+    };
+    format!(
+        "{}/{}",
+        digits(&|n| rule.survives(n)),
+        digits(&|n| rule.births(n))
+    )
+}
+
+impl Universe {
+    /// Reads a Life 1.06 pattern: a `#Life 1.06` line followed by one
+    /// `x y` pair per living cell.
+    pub fn from_life_106(text: &str) -> Result<Universe, LifeError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        match lines.next() {
+            Some((_, LIFE_106_HEADER)) => {}
+            _ => {
+                return Err(LifeError::new(
+                    1,
+                    LifeErrorKind::MissingHeader(LIFE_106_HEADER),
+                ))
+            }
+        }
+        let mut live = Vec::new();
+        let mut last_line = 1;
+        for (line_no, line) in lines {
+            last_line = line_no;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let xy = parse_coordinates(line).ok_or_else(|| {
+                LifeError::new(line_no, LifeErrorKind::InvalidCoordinates(line.to_string()))
+            })?;
+            live.push(xy);
+        }
+        universe_around(&live, last_line)
+    }
+
+    /// Writes the living cells as a Life 1.06 coordinate list, with the
+    /// top left corner of the universe at the origin.
+    pub fn to_life_106(&self) -> String {
+        let mut out = format!("{}\n", LIFE_106_HEADER);
+        for (r, c) in live_cells(self) {
+            out.push_str(&format!("{} {}\n", c, r));
+        }
+        out
+    }
+
+    /// Reads a Life 1.05 pattern: a `#Life 1.05` line, optional `#D`
+    /// descriptions and an `#N` or `#R s/b` rule, then blocks of `.`/`*`
+    /// rows each placed by a `#P x y` line.
+    pub fn from_life_105(text: &str) -> Result<Universe, LifeError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        match lines.next() {
+            Some((_, LIFE_105_HEADER)) => {}
+            _ => {
+                return Err(LifeError::new(
+                    1,
+                    LifeErrorKind::MissingHeader(LIFE_105_HEADER),
+                ))
+            }
+        }
+        let mut rule = Rule::conway();
+        let mut live = Vec::new();
+        let mut block: Option<(i64, i64)> = None;
+        let mut block_row = 0;
+        let mut last_line = 1;
+        for (line_no, line) in lines {
+            last_line = line_no;
+            if let Some(position) = line.strip_prefix("#P") {
+                let xy = parse_coordinates(position).ok_or_else(|| {
+                    let position = position.trim().to_string();
+                    LifeError::new(line_no, LifeErrorKind::InvalidCoordinates(position))
+                })?;
+                block = Some(xy);
+                block_row = 0;
+            } else if let Some(s_b) = line.strip_prefix("#R") {
+                rule = s_b
+                    .trim()
+                    .parse()
+                    .map_err(|e| LifeError::new(line_no, LifeErrorKind::InvalidRule(e)))?;
+            } else if line.starts_with("#N") {
+                rule = Rule::conway();
+            } else if line.starts_with('#') || line.is_empty() {
+                // #D descriptions
+            } else {
+                let (x, y) =
+                    block.ok_or(LifeError::new(line_no, LifeErrorKind::CellsBeforeBlock))?;
+                for (col, c) in line.chars().enumerate() {
+                    match c {
+                        '.' => {}
+                        '*' => {
+                            let xy = x
+                                .checked_add(col as i64)
+                                .zip(y.checked_add(block_row))
+                                .ok_or(LifeError::new(line_no, LifeErrorKind::PatternTooLarge))?;
+                            live.push(xy);
+                        }
+                        c => return Err(LifeError::new(line_no, LifeErrorKind::UnexpectedChar(c))),
+                    }
+                }
+                block_row += 1;
+            }
+        }
+        Ok(universe_around(&live, last_line)?.with_rule(rule))
+    }
+
+    /// Writes the universe as a single Life 1.05 block centred on the
+    /// origin.
+    pub fn to_life_105(&self) -> String {
+        let mut out = format!("{}\n", LIFE_105_HEADER);
+        if self.rule() == Rule::conway() {
+            out.push_str("#N\n");
+        } else {
+            out.push_str(&format!("#R {}\n", survival_birth(&self.rule())));
+        }
+        let (x, y) = (self.width() as i64 / 2, self.height() as i64 / 2);
+        out.push_str(&format!("#P {} {}\n", -x, -y));
+        for row in 0..self.height() {
+            let line: String = (0..self.width())
+                .map(|col| {
+                    if self.cell_at(row, col).is_alive() {
+                        '*'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            // Readers skip empty lines, so a blank row keeps one '.'
+            match line.trim_end_matches('.') {
+                "" => out.push('.'),
+                cells => out.push_str(cells),
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod life_1_0x_tests {
+    use super::*;
+
+    const GLIDER_106: &str = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+
+    const GLIDER_105: &str = "#Life 1.05\n\
+                              #D A glider\n\
+                              #N\n\
+                              #P -1 -1\n\
+                              .*\n\
+                              ..*\n\
+                              ***\n";
+
+    const GLIDER: &str = "Generation 0:\n3 3\n.*.\n..*\n***";
+
+    #[test]
+    fn can_read_a_life_106_glider() {
+        assert_eq!(
+            GLIDER,
+            Universe::from_life_106(GLIDER_106).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn can_read_a_life_105_glider() {
+        assert_eq!(
+            GLIDER,
+            Universe::from_life_105(GLIDER_105).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn life_105_blocks_are_placed_by_their_offsets() {
+        let text = "#Life 1.05\n#P 0 0\n*\n#P 3 -2\n**\n";
+        let universe = Universe::from_life_105(text).unwrap();
+        assert_eq!(
+            "Generation 0:\n3 5\n...**\n.....\n*....",
+            universe.to_string()
+        );
+    }
+
+    #[test]
+    fn life_105_reads_survival_birth_rules() {
+        let text = "#Life 1.05\n#R 23/36\n#P 0 0\n*\n";
+        let universe = Universe::from_life_105(text).unwrap();
+        assert_eq!("B36/S23".parse::<Rule>().unwrap(), universe.rule());
+    }
+
+    #[test]
+    fn reports_a_missing_header() {
+        let err = Universe::from_life_106("0 0\n").unwrap_err();
+        assert_eq!(
+            LifeError::new(1, LifeErrorKind::MissingHeader(LIFE_106_HEADER)),
+            err
+        );
+    }
+
+    #[test]
+    fn reports_bad_coordinates_with_their_line() {
+        let err = Universe::from_life_106("#Life 1.06\n0 0\n1 x\n").unwrap_err();
+        assert_eq!(3, err.line);
+        assert_eq!("line 3: '1 x' is not an 'x y' pair", err.to_string());
+    }
+
+    #[test]
+    fn reports_cells_outside_a_block() {
+        let err = Universe::from_life_105("#Life 1.05\n**\n").unwrap_err();
+        assert_eq!(LifeError::new(2, LifeErrorKind::CellsBeforeBlock), err);
+    }
+
+    #[test]
+    fn reports_patterns_too_large_to_hold() {
+        for text in [
+            "#Life 1.06\n-9223372036854775808 0\n9223372036854775807 0\n",
+            "#Life 1.06\n0 0\n100000000 100000000\n",
+        ] {
+            let err = Universe::from_life_106(text).unwrap_err();
+            assert_eq!(LifeError::new(3, LifeErrorKind::PatternTooLarge), err);
+        }
+        let err =
+            Universe::from_life_105("#Life 1.05\n#P 9223372036854775807 0\n.*\n").unwrap_err();
+        assert_eq!("line 3: pattern is too large to hold", err.to_string());
+    }
+
+    #[test]
+    fn reports_an_empty_pattern() {
+        let err = Universe::from_life_106("#Life 1.06\n").unwrap_err();
+        assert_eq!(LifeErrorKind::EmptyPattern, err.kind);
+    }
+
+    #[test]
+    fn round_trips_through_life_106() {
+        let glider = Universe::from_life_106(GLIDER_106).unwrap();
+        let copy = Universe::from_life_106(&glider.to_life_106()).unwrap();
+        assert_eq!(GLIDER, copy.to_string());
+    }
+
+    #[test]
+    fn round_trips_through_life_105() {
+        let highlife = Universe::from_life_105(GLIDER_105)
+            .unwrap()
+            .with_rule("B36/S23".parse().unwrap());
+        let text = highlife.to_life_105();
+        assert!(text.contains("#R 23/36\n"));
+        let copy = Universe::from_life_105(&text).unwrap();
+        assert_eq!(GLIDER, copy.to_string());
+        assert_eq!(highlife.rule(), copy.rule());
+    }
+
+    #[test]
+    fn blank_rows_survive_a_life_105_round_trip() {
+        let two_cells = Universe::from_plaintext("O\n.\nO\n").unwrap();
+        let text = two_cells.to_life_105();
+        assert!(text.ends_with("*\n.\n*\n"));
+        let copy = Universe::from_life_105(&text).unwrap();
+        assert_eq!("Generation 0:\n3 1\n*\n.\n*", copy.to_string());
+    }
+}
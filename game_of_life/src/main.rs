@@ -1,31 +1,30 @@
-extern crate piston_window;
-mod game_of_life;
-mod game_of_life_controller;
-mod rng;
+mod cli;
 
-use crate::game_of_life::{Cell, Universe};
-use crate::game_of_life_controller::{GameOfLifeController};
-use crate::rng::Rng;
+use game_of_life::analysis::CycleDetector;
+use game_of_life::bit_universe::BitUniverse;
+use game_of_life::engine::{LifeEngine, UnboundedError, Viewport};
+use game_of_life::game_of_life::Universe;
+use game_of_life::game_of_life_controller::{self, GameOfLifeController};
+use game_of_life::hashlife::HashLife;
+use game_of_life::history::{Snapshot, DEFAULT_HISTORY_MEMORY};
+use game_of_life::pattern::{load_macrocell, load_pattern, save_pattern};
+use game_of_life::rng::random_seed;
+use game_of_life::rule::Rule;
+use game_of_life::soup::{Soup, Symmetry};
+use game_of_life::sparse::SparseUniverse;
+use game_of_life::topology::Topology;
 
+#[cfg(feature = "window")]
 use automata_core::grid_view::GridView;
-use std::io;
-
-fn random_cells(num: usize) -> Vec<Cell> {
-    let seed = 96155;
-    let mut rng = Rng::new(seed);
-    let mut cells = Vec::new();
-    for _ in 0..num {
-        if let Some(n) = rng.next() {
-            let cell = match n % 2 {
-                0 => Cell::Alive,
-                1 => Cell::Dead,
-                _ => unreachable!("i % 2 can only be 0 or 1"),
-            };
-            cells.push(cell);
-        }
-    }
-    cells
-}
+use automata_core::headless::{self, HeadlessOptions, Outputs};
+use automata_core::model::Controller;
+use cli::Command;
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process;
+
+#[derive(Debug)]
 struct GameOfLifeApp {
     fps: u64,
     ups: u64,
@@ -33,6 +32,21 @@ struct GameOfLifeApp {
     cols: usize,
     live_color: [f32; 4],
     dead_color: [f32; 4],
+    rule: Option<Rule>,
+    topology: Topology,
+    unbounded: bool,
+    pattern: Option<PathBuf>,
+    offset: Option<(usize, usize)>,
+    /// Picked at random when `None`.
+    seed: Option<u64>,
+    density: f64,
+    symmetry: Symmetry,
+    soup_size: Option<(usize, usize)>,
+    image_dir: PathBuf,
+    scale: u32,
+    step: u64,
+    history_memory: usize,
+    detect_cycles: bool,
 }
 
 impl GameOfLifeApp {
@@ -44,68 +58,365 @@ impl GameOfLifeApp {
             cols: 90,
             live_color: [1.0, 1.0, 1.0, 1.0], // WHITE
             dead_color: [0.0, 0.0, 0.0, 1.0], //BLACK
+            rule: None,
+            topology: Topology::Bounded,
+            unbounded: false,
+            pattern: None,
+            offset: None,
+            seed: None,
+            density: 0.5,
+            symmetry: Symmetry::C1,
+            soup_size: None,
+            image_dir: PathBuf::from("."),
+            scale: 1,
+            step: 10,
+            history_memory: DEFAULT_HISTORY_MEMORY,
+            detect_cycles: false,
         }
     }
 
-    pub fn fps(&self, fps: u64) -> Self {
+    pub fn fps(self, fps: u64) -> Self {
         Self {
             fps,
-            ..*self
+            ..self
         }
     }
 
-    pub fn ups(&self, ups: u64) -> Self {
+    pub fn ups(self, ups: u64) -> Self {
         Self {
             ups,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn rows(&self, rows: usize) -> Self {
+    pub fn rows(self, rows: usize) -> Self {
         Self {
             rows,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn cols(&self, cols: usize) -> Self {
+    pub fn cols(self, cols: usize) -> Self {
         Self {
             cols,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn live_color(&self, live_color: [f32; 4]) -> Self {
+    pub fn live_color(self, live_color: [f32; 4]) -> Self {
         Self {
             live_color,
-            ..*self
+            ..self
         }        
     }
 
-    pub fn dead_color(&self, dead_color: [f32; 4]) -> Self {
+    pub fn dead_color(self, dead_color: [f32; 4]) -> Self {
         Self {
             dead_color,
-            ..*self
+            ..self
         }        
     }
+
+    /// Overrides the rule, including any rule given in the pattern file.
+    pub fn rule(self, rule: Rule) -> Self {
+        Self {
+            rule: Some(rule),
+            ..self
+        }
+    }
+
+    pub fn topology(self, topology: Topology) -> Self {
+        Self {
+            topology,
+            ..self
+        }
+    }
+
+    /// Runs on an unbounded plane, showing a `rows x cols` window onto it.
+    /// The plane has no edges, so the topology must be left bounded. A
+    /// pattern isn't placed in the window's grid, so it may be any size, and
+    /// the window shows its top left corner at the offset, or else its
+    /// middle.
+    pub fn unbounded(self, unbounded: bool) -> Self {
+        Self {
+            unbounded,
+            ..self
+        }
+    }
     
-    pub fn start(&self) {
-        let cells = random_cells(self.rows * self.cols);
-        let universe = Universe::new(&cells, self.cols);
-        let controller =
-            GameOfLifeController::new(universe, self.live_color, self.dead_color);
+    /// Starts from the pattern in `path`, in any format `load_pattern`
+    /// understands, instead of a random soup.
+    pub fn pattern<P: Into<PathBuf>>(self, path: P) -> Self {
+        Self {
+            pattern: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Places the pattern's top left corner at `(row, col)` rather than
+    /// centring it.
+    pub fn offset(self, row: usize, col: usize) -> Self {
+        Self {
+            offset: Some((row, col)),
+            ..self
+        }
+    }
+
+    /// Seeds the random soup used when there is no pattern, instead of
+    /// picking a seed at random.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// The fraction of living cells in the random soup.
+    pub fn density(self, density: f64) -> Self {
+        Self {
+            density,
+            ..self
+        }
+    }
+
+    /// Makes the random soup symmetric.
+    pub fn symmetry(self, symmetry: Symmetry) -> Self {
+        Self {
+            symmetry,
+            ..self
+        }
+    }
+
+    /// Fills only a `rows x cols` region in the middle of the grid with the
+    /// random soup.
+    pub fn soup_size(self, rows: usize, cols: usize) -> Self {
+        Self {
+            soup_size: Some((rows, cols)),
+            ..self
+        }
+    }
+
+    /// Where recordings made with F10, screenshots taken with F12 and the
+    /// clipboard saved with E go.
+    pub fn image_dir<P: Into<PathBuf>>(self, image_dir: P) -> Self {
+        Self {
+            image_dir: image_dir.into(),
+            ..self
+        }
+    }
+
+    /// Pixels per cell in recordings and screenshots.
+    pub fn scale(self, scale: u32) -> Self {
+        Self {
+            scale,
+            ..self
+        }
+    }
 
+    /// How many generations Return advances.
+    pub fn step(self, step: u64) -> Self {
+        Self {
+            step,
+            ..self
+        }
+    }
+
+    /// The most memory, in bytes, kept past generations may take up for
+    /// stepping back with Comma.
+    pub fn history_memory(self, history_memory: usize) -> Self {
+        Self {
+            history_memory,
+            ..self
+        }
+    }
+
+    /// Reports whether a headless run settled into a still life, an
+    /// oscillator or a spaceship. Every generation is hashed to find out.
+    pub fn detect_cycles(self, detect_cycles: bool) -> Self {
+        Self {
+            detect_cycles,
+            ..self
+        }
+    }
+
+    /// The soup settings, with `seed`.
+    fn soup(&self, seed: u64) -> Soup {
+        let soup = Soup::new(seed)
+            .with_density(self.density)
+            .with_symmetry(self.symmetry);
+        match self.soup_size {
+            Some((rows, cols)) => soup.with_size(rows, cols),
+            None => soup,
+        }
+    }
+
+    /// The universe to start from, and the soup it was filled with when
+    /// there is no pattern. A seed picked at random is reported so that the
+    /// run can be repeated.
+    fn initial_universe(&self) -> Result<(Universe, Option<Soup>), Box<dyn Error>> {
+        let (universe, soup) = match &self.pattern {
+            Some(path) => {
+                let in_file = |e: &dyn Error| format!("{}: {}", path.display(), e);
+                let pattern = load_pattern(path).map_err(|e| in_file(&e))?;
+                let universe = if self.unbounded {
+                    pattern
+                } else {
+                    pattern
+                        .place_in(self.rows, self.cols, self.offset)
+                        .map_err(|e| in_file(&e))?
+                };
+                (universe, None)
+            }
+            None => {
+                let seed = self.seed.unwrap_or_else(|| {
+                    let seed = random_seed();
+                    eprintln!("soup seed {} (run again with --seed {})", seed, seed);
+                    seed
+                });
+                let soup = self.soup(seed);
+                (soup.universe(self.rows, self.cols), Some(soup))
+            }
+        };
+        let rule = self.rule.unwrap_or(universe.rule());
+        Ok((universe.with_rule(rule).with_topology(self.topology), soup))
+    }
+
+    /// A controller for `engine`, making new soups like the starting one.
+    fn controller<E>(&self, engine: E, starting_soup: Option<Soup>) -> GameOfLifeController<E>
+    where
+        E: LifeEngine + Snapshot,
+    {
+        let controller = GameOfLifeController::new(engine, self.live_color, self.dead_color)
+            .with_history_memory(self.history_memory);
+        match starting_soup {
+            Some(soup) => controller.with_seed(soup.seed()).with_starting_soup(soup),
+            None => {
+                let soup = self.soup(self.seed.unwrap_or_else(random_seed));
+                controller.with_seed(soup.seed()).with_soup(soup)
+            }
+        }
+    }
+
+    pub fn start(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = &self.pattern {
+            let in_file = |e: &dyn Error| format!("{}: {}", path.display(), e);
+            if let Some(life) = load_macrocell(path).map_err(|e| in_file(&e))? {
+                return self.start_hashlife(life);
+            }
+        }
+        let (universe, soup) = self.initial_universe()?;
+        let title = format!("Conway's game of life ({})", universe.rule());
+
+        if self.unbounded {
+            let sparse = SparseUniverse::from_universe(&universe)?;
+            let viewport = self.window_onto(universe.default_viewport());
+            let controller = self.controller(sparse, soup).with_viewport(viewport);
+            self.run(&title, controller)
+        } else {
+            let bits = BitUniverse::from_universe(&universe);
+            let controller = self.controller(bits, soup);
+            self.run(&title, controller)
+        }
+    }
+
+    /// Runs a macrocell pattern on an unbounded plane without ever expanding
+    /// it into a grid. The window shows the pattern's top left corner at
+    /// `--offset`, or else its middle.
+    fn start_hashlife(&self, life: HashLife) -> Result<(), Box<dyn Error>> {
+        if self.topology != Topology::Bounded {
+            return Err(UnboundedError::Topology(self.topology).into());
+        }
+        let life = match self.rule {
+            Some(rule) => life.with_rule(rule)?,
+            None => life,
+        };
+        let title = format!("Conway's game of life ({})", life.rule());
+        let viewport = self.window_onto(life.default_viewport());
+        let controller = self.controller(life, None).with_viewport(viewport);
+        self.run(&title, controller)
+    }
+
+    /// The window onto a plane holding a pattern within `bounds`, with the
+    /// pattern's top left corner at `--offset`, or else its middle in the
+    /// middle.
+    fn window_onto(&self, bounds: Viewport) -> Viewport {
+        let window = Viewport::new(0, 0, self.rows, self.cols);
+        match self.offset {
+            Some((row, col)) => Viewport {
+                top: bounds.top - row as i64,
+                left: bounds.left - col as i64,
+                ..window
+            },
+            None => window.centred_on(
+                bounds.top + bounds.rows as i64 / 2,
+                bounds.left + bounds.cols as i64 / 2,
+            ),
+        }
+    }
+
+    /// Runs without a window, writing the final state and, if asked for,
+    /// per-generation stats, a recording and the cycle it settled into.
+    pub fn run_headless(&self, options: &HeadlessOptions) -> Result<(), Box<dyn Error>> {
+        let (mut universe, _) = self.initial_universe()?;
+        let in_file = |path: &Path, e: &dyn Error| format!("{}: {}", path.display(), e);
+        let mut outputs = Outputs::create(options, self.ups, self.scale)?;
+        let viewport = universe.default_viewport();
+        let mut detector = self.detect_cycles.then(CycleDetector::new);
+        let mut result = Ok(());
+        headless::run(&mut universe, options.generations, |universe, generation| {
+            if let Some(detector) = &mut detector {
+                detector.observe(universe);
+            }
+            if result.is_ok() {
+                result = outputs.write(generation, || {
+                    game_of_life_controller::view_model(
+                        universe,
+                        &viewport,
+                        self.live_color,
+                        self.dead_color,
+                    )
+                });
+            }
+        });
+        result?;
+        match detector.map(|detector| detector.cycle()) {
+            Some(Some(cycle)) => eprintln!("cycle: {}", cycle),
+            Some(None) => eprintln!(
+                "cycle: none yet, still evolving after {} generations",
+                options.generations
+            ),
+            None => {}
+        }
+        outputs.finish()?;
+
+        match &options.output {
+            Some(path) => save_pattern(&universe, path).map_err(|e| in_file(path, &e))?,
+            None => print!("{}", universe.to_rle()),
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "window")]
+    fn run<C: Controller>(&self, title: &str, controller: C) -> Result<(), Box<dyn Error>> {
         let mut view = GridView::new(
-            "Conway's game of life",
+            title,
             self.fps,
             self.ups,
             controller
-        );
-        view.game_loop();    
+        )
+        .with_output_dir(&self.image_dir)
+        .with_image_scale(self.scale)
+        .with_step_size(self.step);
+        view.game_loop()
+    }
+
+    #[cfg(not(feature = "window"))]
+    fn run<C: Controller>(&self, _title: &str, _controller: C) -> Result<(), Box<dyn Error>> {
+        Err("built without the window feature, so only headless runs are possible".into())
     }
 }
 
-fn main() -> io::Result<()> {
+fn main() {
     const ROWS: usize = 10 * 40;
     const COLS: usize = 18 * 40;
     const UPS: u64 = 5;
@@ -113,14 +424,32 @@ fn main() -> io::Result<()> {
     const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
     const MAGENTA: [f32; 4] =  [1.0, 0.0, 1.0, 1.0];
     
-    GameOfLifeApp::new()
+    let defaults = GameOfLifeApp::new()
         .rows(ROWS)
         .cols(COLS)
         .ups(UPS)
         .fps(FPS)
         .live_color(MAGENTA)
-        .dead_color(GREEN)
-        .start();
-    
-    Ok(())
+        .dead_color(GREEN);
+
+    match cli::parse_args(defaults, env::args().skip(1)) {
+        Ok(Command::Help(usage)) => print!("{}", usage),
+        Ok(Command::Run(app)) => {
+            if let Err(e) = app.start() {
+                eprintln!("game_of_life: {}", e);
+                process::exit(1);
+            }
+        }
+        Ok(Command::Headless(app, options)) => {
+            if let Err(e) = app.run_headless(&options) {
+                eprintln!("game_of_life: {}", e);
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("game_of_life: {}", e);
+            eprintln!("Try 'game_of_life --help' for more information.");
+            process::exit(2);
+        }
+    }
 }
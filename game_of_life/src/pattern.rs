@@ -0,0 +1,497 @@
+use crate::game_of_life::{Cell, Universe};
+use crate::hashlife::{HashLife, MacrocellError};
+use crate::life_1_0x::LifeError;
+use crate::plaintext::PlaintextError;
+use crate::rle::RleError;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The pattern file formats `parse_pattern` can recognise.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PatternFormat {
+    Rle,
+    Plaintext,
+    Life105,
+    Life106,
+    Macrocell,
+}
+
+impl PatternFormat {
+    /// The format a file name's extension names: `.rle`, `.cells`, `.lif`
+    /// or `.life` (written as Life 1.06) or `.mc`.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<PatternFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rle" => Some(Self::Rle),
+            "cells" => Some(Self::Plaintext),
+            "lif" | "life" => Some(Self::Life106),
+            "mc" => Some(Self::Macrocell),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(io::Error),
+    UnknownFormat,
+    Rle(RleError),
+    Plaintext(PlaintextError),
+    Life(LifeError),
+    Macrocell(MacrocellError),
+    EmptyPattern,
+    /// The rows and columns a pattern's cells are spread over, when that is
+    /// too many cells to hold in a grid.
+    TooLarge(usize, usize),
+    UnsupportedRule(PatternFormat, Rule),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::UnknownFormat => write!(f, "not a recognised pattern format"),
+            Self::Rle(e) => write!(f, "invalid RLE: {}", e),
+            Self::Plaintext(e) => write!(f, "invalid plaintext pattern: {}", e),
+            Self::Life(e) => write!(f, "invalid Life pattern: {}", e),
+            Self::Macrocell(e) => write!(f, "invalid macrocell pattern: {}", e),
+            Self::EmptyPattern => write!(f, "pattern has no living cells"),
+            Self::TooLarge(rows, cols) => write!(
+                f,
+                "pattern spans {} by {} cells, too many to hold in a grid",
+                rows, cols
+            ),
+            Self::UnsupportedRule(format, rule) => {
+                write!(f, "rule {} cannot be written in {:?} format", rule, format)
+            }
+        }
+    }
+}
+
+impl Error for PatternError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::UnknownFormat
+            | Self::EmptyPattern
+            | Self::TooLarge(..)
+            | Self::UnsupportedRule(..) => None,
+            Self::Rle(e) => Some(e),
+            Self::Plaintext(e) => Some(e),
+            Self::Life(e) => Some(e),
+            Self::Macrocell(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for PatternError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A pattern too big for the grid it was placed in, or placed too close to
+/// its edge. Sizes are rows by columns.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlacementError {
+    pub pattern: (usize, usize),
+    pub grid: (usize, usize),
+    pub offset: Option<(usize, usize)>,
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} pattern does not fit in a {}x{} grid",
+            self.pattern.0, self.pattern.1, self.grid.0, self.grid.1
+        )?;
+        if let Some((row, col)) = self.offset {
+            write!(f, " at row {}, column {}", row, col)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for PlacementError {}
+
+impl Universe {
+    /// Copies this pattern into an empty `rows x cols` universe with its top
+    /// left corner at `offset`, or centred when there is no offset. The copy
+    /// keeps the pattern's rule and topology.
+    pub fn place_in(
+        &self,
+        rows: usize,
+        cols: usize,
+        offset: Option<(usize, usize)>,
+    ) -> Result<Universe, PlacementError> {
+        let error = || PlacementError {
+            pattern: (self.height(), self.width()),
+            grid: (rows, cols),
+            offset,
+        };
+        let (top, left) = match offset {
+            Some(offset) => offset,
+            None => (
+                rows.checked_sub(self.height()).ok_or_else(error)? / 2,
+                cols.checked_sub(self.width()).ok_or_else(error)? / 2,
+            ),
+        };
+        let bottom = top.checked_add(self.height()).ok_or_else(error)?;
+        let right = left.checked_add(self.width()).ok_or_else(error)?;
+        if bottom > rows || right > cols {
+            return Err(error());
+        }
+
+        let mut cells = vec![Cell::Dead; rows * cols];
+        for (r, c, cell) in self.enumerate_cells() {
+            cells[(top + r) * cols + left + c] = *cell;
+        }
+        Ok(Universe::new(&cells, cols)
+            .with_rule(self.rule())
+            .with_topology(self.topology()))
+    }
+
+    /// Builds a pattern of the same rule and topology whose cell at `(row,
+    /// col)` is this pattern's cell at `source(row, col)`.
+    fn remapped<F>(&self, rows: usize, cols: usize, source: F) -> Universe
+    where
+        F: Fn(usize, usize) -> (usize, usize),
+    {
+        let mut cells = Vec::with_capacity(rows * cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                let (r, c) = source(row, col);
+                cells.push(*self.cell_at(r, c));
+            }
+        }
+        Universe::new(&cells, cols)
+            .with_rule(self.rule())
+            .with_topology(self.topology())
+    }
+
+    /// The pattern turned a quarter turn clockwise.
+    pub fn rotated_clockwise(&self) -> Universe {
+        let height = self.height();
+        self.remapped(self.width(), height, |row, col| (height - 1 - col, row))
+    }
+
+    /// The pattern mirrored left to right.
+    pub fn flipped_horizontally(&self) -> Universe {
+        let width = self.width();
+        self.remapped(self.height(), width, |row, col| (row, width - 1 - col))
+    }
+
+    /// The pattern mirrored top to bottom.
+    pub fn flipped_vertically(&self) -> Universe {
+        let height = self.height();
+        self.remapped(height, self.width(), |row, col| (height - 1 - row, col))
+    }
+}
+
+/// Works out which format `text` is written in from its contents alone.
+pub fn sniff_format(text: &str) -> Option<PatternFormat> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let first = lines.next()?;
+    if first.starts_with("#Life 1.06") {
+        return Some(PatternFormat::Life106);
+    }
+    if first.starts_with("#Life 1.05") {
+        return Some(PatternFormat::Life105);
+    }
+    if first.starts_with("[M2]") {
+        return Some(PatternFormat::Macrocell);
+    }
+    if first.starts_with('!') {
+        return Some(PatternFormat::Plaintext);
+    }
+
+    // RLE starts with optional '#' comments and then its 'x = ' header
+    let first_body = std::iter::once(first)
+        .chain(lines)
+        .find(|line| !line.starts_with('#'))?;
+    let is_rle_header = first_body
+        .split_once('=')
+        .is_some_and(|(key, _)| key.trim() == "x");
+    if is_rle_header {
+        Some(PatternFormat::Rle)
+    } else if first == first_body && first_body.chars().all(|c| ".O*".contains(c)) {
+        Some(PatternFormat::Plaintext)
+    } else {
+        None
+    }
+}
+
+/// Reads a pattern in any supported format, recognised from its contents.
+/// Macrocell patterns are expanded into a grid, so fail if their cells are
+/// too far apart; use `HashLife::from_macrocell` to keep them as a quadtree.
+pub fn parse_pattern(text: &str) -> Result<Universe, PatternError> {
+    match sniff_format(text).ok_or(PatternError::UnknownFormat)? {
+        PatternFormat::Rle => Universe::from_rle(text).map_err(PatternError::Rle),
+        PatternFormat::Plaintext => Universe::from_plaintext(text).map_err(PatternError::Plaintext),
+        PatternFormat::Life105 => Universe::from_life_105(text).map_err(PatternError::Life),
+        PatternFormat::Life106 => Universe::from_life_106(text).map_err(PatternError::Life),
+        PatternFormat::Macrocell => HashLife::from_macrocell(text)
+            .map_err(PatternError::Macrocell)?
+            .to_universe(),
+    }
+}
+
+/// Reads a pattern file in any supported format, recognised from its
+/// contents rather than its extension.
+pub fn load_pattern<P: AsRef<Path>>(path: P) -> Result<Universe, PatternError> {
+    let text = fs::read_to_string(path)?;
+    parse_pattern(&text)
+}
+
+/// Reads a macrocell pattern file straight into `HashLife`, or gives `None`
+/// for files in other formats. Unlike `load_pattern` this never expands the
+/// quadtree into a grid, so it copes with patterns far too big for one.
+pub fn load_macrocell<P: AsRef<Path>>(path: P) -> Result<Option<HashLife>, PatternError> {
+    let text = fs::read_to_string(path)?;
+    if sniff_format(&text) != Some(PatternFormat::Macrocell) {
+        return Ok(None);
+    }
+    HashLife::from_macrocell(&text)
+        .map(Some)
+        .map_err(PatternError::Macrocell)
+}
+
+/// Writes `universe` in `format`. Macrocell output goes through `HashLife`,
+/// which cannot hold rules with births on zero neighbours.
+pub fn format_pattern(universe: &Universe, format: PatternFormat) -> Result<String, PatternError> {
+    Ok(match format {
+        PatternFormat::Rle => universe.to_rle(),
+        PatternFormat::Plaintext => universe.to_plaintext(None),
+        PatternFormat::Life105 => universe.to_life_105(),
+        PatternFormat::Life106 => universe.to_life_106(),
+        PatternFormat::Macrocell => {
+            // Macrocell patterns have no edges, so only the cells and rule
+            // are kept
+            let plane = universe.clone().with_topology(Topology::Bounded);
+            HashLife::from_universe(&plane)
+                .map_err(|_| PatternError::UnsupportedRule(format, universe.rule()))?
+                .to_macrocell()
+        }
+    })
+}
+
+/// Saves `universe` in the format `path`'s extension names, or as RLE when
+/// it names none.
+pub fn save_pattern<P: AsRef<Path>>(universe: &Universe, path: P) -> Result<(), PatternError> {
+    let format = PatternFormat::from_extension(&path).unwrap_or(PatternFormat::Rle);
+    fs::write(path, format_pattern(universe, format)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod pattern_tests {
+    use super::*;
+
+    const GLIDER: &str = "Generation 0:\n3 3\n.*.\n..*\n***";
+    const GLIDER_RLE: &str = "x = 3, y = 3\nbo$2bo$3o!";
+
+    #[test]
+    fn rotates_and_flips_patterns() {
+        let pattern = Universe::from_rle("x = 3, y = 2, rule = B36/S23\n3o$o!").unwrap();
+        let body = |p: Universe| p.to_string().splitn(3, '\n').nth(2).unwrap().to_string();
+        assert_eq!("**\n.*\n.*", body(pattern.rotated_clockwise()));
+        assert_eq!("***\n..*", body(pattern.flipped_horizontally()));
+        assert_eq!("*..\n***", body(pattern.flipped_vertically()));
+        let half_turn = pattern.rotated_clockwise().rotated_clockwise();
+        assert_eq!(
+            pattern
+                .flipped_horizontally()
+                .flipped_vertically()
+                .to_string(),
+            half_turn.to_string()
+        );
+        let turned = half_turn.rotated_clockwise().rotated_clockwise();
+        assert_eq!(pattern.to_string(), turned.to_string());
+        assert_eq!(pattern.rule(), turned.rule());
+    }
+
+    #[test]
+    fn recognises_each_format() {
+        let cases = [
+            ("#N Glider\nx = 3, y = 3\nbo$2bo$3o!", PatternFormat::Rle),
+            ("x = 3, y = 3\nbo$2bo$3o!", PatternFormat::Rle),
+            ("!Name: Glider\n.O.\n..O\nOOO\n", PatternFormat::Plaintext),
+            (".O.\n..O\nOOO\n", PatternFormat::Plaintext),
+            (
+                "#Life 1.05\n#P -1 -1\n.*\n..*\n***\n",
+                PatternFormat::Life105,
+            ),
+            (
+                "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n",
+                PatternFormat::Life106,
+            ),
+            (
+                "[M2] (golly 4.2)\n#R B3/S23\n.*$..*$***$\n",
+                PatternFormat::Macrocell,
+            ),
+        ];
+        for (text, format) in cases {
+            assert_eq!(Some(format), sniff_format(text), "{}", text);
+            assert_eq!(GLIDER, parse_pattern(text).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn unrecognised_text_is_an_error() {
+        assert_eq!(None, sniff_format("hello world"));
+        assert!(matches!(
+            parse_pattern("hello world"),
+            Err(PatternError::UnknownFormat)
+        ));
+        assert!(matches!(
+            parse_pattern(""),
+            Err(PatternError::UnknownFormat)
+        ));
+    }
+
+    #[test]
+    fn format_errors_are_passed_through() {
+        let err = parse_pattern("x = 1, y = 1\nz!").unwrap_err();
+        assert_eq!(
+            "invalid RLE: line 2, column 1: unexpected character 'z'",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn an_empty_macrocell_is_an_error() {
+        assert!(matches!(
+            parse_pattern("[M2]\n#R B3/S23\n"),
+            Err(PatternError::EmptyPattern)
+        ));
+    }
+
+    #[test]
+    fn macrocells_too_spread_out_for_a_grid_are_an_error() {
+        let mut life = HashLife::new();
+        life.set_cell_at(0, 0, Cell::Alive);
+        life.set_cell_at(1 << 40, 1 << 40, Cell::Alive);
+        let err = parse_pattern(&life.to_macrocell()).unwrap_err();
+        let span = (1 << 40) + 1;
+        assert!(matches!(err, PatternError::TooLarge(rows, cols) if rows == span && cols == span));
+    }
+
+    #[test]
+    fn patterns_are_centred_by_default() {
+        let glider = parse_pattern(GLIDER_RLE).unwrap();
+        let placed = glider.place_in(5, 6, None).unwrap();
+        assert_eq!(
+            "Generation 0:\n5 6\n......\n..*...\n...*..\n.***..\n......",
+            placed.to_string()
+        );
+    }
+
+    #[test]
+    fn patterns_can_be_placed_at_an_offset() {
+        let glider = parse_pattern(GLIDER_RLE).unwrap();
+        let placed = glider.place_in(4, 4, Some((1, 0))).unwrap();
+        assert_eq!(
+            "Generation 0:\n4 4\n....\n.*..\n..*.\n***.",
+            placed.to_string()
+        );
+    }
+
+    #[test]
+    fn placement_keeps_the_pattern_rule() {
+        let text = "x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!";
+        let placed = parse_pattern(text).unwrap().place_in(10, 10, None).unwrap();
+        assert_eq!(
+            "B36/S23".parse::<crate::rule::Rule>().unwrap(),
+            placed.rule()
+        );
+    }
+
+    #[test]
+    fn patterns_that_do_not_fit_are_an_error() {
+        let glider = parse_pattern(GLIDER_RLE).unwrap();
+        let err = glider.place_in(2, 10, None).unwrap_err();
+        assert_eq!("a 3x3 pattern does not fit in a 2x10 grid", err.to_string());
+        let err = glider.place_in(10, 10, Some((2, 8))).unwrap_err();
+        assert_eq!(
+            "a 3x3 pattern does not fit in a 10x10 grid at row 2, column 8",
+            err.to_string()
+        );
+        let offset = Some((usize::MAX, 0));
+        let err = glider.place_in(10, 10, offset).unwrap_err();
+        assert_eq!(offset, err.offset);
+    }
+
+    #[test]
+    fn loads_patterns_from_files() {
+        let path = std::env::temp_dir().join(format!("glider-{}.lif", std::process::id()));
+        fs::write(&path, "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        let loaded = load_pattern(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(GLIDER, loaded.unwrap().to_string());
+    }
+
+    #[test]
+    fn loads_macrocell_files_without_expanding_them() {
+        let mut far_apart = HashLife::new();
+        far_apart.set_cell_at(0, 0, Cell::Alive);
+        far_apart.set_cell_at(1 << 40, 1 << 40, Cell::Alive);
+        let dir = std::env::temp_dir();
+        let mc = dir.join(format!("far-apart-{}.mc", std::process::id()));
+        fs::write(&mc, far_apart.to_macrocell()).unwrap();
+        let loaded = load_macrocell(&mc);
+        fs::remove_file(&mc).unwrap();
+        let loaded = loaded.unwrap().unwrap();
+        assert_eq!(2, loaded.population());
+        assert_eq!(&Cell::Alive, loaded.cell_at(1 << 40, 1 << 40));
+
+        let rle = dir.join(format!("glider-{}.rle", std::process::id()));
+        fs::write(&rle, GLIDER_RLE).unwrap();
+        let loaded = load_macrocell(&rle);
+        fs::remove_file(&rle).unwrap();
+        assert!(loaded.unwrap().is_none());
+    }
+
+    #[test]
+    fn saves_in_the_format_the_extension_names() {
+        let glider = parse_pattern(GLIDER_RLE).unwrap();
+        let dir = std::env::temp_dir();
+        for (extension, format) in [
+            ("rle", PatternFormat::Rle),
+            ("cells", PatternFormat::Plaintext),
+            ("lif", PatternFormat::Life106),
+            ("mc", PatternFormat::Macrocell),
+            ("txt", PatternFormat::Rle),
+        ] {
+            let path = dir.join(format!("glider-{}.{}", std::process::id(), extension));
+            save_pattern(&glider, &path).unwrap();
+            let text = fs::read_to_string(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(Some(format), sniff_format(&text), "{}", extension);
+            assert_eq!(GLIDER, parse_pattern(&text).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn b0_rules_cannot_be_saved_as_macrocells() {
+        let universe = parse_pattern(GLIDER_RLE)
+            .unwrap()
+            .with_rule("B03/S23".parse().unwrap());
+        let err = format_pattern(&universe, PatternFormat::Macrocell).unwrap_err();
+        assert_eq!(
+            "rule B03/S23 cannot be written in Macrocell format",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let err = load_pattern("/definitely/not/a/pattern.rle").unwrap_err();
+        assert!(matches!(err, PatternError::Io(_)));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::game_of_life::{Cell, Universe};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PlaintextErrorKind {
+    EmptyPattern,
+    UnexpectedChar(char),
+}
+
+/// A problem found while reading a plaintext pattern, with the 1-based line
+/// and column where it was found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlaintextError {
+    pub line: usize,
+    pub col: usize,
+    pub kind: PlaintextErrorKind,
+}
+
+impl fmt::Display for PlaintextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.col)?;
+        match self.kind {
+            PlaintextErrorKind::EmptyPattern => write!(f, "pattern has no cells"),
+            PlaintextErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+        }
+    }
+}
+
+impl Error for PlaintextError {}
+
+impl Universe {
+    /// Reads a pattern in the plaintext (`.cells`) format: `!` comment
+    /// lines, then one line per row with `.` for dead and `O` for living
+    /// cells. Short rows are padded with dead cells.
+    pub fn from_plaintext(text: &str) -> Result<Universe, PlaintextError> {
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        let mut last_line = 0;
+        for (i, line) in text.lines().enumerate() {
+            last_line = i + 1;
+            if line.starts_with('!') {
+                continue;
+            }
+            let mut row = Vec::new();
+            for (j, c) in line.trim_end().chars().enumerate() {
+                row.push(match c {
+                    '.' => Cell::Dead,
+                    // Some collections use '*' for living cells
+                    'O' | '*' => Cell::Alive,
+                    c => {
+                        return Err(PlaintextError {
+                            line: i + 1,
+                            col: j + 1,
+                            kind: PlaintextErrorKind::UnexpectedChar(c),
+                        })
+                    }
+                });
+            }
+            rows.push(row);
+        }
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        if width == 0 {
+            return Err(PlaintextError {
+                line: last_line + 1,
+                col: 1,
+                kind: PlaintextErrorKind::EmptyPattern,
+            });
+        }
+        let mut cells = Vec::with_capacity(width * rows.len());
+        for mut row in rows {
+            row.resize(width, Cell::Dead);
+            cells.extend(row);
+        }
+        Ok(Universe::new(&cells, width))
+    }
+
+    /// Writes the universe in the plaintext (`.cells`) format, optionally
+    /// under a `!Name:` line.
+    pub fn to_plaintext(&self, name: Option<&str>) -> String {
+        let mut out = String::new();
+        if let Some(name) = name {
+            out.push_str(&format!("!Name: {}\n", name));
+        }
+        for row in 0..self.height() {
+            for col in 0..self.width() {
+                out.push(if self.cell_at(row, col).is_alive() {
+                    'O'
+                } else {
+                    '.'
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod plaintext_tests {
+    use super::*;
+
+    const GLIDER: &str = "!Name: Glider\n!\n.O.\n..O\nOOO\n";
+
+    #[test]
+    fn can_read_a_glider() {
+        let glider = Universe::from_plaintext(GLIDER).unwrap();
+        assert_eq!("Generation 0:\n3 3\n.*.\n..*\n***", glider.to_string());
+    }
+
+    #[test]
+    fn short_and_blank_rows_are_padded_with_dead_cells() {
+        let universe = Universe::from_plaintext("O\n\n..O\n").unwrap();
+        assert_eq!("Generation 0:\n3 3\n*..\n...\n..*", universe.to_string());
+    }
+
+    #[test]
+    fn accepts_stars_for_living_cells() {
+        let universe = Universe::from_plaintext("*.*").unwrap();
+        assert_eq!("Generation 0:\n1 3\n*.*", universe.to_string());
+    }
+
+    #[test]
+    fn reports_unexpected_characters_with_line_and_column() {
+        let err = Universe::from_plaintext("!Name: x\n.O.\n.X.").unwrap_err();
+        assert_eq!(
+            PlaintextError {
+                line: 3,
+                col: 2,
+                kind: PlaintextErrorKind::UnexpectedChar('X')
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn reports_a_pattern_without_cells() {
+        let err = Universe::from_plaintext("!Name: nothing\n\n").unwrap_err();
+        assert_eq!(PlaintextErrorKind::EmptyPattern, err.kind);
+    }
+
+    #[test]
+    fn round_trips_through_plaintext() {
+        let glider = Universe::from_plaintext(GLIDER).unwrap();
+        assert_eq!(
+            "!Name: Glider\n.O.\n..O\nOOO\n",
+            glider.to_plaintext(Some("Glider"))
+        );
+        let copy = Universe::from_plaintext(&glider.to_plaintext(None)).unwrap();
+        assert_eq!(glider.to_string(), copy.to_string());
+    }
+}
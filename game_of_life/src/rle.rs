@@ -0,0 +1,536 @@
+use crate::game_of_life::{Cell, Universe};
+use crate::rule::{ParseRuleError, Rule};
+use crate::topology::Topology;
+use std::error::Error;
+use std::fmt;
+
+/// Longest line `to_rle` will write, as recommended by the format.
+const MAX_LINE_LEN: usize = 70;
+
+/// Starts the comment that keeps topologies Golly has no grid type for,
+/// such as alive edges.
+const TOPOLOGY_COMMENT: &str = "#C topology ";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RleErrorKind {
+    MissingHeader,
+    InvalidHeader(String),
+    InvalidRule(ParseRuleError),
+    PatternTooLarge,
+    RunTooLong,
+    UnexpectedChar(char),
+    RowTooLong(usize),
+    TooManyRows(usize),
+}
+
+/// A problem found while reading RLE, with the 1-based line and column
+/// where it was found.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RleError {
+    pub line: usize,
+    pub col: usize,
+    pub kind: RleErrorKind,
+}
+
+impl RleError {
+    fn new(line: usize, col: usize, kind: RleErrorKind) -> Self {
+        Self { line, col, kind }
+    }
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.col)?;
+        match &self.kind {
+            RleErrorKind::MissingHeader => write!(f, "expected an 'x = .., y = ..' header"),
+            RleErrorKind::InvalidHeader(reason) => write!(f, "invalid header: {}", reason),
+            RleErrorKind::InvalidRule(e) => write!(f, "invalid rule: {}", e),
+            RleErrorKind::PatternTooLarge => write!(f, "pattern is too large to hold"),
+            RleErrorKind::RunTooLong => write!(f, "run count is too large"),
+            RleErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+            RleErrorKind::RowTooLong(width) => {
+                write!(f, "row is wider than the header's x = {}", width)
+            }
+            RleErrorKind::TooManyRows(height) => {
+                write!(f, "pattern is taller than the header's y = {}", height)
+            }
+        }
+    }
+}
+
+impl Error for RleError {}
+
+struct Header {
+    width: usize,
+    height: usize,
+    rule: Option<Rule>,
+    topology: Topology,
+}
+
+/// Golly appends the bounded grid it was saved from to the rule, as in
+/// `B3/S23:T80,60`.
+fn parse_rule_and_topology(value: &str) -> Result<(Rule, Topology), RleErrorKind> {
+    let (rule, grid) = match value.split_once(':') {
+        Some((rule, grid)) => (rule, Some(grid)),
+        None => (value, None),
+    };
+    let rule = rule.parse().map_err(RleErrorKind::InvalidRule)?;
+    let topology = match grid.and_then(|g| g.trim().chars().next()) {
+        None | Some('P') => Topology::Bounded,
+        Some('T') => Topology::Torus,
+        Some('K') => Topology::KleinBottle,
+        Some('C') => Topology::CrossSurface,
+        Some(other) => {
+            let reason = format!("unsupported grid type '{}'", other);
+            return Err(RleErrorKind::InvalidHeader(reason));
+        }
+    };
+    Ok((rule, topology))
+}
+
+fn parse_header(line: &str) -> Result<Header, RleErrorKind> {
+    let mut header = Header {
+        width: 0,
+        height: 0,
+        rule: None,
+        topology: Topology::Bounded,
+    };
+    let (mut has_x, mut has_y) = (false, false);
+    // The rule comes last and may itself contain a comma, as in `:T80,60`
+    let (sizes, rule) = match line.find("rule") {
+        Some(i) => (&line[..i], Some(&line[i..])),
+        None => (line, None),
+    };
+    let key_value = |field: &str| {
+        field
+            .split_once('=')
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .ok_or_else(|| {
+                RleErrorKind::InvalidHeader(format!("'{}' is not 'key = value'", field.trim()))
+            })
+    };
+    for field in sizes.split(',').filter(|f| !f.trim().is_empty()) {
+        let (key, value) = key_value(field)?;
+        let size = value
+            .parse::<usize>()
+            .map_err(|_| RleErrorKind::InvalidHeader(format!("'{}' is not a size", value)))?;
+        match key.as_str() {
+            "x" => {
+                header.width = size;
+                has_x = true;
+            }
+            "y" => {
+                header.height = size;
+                has_y = true;
+            }
+            other => {
+                let reason = format!("unknown key '{}'", other);
+                return Err(RleErrorKind::InvalidHeader(reason));
+            }
+        }
+    }
+    if let Some(rule) = rule {
+        let (_, value) = key_value(rule)?;
+        let (rule, topology) = parse_rule_and_topology(&value)?;
+        header.rule = Some(rule);
+        header.topology = topology;
+    }
+    if !has_x || !has_y {
+        return Err(RleErrorKind::MissingHeader);
+    }
+    Ok(header)
+}
+
+impl Universe {
+    /// Reads a pattern in Run Length Encoded format. Cells in any state
+    /// other than 0 of a multi-state pattern are read as alive. An empty
+    /// pattern, which Golly writes as `x = 0, y = 0`, is read as a single
+    /// dead cell, as a `Universe` cannot be empty.
+    pub fn from_rle(rle: &str) -> Result<Universe, RleError> {
+        let mut lines = rle.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let mut old_style_rule = None;
+        let mut comment_topology = None;
+
+        let (header_line, header) = loop {
+            let (line_no, line) = lines.next().ok_or_else(|| {
+                RleError::new(rle.lines().count() + 1, 1, RleErrorKind::MissingHeader)
+            })?;
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                // #N name, #C/#c comment, #O author and #P/#R offsets carry
+                // nothing a Universe can hold, but XLife's #r is the rule and
+                // a #C topology comment the topology
+                if let Some(rule) = comment.strip_prefix('r') {
+                    let rule = rule
+                        .trim()
+                        .parse()
+                        .map_err(|e| RleError::new(line_no, 3, RleErrorKind::InvalidRule(e)))?;
+                    old_style_rule = Some(rule);
+                }
+                // Comments that merely start the same way are ignored
+                if let Some(name) = trimmed.strip_prefix(TOPOLOGY_COMMENT) {
+                    comment_topology = name.parse().ok();
+                }
+                continue;
+            }
+            let header = parse_header(trimmed).map_err(|kind| RleError::new(line_no, 1, kind))?;
+            break (line_no, header);
+        };
+
+        let (width, height) = (header.width, header.height);
+        let size = width
+            .checked_mul(height)
+            .ok_or_else(|| RleError::new(header_line, 1, RleErrorKind::PatternTooLarge))?;
+        let mut cells = vec![Cell::Dead; size];
+        let (mut row, mut col): (usize, usize) = (0, 0);
+        let mut run: Option<usize> = None;
+        let mut prefix: Option<(usize, usize)> = None;
+
+        'body: for (line_no, line) in lines {
+            for (i, c) in line.chars().enumerate() {
+                let col_no = i + 1;
+                let err = |kind| RleError::new(line_no, col_no, kind);
+                if let Some((prefix_line, prefix_col)) = prefix {
+                    // Multi-state letters above 24 are written 'p'..'y' then 'A'..'X'
+                    if !c.is_ascii_uppercase() {
+                        return Err(RleError::new(
+                            prefix_line,
+                            prefix_col,
+                            RleErrorKind::UnexpectedChar(c),
+                        ));
+                    }
+                }
+                let count = run.unwrap_or(1);
+                match c {
+                    '0'..='9' => {
+                        let digit = c.to_digit(10).unwrap() as usize;
+                        let longer = run
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|n| n.checked_add(digit));
+                        run = Some(longer.ok_or_else(|| err(RleErrorKind::RunTooLong))?);
+                        continue;
+                    }
+                    'p'..='y' => {
+                        prefix = Some((line_no, col_no));
+                        continue;
+                    }
+                    'b' | '.' => col = col.saturating_add(count),
+                    'o' | 'A'..='X' => {
+                        if row >= height {
+                            return Err(err(RleErrorKind::TooManyRows(height)));
+                        }
+                        if col.saturating_add(count) > width {
+                            return Err(err(RleErrorKind::RowTooLong(width)));
+                        }
+                        for cell in &mut cells[row * width + col..row * width + col + count] {
+                            *cell = Cell::Alive;
+                        }
+                        col += count;
+                    }
+                    '$' => {
+                        row = row.saturating_add(count);
+                        col = 0;
+                    }
+                    '!' => break 'body,
+                    c if c.is_whitespace() => continue,
+                    c => return Err(err(RleErrorKind::UnexpectedChar(c))),
+                }
+                if col > width {
+                    return Err(err(RleErrorKind::RowTooLong(width)));
+                }
+                run = None;
+                prefix = None;
+            }
+        }
+
+        let rule = header.rule.or(old_style_rule).unwrap_or_default();
+        if cells.is_empty() {
+            cells.push(Cell::Dead);
+        }
+        let topology = match comment_topology {
+            Some(topology) if header.topology == Topology::Bounded => topology,
+            _ => header.topology,
+        };
+        Ok(Universe::new(&cells, width.max(1))
+            .with_rule(rule)
+            .with_topology(topology))
+    }
+
+    /// Writes the universe as Run Length Encoded text, wrapping lines at 70
+    /// columns. Alive edges, which Golly has no grid type for, are kept in a
+    /// `#C topology` comment.
+    pub fn to_rle(&self) -> String {
+        let mut rule = self.rule().to_string();
+        let mut out = String::new();
+        let grid_type = match self.topology() {
+            Topology::Torus => Some('T'),
+            Topology::KleinBottle => Some('K'),
+            Topology::CrossSurface => Some('C'),
+            Topology::AliveEdge => {
+                out.push_str(&format!("{}{}\n", TOPOLOGY_COMMENT, self.topology()));
+                None
+            }
+            Topology::Bounded => None,
+        };
+        if let Some(grid_type) = grid_type {
+            rule.push_str(&format!(":{}{},{}", grid_type, self.width(), self.height()));
+        }
+        out.push_str(&format!(
+            "x = {}, y = {}, rule = {}\n",
+            self.width(),
+            self.height(),
+            rule
+        ));
+
+        let mut tokens = Vec::new();
+        // Blank rows not yet written, and whether any row has been
+        let mut pending_rows = 0;
+        let mut wrote_row = false;
+        for row in 0..self.height() {
+            let mut runs: Vec<(usize, char)> = Vec::new();
+            for col in 0..self.width() {
+                let tag = if self.cell_at(row, col).is_alive() {
+                    'o'
+                } else {
+                    'b'
+                };
+                match runs.last_mut() {
+                    Some((count, last)) if *last == tag => *count += 1,
+                    _ => runs.push((1, tag)),
+                }
+            }
+            if let Some((_, 'b')) = runs.last() {
+                runs.pop();
+            }
+            if runs.is_empty() {
+                pending_rows += 1;
+                continue;
+            }
+            // A `$` ends each written row; blank rows before the first need
+            // one each to skip them
+            if wrote_row {
+                tokens.push(run_token(pending_rows + 1, '$'));
+            } else if pending_rows > 0 {
+                tokens.push(run_token(pending_rows, '$'));
+            }
+            pending_rows = 0;
+            wrote_row = true;
+            tokens.extend(runs.into_iter().map(|(count, tag)| run_token(count, tag)));
+        }
+        tokens.push("!".to_string());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len + token.len() > MAX_LINE_LEN {
+                out.push('\n');
+                line_len = 0;
+            }
+            line_len += token.len();
+            out.push_str(&token);
+        }
+        out.push('\n');
+        out
+    }
+}
+
+fn run_token(count: usize, tag: char) -> String {
+    if count == 1 {
+        tag.to_string()
+    } else {
+        format!("{}{}", count, tag)
+    }
+}
+
+#[cfg(test)]
+mod rle_tests {
+    use super::*;
+
+    const GLIDER: &str = "#N Glider\n\
+                          #O Richard K. Guy\n\
+                          #C The smallest, most common, and first discovered spaceship.\n\
+                          x = 3, y = 3, rule = B3/S23\n\
+                          bob$2bo$3o!\n";
+
+    const GOSPER_GUN: &str = "#N Gosper glider gun\n\
+        x = 36, y = 9, rule = B3/S23\n\
+        24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b\n\
+        obo$10bo5bo7bo$11bo3bo$12b2o!\n";
+
+    fn population(universe: &Universe) -> usize {
+        universe
+            .enumerate_cells()
+            .filter(|(_, _, c)| c.is_alive())
+            .count()
+    }
+
+    #[test]
+    fn can_read_a_glider() {
+        let glider = Universe::from_rle(GLIDER).unwrap();
+        let expected = "Generation 0:\n3 3\n.*.\n..*\n***";
+        assert_eq!(expected, glider.to_string());
+        assert_eq!(Rule::conway(), glider.rule());
+    }
+
+    #[test]
+    fn can_read_a_pattern_wrapped_across_lines() {
+        let gun = Universe::from_rle(GOSPER_GUN).unwrap();
+        assert_eq!((9, 36), (gun.height(), gun.width()));
+        assert_eq!(36, population(&gun));
+        assert_eq!(&Cell::Alive, gun.cell_at(5, 22));
+    }
+
+    #[test]
+    fn reads_the_rule_from_the_header() {
+        let rle = "x = 2, y = 1, rule = B36/S23\n2o!";
+        let universe = Universe::from_rle(rle).unwrap();
+        assert_eq!("B36/S23".parse::<Rule>().unwrap(), universe.rule());
+    }
+
+    #[test]
+    fn reads_the_topology_from_the_rule_suffix() {
+        let rle = "x = 2, y = 1, rule = B3/S23:T2,1\n2o!";
+        assert_eq!(Topology::Torus, Universe::from_rle(rle).unwrap().topology());
+    }
+
+    #[test]
+    fn defaults_to_conway_without_a_rule() {
+        let universe = Universe::from_rle("x = 1, y = 1\no!").unwrap();
+        assert_eq!(Rule::conway(), universe.rule());
+    }
+
+    #[test]
+    fn multi_state_cells_are_read_as_alive() {
+        let universe = Universe::from_rle("x = 5, y = 1\n.A2BpA!").unwrap();
+        assert_eq!("Generation 0:\n1 5\n.****", universe.to_string());
+    }
+
+    #[test]
+    fn reports_unexpected_characters_with_line_and_column() {
+        let err = Universe::from_rle("#C comment\nx = 3, y = 1\nbo\nbz!").unwrap_err();
+        assert_eq!(RleError::new(4, 2, RleErrorKind::UnexpectedChar('z')), err);
+        assert_eq!(
+            "line 4, column 2: unexpected character 'z'",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn reports_rows_wider_than_the_header() {
+        let err = Universe::from_rle("x = 2, y = 1\n3o!").unwrap_err();
+        assert_eq!(RleError::new(2, 2, RleErrorKind::RowTooLong(2)), err);
+    }
+
+    #[test]
+    fn reports_patterns_taller_than_the_header() {
+        let err = Universe::from_rle("x = 1, y = 1\no$o!").unwrap_err();
+        assert_eq!(RleError::new(2, 3, RleErrorKind::TooManyRows(1)), err);
+    }
+
+    #[test]
+    fn reports_a_missing_header() {
+        let err = Universe::from_rle("#N nothing\n").unwrap_err();
+        assert_eq!(RleErrorKind::MissingHeader, err.kind);
+    }
+
+    #[test]
+    fn reports_an_invalid_rule() {
+        let err = Universe::from_rle("x = 1, y = 1, rule = B9/S\no!").unwrap_err();
+        assert_eq!(1, err.line);
+        assert!(matches!(err.kind, RleErrorKind::InvalidRule(_)));
+    }
+
+    #[test]
+    fn writes_runs_rows_and_header() {
+        let glider = Universe::from_rle(GLIDER).unwrap();
+        assert_eq!("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n", glider.to_rle());
+    }
+
+    #[test]
+    fn writes_blank_rows_as_a_single_run() {
+        let rle = "x = 2, y = 4, rule = B3/S23\no3$bo!\n";
+        assert_eq!(rle, Universe::from_rle(rle).unwrap().to_rle());
+    }
+
+    #[test]
+    fn writes_leading_blank_rows_once() {
+        let mut universe = Universe::new(&[Cell::Dead; 9], 3);
+        universe.set_cell_at(2, 1, Cell::Alive);
+        let rle = universe.to_rle();
+        assert_eq!("x = 3, y = 3, rule = B3/S23\n2$bo!\n", rle);
+        let copy = Universe::from_rle(&rle).unwrap();
+        assert_eq!(universe.to_string(), copy.to_string());
+    }
+
+    #[test]
+    fn reads_golly_s_empty_patterns() {
+        let empty = Universe::from_rle("x = 0, y = 0, rule = B3/S23\n!\n").unwrap();
+        assert_eq!((1, 1), (empty.height(), empty.width()));
+        assert_eq!(0, population(&empty));
+        let err = Universe::from_rle("x = 0, y = 0\no!").unwrap_err();
+        assert_eq!(RleErrorKind::TooManyRows(0), err.kind);
+    }
+
+    #[test]
+    fn reports_run_counts_too_large_to_hold() {
+        let rle = format!("x = 2, y = 1\n{}0o!", usize::MAX);
+        let err = Universe::from_rle(&rle).unwrap_err();
+        assert_eq!(RleErrorKind::RunTooLong, err.kind);
+        let rle = format!("x = 2, y = 1\n{}b{}bo!", usize::MAX, usize::MAX);
+        let err = Universe::from_rle(&rle).unwrap_err();
+        assert_eq!(RleErrorKind::RowTooLong(2), err.kind);
+        let rle = format!("x = {}, y = 2\no!", usize::MAX);
+        let err = Universe::from_rle(&rle).unwrap_err();
+        assert_eq!(RleErrorKind::PatternTooLarge, err.kind);
+    }
+
+    #[test]
+    fn wraps_lines_at_70_columns() {
+        let rle = Universe::from_rle(GOSPER_GUN).unwrap().to_rle();
+        assert!(rle.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        assert!(rle.lines().count() > 2);
+    }
+
+    #[test]
+    fn round_trips_through_rle() {
+        let blank_first_row = "x = 4, y = 5, rule = B3/S23\n$2bo2$o2bo$b2o!\n";
+        for source in [GLIDER, GOSPER_GUN, blank_first_row] {
+            let universe = Universe::from_rle(source).unwrap();
+            let copy = Universe::from_rle(&universe.to_rle()).unwrap();
+            assert_eq!(universe.to_string(), copy.to_string());
+            assert_eq!(universe.rule(), copy.rule());
+        }
+    }
+
+    #[test]
+    fn round_trips_rule_and_topology() {
+        let universe = Universe::from_rle("x = 3, y = 2, rule = B36/S23\n3o$o!")
+            .unwrap()
+            .with_topology(Topology::KleinBottle);
+        let copy = Universe::from_rle(&universe.to_rle()).unwrap();
+        assert_eq!(Topology::KleinBottle, copy.topology());
+        assert_eq!(universe.rule(), copy.rule());
+    }
+
+    #[test]
+    fn round_trips_alive_edges_in_a_comment() {
+        let universe = Universe::from_rle("x = 3, y = 2\n3o$o!")
+            .unwrap()
+            .with_topology(Topology::AliveEdge);
+        let rle = universe.to_rle();
+        assert!(rle.starts_with("#C topology alive-edge\nx = 3, y = 2, rule = B3/S23\n"));
+        assert_eq!(
+            Topology::AliveEdge,
+            Universe::from_rle(&rle).unwrap().topology()
+        );
+
+        let comment = "#C topology of the pattern\nx = 1, y = 1\no!";
+        assert_eq!(
+            Topology::Bounded,
+            Universe::from_rle(comment).unwrap().topology()
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::game_of_life::Cell;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+const MAX_NEIGHBORS: usize = 8;
+
+/// A life-like (outer totalistic, Moore neighborhood) rule, written in the
+/// usual `B3/S23` notation or the older `S/B` form such as `23/3`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Rule {
+    birth: [bool; MAX_NEIGHBORS + 1],
+    survival: [bool; MAX_NEIGHBORS + 1],
+}
+
+impl Rule {
+    /// A rule from its birth and survival neighbor counts, which must be
+    /// at most 8.
+    pub fn new(birth: &[u8], survival: &[u8]) -> Result<Self, ParseRuleError> {
+        let counts = |ns: &[u8]| {
+            let mut counts = [false; MAX_NEIGHBORS + 1];
+            for &n in ns {
+                *counts
+                    .get_mut(n as usize)
+                    .ok_or(ParseRuleError::CountOutOfRange(n))? = true;
+            }
+            Ok(counts)
+        };
+        Ok(Self {
+            birth: counts(birth)?,
+            survival: counts(survival)?,
+        })
+    }
+
+    pub fn conway() -> Self {
+        Self::new(&[3], &[2, 3]).expect("Conway's neighbor counts are in range")
+    }
+
+    pub fn births(&self, living_neighbors: u8) -> bool {
+        self.birth[living_neighbors as usize]
+    }
+
+    pub fn survives(&self, living_neighbors: u8) -> bool {
+        self.survival[living_neighbors as usize]
+    }
+
+    pub fn next_cell_state(&self, cell: Cell, living_neighbors: u8) -> Cell {
+        let lives = match cell {
+            Cell::Alive => self.survives(living_neighbors),
+            Cell::Dead => self.births(living_neighbors),
+        };
+        if lives {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    fn parse_counts(digits: &str, rule: &str) -> Result<[bool; MAX_NEIGHBORS + 1], ParseRuleError> {
+        let mut counts = [false; MAX_NEIGHBORS + 1];
+        for c in digits.chars() {
+            match c.to_digit(10) {
+                Some(n) if n as usize <= MAX_NEIGHBORS => {
+                    if counts[n as usize] {
+                        return Err(ParseRuleError::DuplicateCount(c));
+                    }
+                    counts[n as usize] = true;
+                }
+                Some(n) => return Err(ParseRuleError::CountOutOfRange(n as u8)),
+                None => return Err(ParseRuleError::UnexpectedChar(c, rule.to_string())),
+            }
+        }
+        Ok(counts)
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseRuleError::Empty);
+        }
+        let (left, right) = s
+            .split_once('/')
+            .ok_or_else(|| ParseRuleError::MissingSeparator(s.to_string()))?;
+
+        let strip = |part: &str, prefix: char| {
+            part.strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .map(str::to_string)
+        };
+
+        let (birth, survival) = match (strip(left, 'B'), strip(right, 'S')) {
+            (Some(b), Some(s)) => (b, s),
+            (None, None) => match (strip(left, 'S'), strip(right, 'B')) {
+                // S23/B3
+                (Some(s), Some(b)) => (b, s),
+                // 23/3
+                (None, None) => (right.to_string(), left.to_string()),
+                _ => return Err(ParseRuleError::MismatchedPrefixes(s.to_string())),
+            },
+            _ => return Err(ParseRuleError::MismatchedPrefixes(s.to_string())),
+        };
+
+        Ok(Self {
+            birth: Self::parse_counts(&birth, s)?,
+            survival: Self::parse_counts(&survival, s)?,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = |counts: &[bool]| -> String {
+            counts
+                .iter()
+                .enumerate()
+                .filter(|(_, &on)| on)
+                .map(|(n, _)| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survival))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseRuleError {
+    Empty,
+    MissingSeparator(String),
+    MismatchedPrefixes(String),
+    UnexpectedChar(char, String),
+    CountOutOfRange(u8),
+    DuplicateCount(char),
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "rulestring is empty"),
+            Self::MissingSeparator(s) => {
+                write!(
+                    f,
+                    "rulestring '{}' has no '/' between birth and survival",
+                    s
+                )
+            }
+            Self::MismatchedPrefixes(s) => {
+                write!(
+                    f,
+                    "rulestring '{}' must use both 'B' and 'S' prefixes or neither",
+                    s
+                )
+            }
+            Self::UnexpectedChar(c, s) => {
+                write!(f, "unexpected character '{}' in rulestring '{}'", c, s)
+            }
+            Self::CountOutOfRange(c) => {
+                write!(
+                    f,
+                    "neighbor count {} is out of range 0-{}",
+                    c, MAX_NEIGHBORS
+                )
+            }
+            Self::DuplicateCount(c) => write!(f, "neighbor count {} appears more than once", c),
+        }
+    }
+}
+
+impl Error for ParseRuleError {}
+
+#[cfg(test)]
+mod rule_tests {
+    use super::*;
+
+    #[test]
+    fn the_default_rule_is_conway() {
+        assert_eq!(Rule::conway(), Rule::default());
+        assert_eq!("B3/S23", Rule::default().to_string());
+    }
+
+    #[test]
+    fn can_parse_bs_notation() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(Rule::new(&[3, 6], &[2, 3]).unwrap(), highlife);
+    }
+
+    #[test]
+    fn can_parse_lowercase_bs_notation() {
+        let day_and_night: Rule = "b3678/s34678".parse().unwrap();
+        assert_eq!("B3678/S34678", day_and_night.to_string());
+    }
+
+    #[test]
+    fn can_parse_sb_notation_with_prefixes() {
+        let rule: Rule = "S23/B36".parse().unwrap();
+        assert_eq!(Rule::new(&[3, 6], &[2, 3]).unwrap(), rule);
+    }
+
+    #[test]
+    fn can_parse_survival_birth_notation_without_prefixes() {
+        let highlife: Rule = "23/36".parse().unwrap();
+        assert_eq!(Rule::new(&[3, 6], &[2, 3]).unwrap(), highlife);
+    }
+
+    #[test]
+    fn can_parse_rules_with_empty_survival() {
+        let seeds: Rule = "B2/S".parse().unwrap();
+        assert_eq!(Rule::new(&[2], &[]).unwrap(), seeds);
+        assert_eq!("B2/S", seeds.to_string());
+    }
+
+    #[test]
+    fn a_rulestring_without_a_separator_is_an_error() {
+        assert_eq!(
+            Err(ParseRuleError::MissingSeparator("B3S23".to_string())),
+            "B3S23".parse::<Rule>()
+        );
+    }
+
+    #[test]
+    fn a_neighbor_count_above_eight_is_an_error() {
+        assert_eq!(
+            Err(ParseRuleError::CountOutOfRange(9)),
+            "B39/S23".parse::<Rule>()
+        );
+    }
+
+    #[test]
+    fn building_a_rule_with_a_count_above_eight_is_an_error() {
+        assert_eq!(
+            Err(ParseRuleError::CountOutOfRange(12)),
+            Rule::new(&[3], &[2, 12])
+        );
+    }
+
+    #[test]
+    fn a_repeated_neighbor_count_is_an_error() {
+        assert_eq!(
+            Err(ParseRuleError::DuplicateCount('3')),
+            "B33/S23".parse::<Rule>()
+        );
+    }
+
+    #[test]
+    fn a_stray_character_is_an_error() {
+        assert!(matches!(
+            "B3/S2x".parse::<Rule>(),
+            Err(ParseRuleError::UnexpectedChar('x', _))
+        ));
+    }
+
+    #[test]
+    fn mixing_prefixed_and_bare_halves_is_an_error() {
+        assert!(matches!(
+            "B3/23".parse::<Rule>(),
+            Err(ParseRuleError::MismatchedPrefixes(_))
+        ));
+    }
+
+    #[test]
+    fn an_empty_rulestring_is_an_error() {
+        assert_eq!(Err(ParseRuleError::Empty), "  ".parse::<Rule>());
+    }
+
+    #[test]
+    fn highlife_births_on_six_neighbors() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(Cell::Alive, highlife.next_cell_state(Cell::Dead, 6));
+        assert_eq!(Cell::Dead, Rule::conway().next_cell_state(Cell::Dead, 6));
+    }
+}
@@ -0,0 +1,311 @@
+//! Random starting states ("soups"), reproducible from their seed and
+//! optionally symmetric, as soup searches use to find new objects.
+
+use crate::game_of_life::{Cell, Universe};
+use crate::rng::Rng;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The symmetry group a soup is made invariant under, in the notation soup
+/// searches use.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Symmetry {
+    /// No symmetry.
+    #[default]
+    C1,
+    /// Unchanged by a half turn.
+    C2,
+    /// Unchanged by a quarter turn.
+    C4,
+    /// Unchanged by quarter turns and reflections.
+    D8,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 4] = [Self::C1, Self::C2, Self::C4, Self::D8];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::C1 => "C1",
+            Self::C2 => "C2",
+            Self::C4 => "C4",
+            Self::D8 => "D8",
+        }
+    }
+
+    /// Whether the group holds a quarter turn, which needs a square region.
+    fn needs_square(&self) -> bool {
+        matches!(self, Self::C4 | Self::D8)
+    }
+
+    /// The cells `(row, col)` is carried to in a `height x width` region,
+    /// including itself.
+    fn images(&self, row: usize, col: usize, height: usize, width: usize) -> Vec<(usize, usize)> {
+        let (bottom, right) = (height - 1, width - 1);
+        match self {
+            Self::C1 => vec![(row, col)],
+            Self::C2 => vec![(row, col), (bottom - row, right - col)],
+            Self::C4 | Self::D8 => {
+                let turns = [
+                    (row, col),
+                    (col, right - row),
+                    (bottom - row, right - col),
+                    (bottom - col, row),
+                ];
+                let mut images = turns.to_vec();
+                if *self == Self::D8 {
+                    images.extend(turns.iter().map(|&(r, c)| (r, right - c)));
+                }
+                images
+            }
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseSymmetryError(String);
+
+impl fmt::Display for ParseSymmetryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = Symmetry::ALL.iter().map(Symmetry::name).collect();
+        write!(
+            f,
+            "unknown symmetry '{}', expected one of {}",
+            self.0,
+            names.join(", ")
+        )
+    }
+}
+
+impl Error for ParseSymmetryError {}
+
+impl FromStr for Symmetry {
+    type Err = ParseSymmetryError;
+
+    /// Accepts the names `Display` writes, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_uppercase();
+        Self::ALL
+            .into_iter()
+            .find(|symmetry| symmetry.name() == name)
+            .ok_or_else(|| ParseSymmetryError(s.to_string()))
+    }
+}
+
+/// How to make a soup. The same settings always give the same cells.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Soup {
+    seed: u64,
+    density: f64,
+    symmetry: Symmetry,
+    size: Option<(usize, usize)>,
+}
+
+impl Soup {
+    /// A soup filling the whole grid with half its cells alive.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            density: 0.5,
+            symmetry: Symmetry::C1,
+            size: None,
+        }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    /// The chance of each cell, or each set of cells the symmetry ties
+    /// together, being alive.
+    pub fn with_density(self, density: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&density),
+            "density must be between 0 and 1"
+        );
+        Self { density, ..self }
+    }
+
+    pub fn with_symmetry(self, symmetry: Symmetry) -> Self {
+        Self { symmetry, ..self }
+    }
+
+    /// Fills only a `rows x cols` region in the middle of the grid, leaving
+    /// the rest dead.
+    pub fn with_size(self, rows: usize, cols: usize) -> Self {
+        Self {
+            size: Some((rows, cols)),
+            ..self
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    pub fn size(&self) -> Option<(usize, usize)> {
+        self.size
+    }
+
+    /// The `(top, left, height, width)` of the filled region in a
+    /// `rows x cols` grid: the asked for size cut down to fit, and to a
+    /// square for symmetries with quarter turns.
+    fn region(&self, rows: usize, cols: usize) -> (usize, usize, usize, usize) {
+        let (mut height, mut width) = match self.size {
+            Some((height, width)) => (height.min(rows), width.min(cols)),
+            None => (rows, cols),
+        };
+        if self.symmetry.needs_square() {
+            height = height.min(width);
+            width = height;
+        }
+        ((rows - height) / 2, (cols - width) / 2, height, width)
+    }
+
+    /// The living cells of the soup in a `rows x cols` grid, row by row.
+    pub fn live_cells(&self, rows: usize, cols: usize) -> Vec<(usize, usize)> {
+        let (top, left, height, width) = self.region(rows, cols);
+        let mut rng = Rng::new(self.seed);
+        let alive: Vec<bool> = (0..height * width)
+            .map(|_| rng.chance(self.density))
+            .collect();
+        let mut live = Vec::new();
+        for row in 0..height {
+            for col in 0..width {
+                // Every cell takes after the first of the cells the
+                // symmetry ties it to, so they all agree.
+                let images = self.symmetry.images(row, col, height, width);
+                let (r, c) = images.into_iter().min().unwrap_or((row, col));
+                if alive[r * width + c] {
+                    live.push((top + row, left + col));
+                }
+            }
+        }
+        live
+    }
+
+    /// The soup as a `rows x cols` universe.
+    pub fn universe(&self, rows: usize, cols: usize) -> Universe {
+        let mut cells = vec![Cell::Dead; rows * cols];
+        for (row, col) in self.live_cells(rows, cols) {
+            cells[row * cols + col] = Cell::Alive;
+        }
+        Universe::new(&cells, cols)
+    }
+}
+
+#[cfg(test)]
+mod soup_tests {
+    use super::*;
+
+    fn grid(soup: &Soup, rows: usize, cols: usize) -> Vec<Vec<bool>> {
+        let mut grid = vec![vec![false; cols]; rows];
+        for (row, col) in soup.live_cells(rows, cols) {
+            grid[row][col] = true;
+        }
+        grid
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_soup() {
+        let soup = Soup::new(96155);
+        assert_eq!(soup.live_cells(20, 30), soup.live_cells(20, 30));
+        assert_ne!(
+            soup.live_cells(20, 30),
+            soup.with_seed(96156).live_cells(20, 30)
+        );
+    }
+
+    #[test]
+    fn density_sets_the_fraction_alive() {
+        for density in [0.0, 0.2, 0.5, 1.0] {
+            let live = Soup::new(1).with_density(density).live_cells(100, 100);
+            let fraction = live.len() as f64 / 10_000.0;
+            assert!((fraction - density).abs() < 0.03, "{}", fraction);
+        }
+    }
+
+    #[test]
+    fn c2_soups_are_unchanged_by_a_half_turn() {
+        let grid = grid(&Soup::new(5).with_symmetry(Symmetry::C2), 7, 10);
+        for (row, cells) in grid.iter().enumerate() {
+            for (col, &alive) in cells.iter().enumerate() {
+                assert_eq!(alive, grid[6 - row][9 - col], "({}, {})", row, col);
+            }
+        }
+    }
+
+    #[test]
+    fn c4_and_d8_soups_are_unchanged_by_their_symmetries() {
+        let n = 9;
+        let c4 = grid(&Soup::new(5).with_symmetry(Symmetry::C4), n, n);
+        let d8 = grid(&Soup::new(5).with_symmetry(Symmetry::D8), n, n);
+        for row in 0..n {
+            for col in 0..n {
+                assert_eq!(c4[row][col], c4[col][n - 1 - row]);
+                assert_eq!(d8[row][col], d8[col][n - 1 - row]);
+                assert_eq!(d8[row][col], d8[row][n - 1 - col]);
+            }
+        }
+        let c1 = grid(&Soup::new(5), n, n);
+        assert!((0..n).any(|row| c1[row][0] != c1[row][n - 1]));
+    }
+
+    #[test]
+    fn sized_soups_are_centred_and_cut_to_fit() {
+        let soup = Soup::new(3).with_density(1.0).with_size(2, 4);
+        let live = soup.live_cells(6, 6);
+        assert_eq!(8, live.len());
+        assert!(live
+            .iter()
+            .all(|&(row, col)| (2..4).contains(&row) && (1..5).contains(&col)));
+        assert_eq!(8, soup.live_cells(3, 6).len());
+        assert_eq!(2, soup.live_cells(6, 1).len());
+
+        // Quarter turns need a square, so the region shrinks to one.
+        let square = soup.with_symmetry(Symmetry::C4).live_cells(6, 6);
+        assert_eq!(vec![(2, 2), (2, 3), (3, 2), (3, 3)], square);
+    }
+
+    #[test]
+    fn parses_symmetry_names() {
+        assert_eq!(Ok(Symmetry::D8), "d8".parse());
+        assert_eq!(Ok(Symmetry::C2), " C2 ".parse());
+        let err = "C3".parse::<Symmetry>().unwrap_err();
+        assert_eq!(
+            "unknown symmetry 'C3', expected one of C1, C2, C4, D8",
+            err.to_string()
+        );
+        for symmetry in Symmetry::ALL {
+            assert_eq!(Ok(symmetry), symmetry.to_string().parse());
+        }
+    }
+
+    #[test]
+    fn soups_fill_universes() {
+        let soup = Soup::new(9).with_size(3, 3);
+        let universe = soup.universe(5, 8);
+        assert_eq!((5, 8), (universe.height(), universe.width()));
+        let live: Vec<(usize, usize)> = universe
+            .enumerate_cells()
+            .filter(|(_, _, cell)| cell.is_alive())
+            .map(|(row, col, _)| (row, col))
+            .collect();
+        assert_eq!(soup.live_cells(5, 8), live);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::engine::{LifeEngine, UnboundedError, Viewport};
+use crate::game_of_life::{Cell, Universe};
+use crate::history::Snapshot;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+/// An unbounded universe that only stores the coordinates of its living
+/// cells, so patterns are free to grow in any direction.
+#[derive(Debug, Clone, Default)]
+pub struct SparseUniverse {
+    generation: u64,
+    live: HashSet<(i64, i64)>,
+    rule: Rule,
+}
+
+impl SparseUniverse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places the living cells of `universe` at the same coordinates in an
+    /// unbounded plane, keeping its rule. Only bounded universes can be
+    /// placed, as the plane has no edges for other topologies to join.
+    pub fn from_universe(universe: &Universe) -> Result<Self, UnboundedError> {
+        if universe.topology() != Topology::Bounded {
+            return Err(UnboundedError::Topology(universe.topology()));
+        }
+        let live = universe
+            .enumerate_cells()
+            .filter(|(_, _, cell)| cell.is_alive())
+            .map(|(r, c, _)| (r as i64, c as i64))
+            .collect();
+        Self::new()
+            .with_rule(universe.rule())
+            .map(|sparse| Self { live, ..sparse })
+    }
+
+    /// Fails for rules with births on zero neighbors, which would fill the
+    /// plane in one generation.
+    pub fn with_rule(self, rule: Rule) -> Result<Self, UnboundedError> {
+        if rule.births(0) {
+            return Err(UnboundedError::BirthOnZero(rule));
+        }
+        Ok(Self { rule, ..self })
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> usize {
+        self.live.len()
+    }
+
+    pub fn live_cells(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.live.iter().copied()
+    }
+
+    pub fn cell_at(&self, row: i64, col: i64) -> &Cell {
+        if self.live.contains(&(row, col)) {
+            &Cell::Alive
+        } else {
+            &Cell::Dead
+        }
+    }
+
+    pub fn set_cell_at(&mut self, row: i64, col: i64, new_cell: Cell) {
+        match new_cell {
+            Cell::Alive => self.live.insert((row, col)),
+            Cell::Dead => self.live.remove(&(row, col)),
+        };
+    }
+
+    pub fn toggle_cell_at(&mut self, row: i64, col: i64) {
+        let toggled = self.cell_at(row, col).toggle();
+        self.set_cell_at(row, col, toggled);
+    }
+
+    pub fn clear(&mut self) {
+        self.live.clear();
+        self.generation = 0;
+    }
+
+    /// The smallest `Viewport` holding every living cell.
+    pub fn bounding_box(&self) -> Option<Viewport> {
+        let mut cells = self.live.iter();
+        let &(r0, c0) = cells.next()?;
+        let (top, left, bottom, right) = cells
+            .fold((r0, c0, r0, c0), |(t, l, b, r), &(row, col)| {
+                (t.min(row), l.min(col), b.max(row), r.max(col))
+            });
+        Some(Viewport::new(
+            top,
+            left,
+            (bottom - top + 1) as usize,
+            (right - left + 1) as usize,
+        ))
+    }
+
+    pub fn next_gen(&mut self) {
+        let mut living_neighbors: HashMap<(i64, i64), u8> = HashMap::new();
+        for &(row, col) in &self.live {
+            for dr in -1..=1 {
+                for dc in -1..=1 {
+                    if dr != 0 || dc != 0 {
+                        *living_neighbors.entry((row + dr, col + dc)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+
+        let mut new_live = HashSet::with_capacity(self.live.len());
+        for (&pos, &count) in &living_neighbors {
+            if self
+                .rule
+                .next_cell_state(*self.cell_at(pos.0, pos.1), count)
+                .is_alive()
+            {
+                new_live.insert(pos);
+            }
+        }
+        // Isolated cells never appear in the neighbor map
+        if self.rule.survives(0) {
+            for &pos in &self.live {
+                if !living_neighbors.contains_key(&pos) {
+                    new_live.insert(pos);
+                }
+            }
+        }
+        self.live = new_live;
+        self.generation += 1;
+    }
+}
+
+impl Snapshot for SparseUniverse {
+    fn memory_size(&self) -> usize {
+        mem::size_of::<Self>() + self.live.capacity() * mem::size_of::<(i64, i64)>()
+    }
+}
+
+impl LifeEngine for SparseUniverse {
+    fn next_gen(&mut self) {
+        SparseUniverse::next_gen(self);
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn cell(&self, row: i64, col: i64) -> Cell {
+        *self.cell_at(row, col)
+    }
+
+    fn set_cell(&mut self, row: i64, col: i64, cell: Cell) {
+        self.set_cell_at(row, col, cell);
+    }
+
+    fn clear(&mut self) {
+        SparseUniverse::clear(self);
+    }
+
+    fn population(&self) -> u64 {
+        SparseUniverse::population(self) as u64
+    }
+
+    fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    fn topology(&self) -> Option<Topology> {
+        None
+    }
+
+    fn default_viewport(&self) -> Viewport {
+        self.bounding_box()
+            .unwrap_or_else(|| Viewport::new(0, 0, 1, 1))
+    }
+
+    fn live_cells_in(&self, viewport: &Viewport) -> Vec<(usize, usize)> {
+        self.live
+            .iter()
+            .filter(|&&(row, col)| viewport.contains(row, col))
+            .map(|&(row, col)| {
+                (
+                    (row - viewport.top) as usize,
+                    (col - viewport.left) as usize,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod sparse_tests {
+    use super::*;
+
+    fn glider() -> SparseUniverse {
+        let mut universe = SparseUniverse::new();
+        for (row, col) in [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            universe.set_cell_at(row, col, Cell::Alive);
+        }
+        universe
+    }
+
+    #[test]
+    fn a_new_sparse_universe_is_empty() {
+        let universe = SparseUniverse::new();
+        assert_eq!(0, universe.population());
+        assert_eq!(None, universe.bounding_box());
+    }
+
+    #[test]
+    fn cells_can_be_set_at_negative_coordinates() {
+        let mut universe = SparseUniverse::new();
+        universe.set_cell_at(-1_000_000, -3, Cell::Alive);
+        assert_eq!(&Cell::Alive, universe.cell_at(-1_000_000, -3));
+        assert_eq!(&Cell::Dead, universe.cell_at(-1_000_000, -2));
+        universe.toggle_cell_at(-1_000_000, -3);
+        assert_eq!(0, universe.population());
+    }
+
+    #[test]
+    fn a_glider_travels_without_hitting_an_edge() {
+        let mut universe = glider();
+        for _ in 0..400 {
+            universe.next_gen();
+        }
+        assert_eq!(400, universe.generation());
+        assert_eq!(5, universe.population());
+        assert_eq!(Some(Viewport::new(100, 100, 3, 3)), universe.bounding_box());
+    }
+
+    #[test]
+    fn a_sparse_universe_matches_a_bounded_universe_away_from_the_edges() {
+        let mut cells = vec![Cell::Dead; 20 * 20];
+        for index in [8 * 20 + 9, 8 * 20 + 10, 9 * 20 + 8, 9 * 20 + 9, 10 * 20 + 9] {
+            cells[index] = Cell::Alive;
+        }
+        let mut bounded = Universe::new(&cells, 20);
+        let mut sparse = SparseUniverse::from_universe(&bounded).unwrap();
+        for _ in 0..5 {
+            bounded.next_gen();
+            sparse.next_gen();
+        }
+        for (r, c, cell) in bounded.enumerate_cells() {
+            assert_eq!(cell, sparse.cell_at(r as i64, c as i64));
+        }
+    }
+
+    #[test]
+    fn a_sparse_universe_uses_its_rule() {
+        // Seeds: every live cell dies, pairs of neighbours give birth
+        let mut universe = SparseUniverse::new()
+            .with_rule("B2/S".parse().unwrap())
+            .unwrap();
+        universe.set_cell_at(0, 0, Cell::Alive);
+        universe.set_cell_at(0, 1, Cell::Alive);
+        universe.next_gen();
+        assert_eq!(4, universe.population());
+        assert_eq!(&Cell::Dead, universe.cell_at(0, 0));
+        assert_eq!(&Cell::Alive, universe.cell_at(-1, 0));
+    }
+
+    #[test]
+    fn isolated_cells_survive_under_s0_rules() {
+        let mut universe = SparseUniverse::new()
+            .with_rule("B3/S0".parse().unwrap())
+            .unwrap();
+        universe.set_cell_at(5, 5, Cell::Alive);
+        universe.next_gen();
+        assert_eq!(&Cell::Alive, universe.cell_at(5, 5));
+    }
+
+    #[test]
+    fn b0_rules_are_rejected() {
+        let b0 = "B0/S".parse().unwrap();
+        assert_eq!(
+            Some(UnboundedError::BirthOnZero(b0)),
+            SparseUniverse::new().with_rule(b0).err()
+        );
+        let universe = Universe::new(&[Cell::Alive], 1).with_rule(b0);
+        assert_eq!(
+            Some(UnboundedError::BirthOnZero(b0)),
+            SparseUniverse::from_universe(&universe).err()
+        );
+    }
+
+    #[test]
+    fn only_bounded_universes_can_be_placed_on_the_plane() {
+        let torus = Universe::new(&[Cell::Alive], 1).with_topology(Topology::Torus);
+        let err = SparseUniverse::from_universe(&torus).unwrap_err();
+        assert_eq!(UnboundedError::Topology(Topology::Torus), err);
+        assert_eq!(
+            "topology torus cannot be used on an unbounded plane",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn live_cells_in_a_viewport_are_relative_to_its_corner() {
+        let universe = glider();
+        let mut live = universe.live_cells_in(&Viewport::new(1, 1, 5, 5));
+        live.sort();
+        assert_eq!(vec![(0, 1), (1, 0), (1, 1)], live);
+    }
+}
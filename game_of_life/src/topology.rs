@@ -0,0 +1,202 @@
+use crate::game_of_life::Cell;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// How a finite `height * width` grid is glued together at its edges, i.e.
+/// what a cell just past the border sees as its neighbor.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Topology {
+    /// Everything past the edge is permanently dead.
+    #[default]
+    Bounded,
+    /// Top wraps to bottom and left wraps to right.
+    Torus,
+    /// Left wraps to right; crossing top/bottom also mirrors the column.
+    KleinBottle,
+    /// Crossing either pair of edges mirrors the other coordinate
+    /// (the real projective plane).
+    CrossSurface,
+    /// Everything past the edge is permanently alive.
+    AliveEdge,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 5] = [
+        Self::Bounded,
+        Self::Torus,
+        Self::KleinBottle,
+        Self::CrossSurface,
+        Self::AliveEdge,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Bounded => "bounded",
+            Self::Torus => "torus",
+            Self::KleinBottle => "klein-bottle",
+            Self::CrossSurface => "cross-surface",
+            Self::AliveEdge => "alive-edge",
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseTopologyError(String);
+
+impl fmt::Display for ParseTopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = Topology::ALL.iter().map(Topology::name).collect();
+        write!(
+            f,
+            "unknown topology '{}', expected one of {}",
+            self.0,
+            names.join(", ")
+        )
+    }
+}
+
+impl Error for ParseTopologyError {}
+
+impl FromStr for Topology {
+    type Err = ParseTopologyError;
+
+    /// Accepts the names `Display` writes, ignoring case and treating `_`
+    /// like `-`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase().replace('_', "-");
+        Self::ALL
+            .into_iter()
+            .find(|topology| topology.name() == name)
+            .ok_or_else(|| ParseTopologyError(s.to_string()))
+    }
+}
+
+/// Where a (possibly off-grid) coordinate ends up under a `Topology`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Location {
+    Inside(usize, usize),
+    Outside(Cell),
+}
+
+impl Topology {
+    pub fn locate(&self, row: isize, col: isize, height: usize, width: usize) -> Location {
+        let (h, w) = (height as isize, width as isize);
+        if (0..h).contains(&row) && (0..w).contains(&col) {
+            return Location::Inside(row as usize, col as usize);
+        }
+
+        let (row_wraps, col_wraps) = (row.div_euclid(h), col.div_euclid(w));
+        let (mut r, mut c) = (row.rem_euclid(h), col.rem_euclid(w));
+        let (flip_col, flip_row) = match self {
+            Self::Bounded => return Location::Outside(Cell::Dead),
+            Self::AliveEdge => return Location::Outside(Cell::Alive),
+            Self::Torus => (false, false),
+            Self::KleinBottle => (row_wraps % 2 != 0, false),
+            Self::CrossSurface => (row_wraps % 2 != 0, col_wraps % 2 != 0),
+        };
+        if flip_col {
+            c = w - 1 - c;
+        }
+        if flip_row {
+            r = h - 1 - r;
+        }
+        Location::Inside(r as usize, c as usize)
+    }
+}
+
+#[cfg(test)]
+mod topology_tests {
+    use super::*;
+
+    const H: usize = 4;
+    const W: usize = 6;
+
+    #[test]
+    fn on_grid_coordinates_are_unchanged_by_every_topology() {
+        for topology in [
+            Topology::Bounded,
+            Topology::Torus,
+            Topology::KleinBottle,
+            Topology::CrossSurface,
+            Topology::AliveEdge,
+        ] {
+            assert_eq!(Location::Inside(2, 3), topology.locate(2, 3, H, W));
+        }
+    }
+
+    #[test]
+    fn bounded_edges_are_dead() {
+        assert_eq!(
+            Location::Outside(Cell::Dead),
+            Topology::Bounded.locate(-1, 0, H, W)
+        );
+        assert_eq!(
+            Location::Outside(Cell::Dead),
+            Topology::Bounded.locate(0, 6, H, W)
+        );
+    }
+
+    #[test]
+    fn alive_edges_are_alive() {
+        assert_eq!(
+            Location::Outside(Cell::Alive),
+            Topology::AliveEdge.locate(4, 0, H, W)
+        );
+    }
+
+    #[test]
+    fn a_torus_wraps_both_edges() {
+        assert_eq!(Location::Inside(3, 1), Topology::Torus.locate(-1, 1, H, W));
+        assert_eq!(Location::Inside(1, 0), Topology::Torus.locate(1, 6, H, W));
+        assert_eq!(Location::Inside(0, 5), Topology::Torus.locate(4, -1, H, W));
+    }
+
+    #[test]
+    fn a_klein_bottle_mirrors_columns_across_the_top_and_bottom() {
+        assert_eq!(
+            Location::Inside(3, 4),
+            Topology::KleinBottle.locate(-1, 1, H, W)
+        );
+        assert_eq!(
+            Location::Inside(1, 0),
+            Topology::KleinBottle.locate(1, 6, H, W)
+        );
+    }
+
+    #[test]
+    fn a_cross_surface_mirrors_across_both_edges() {
+        assert_eq!(
+            Location::Inside(3, 4),
+            Topology::CrossSurface.locate(-1, 1, H, W)
+        );
+        assert_eq!(
+            Location::Inside(2, 0),
+            Topology::CrossSurface.locate(1, 6, H, W)
+        );
+    }
+
+    #[test]
+    fn topologies_round_trip_through_their_names() {
+        for topology in Topology::ALL {
+            assert_eq!(Ok(topology), topology.to_string().parse());
+        }
+        assert_eq!(Ok(Topology::KleinBottle), "Klein_Bottle".parse());
+    }
+
+    #[test]
+    fn unknown_topology_names_are_an_error() {
+        let err = "sphere".parse::<Topology>().unwrap_err();
+        assert_eq!(
+            "unknown topology 'sphere', expected one of \
+             bounded, torus, klein-bottle, cross-surface, alive-edge",
+            err.to_string()
+        );
+    }
+}